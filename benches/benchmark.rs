@@ -0,0 +1,35 @@
+use collatz_m4m6::packed;
+use collatz_m4m6::pair_number::PairNumber;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_bigint::BigUint;
+use num_traits::One;
+
+/// start から 1 に着くまでの奇数軌道（各ステップの入力）
+fn orbit(start: &BigUint) -> Vec<PairNumber> {
+    let mut values = Vec::new();
+    let mut pn = PairNumber::from_biguint(start);
+    while !pn.is_one() {
+        values.push(pn.clone());
+        let r = packed::packed_step_3n1_opt(&pn, false);
+        pn = PairNumber::from_packed(r.new_m4, r.new_m6, r.new_pair_count);
+    }
+    values
+}
+
+/// 2^200 付近の軌道を 1 周する: 全ワード走査の packed_step_3n1_opt と小さい数向けの packed_step_3n1_small
+fn bench_packed_step_3n1_small(c: &mut Criterion) {
+    let orbit = orbit(&((BigUint::one() << 200u32) + 27u32));
+    let mut group = c.benchmark_group("packed_step_3n1 orbit 2^200");
+    for collect_gpk in [false, true] {
+        group.bench_with_input(BenchmarkId::new("opt", collect_gpk), &collect_gpk, |b, &gpk| {
+            b.iter(|| orbit.iter().map(|pn| packed::packed_step_3n1_opt(black_box(pn), gpk).d).sum::<u64>())
+        });
+        group.bench_with_input(BenchmarkId::new("small", collect_gpk), &collect_gpk, |b, &gpk| {
+            b.iter(|| orbit.iter().map(|pn| packed::packed_step_3n1_small(black_box(pn), gpk).d).sum::<u64>())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_packed_step_3n1_small);
+criterion_main!(benches);
@@ -4,6 +4,7 @@ use collatz_m4m6::*;
 use eframe::egui;
 use egui_plot::{Bar, BarChart, Plot};
 use num_bigint::BigUint;
+use num_traits::ToPrimitive;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write as IoWrite};
 use std::path::PathBuf;
@@ -97,6 +98,29 @@ fn short_n(n: &str) -> String {
     }
 }
 
+/// [start, end] の奇数の個数（f64 近似）
+fn odd_count_f64(start: &BigUint, end: &BigUint) -> f64 {
+    odd_count(start, end).to_f64().unwrap_or(f64::INFINITY)
+}
+
+/// 秒数を「3.2時間」「1.5年」のような読みやすい表記にする
+fn format_duration_s(secs: f64) -> String {
+    if secs < 60.0 {
+        format!("{:.0}秒", secs)
+    } else if secs < 3600.0 {
+        format!("{:.1}分", secs / 60.0)
+    } else if secs < 86400.0 {
+        format!("{:.1}時間", secs / 3600.0)
+    } else if secs < 86400.0 * 365.0 {
+        format!("{:.1}日", secs / 86400.0)
+    } else {
+        format!("{:.3e}年", secs / (86400.0 * 365.0))
+    }
+}
+
+/// 見積もり所要時間がこれを超える検証は開始前に確認する（秒）
+const VERIFY_CONFIRM_THRESHOLD_S: f64 = 3600.0;
+
 fn gpk_to_str(info: &GpkInfo) -> String {
     info.gpk_string(info.active_pairs)
 }
@@ -138,10 +162,20 @@ struct SingleTraceState {
 struct RangeState {
     running: bool,
     done: u64,
-    total: u64,
+    /// 検証する奇数の総数（odd_count）
+    total: BigUint,
+    /// 進捗の割合（progress_fraction）
+    fraction: f64,
     nps: f64,
     elapsed_s: f64,
     result: Option<VerifyResultDisplay>,
+    /// 所要時間の見積もり中（ワーカースレッドで標本を検証している）
+    estimating: bool,
+    /// 終わった見積もり (見積もった設定, 所要時間（秒）)。UI スレッドが取り出して確認ダイアログか
+    /// 開始に回し、検証はこの設定のまま走らせる（開始後に入力欄を書き換えても見積もりとずれない）
+    estimate: Option<(VerifyConfig, f64)>,
+    /// 設定エラーで検証を始められなかったときのメッセージ
+    error: Option<String>,
 }
 
 struct VerifyResultDisplay {
@@ -181,6 +215,8 @@ struct CollatzApp {
     range_end_input: String,
     range_state: Arc<Mutex<RangeState>>,
     range_cancel: Arc<AtomicBool>,
+    /// 確認待ちの長時間検証 (見積もった設定, 見積もり所要時間（秒）)
+    range_confirm: Option<(VerifyConfig, f64)>,
     // 解析タブ: ログビューア
     log_files: Vec<String>,
     selected_log: Option<usize>,
@@ -206,9 +242,11 @@ impl Default for CollatzApp {
             range_start_input: "3".to_string(),
             range_end_input: "9999999".to_string(),
             range_state: Arc::new(Mutex::new(RangeState {
-                running: false, done: 0, total: 0, nps: 0.0, elapsed_s: 0.0, result: None,
+                running: false, done: 0, total: BigUint::ZERO, fraction: 0.0, nps: 0.0, elapsed_s: 0.0, result: None,
+                estimating: false, estimate: None, error: None,
             })),
             range_cancel: Arc::new(AtomicBool::new(false)),
+            range_confirm: None,
             log_files: Vec::new(),
             selected_log: None,
             loaded_log: None,
@@ -220,7 +258,10 @@ impl eframe::App for CollatzApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         {
             let trace_running = self.single_trace_state.lock().unwrap().running;
-            let range_running = self.range_state.lock().unwrap().running;
+            let range_running = {
+                let s = self.range_state.lock().unwrap();
+                s.running || s.estimating
+            };
             if trace_running || range_running {
                 ctx.request_repaint();
             }
@@ -251,7 +292,8 @@ impl eframe::App for CollatzApp {
                 }
                 ui.separator();
                 ui.label("max_steps:");
-                ui.add(egui::TextEdit::singleline(&mut self.max_steps_input).desired_width(60.0));
+                ui.add(egui::TextEdit::singleline(&mut self.max_steps_input).desired_width(60.0))
+                    .on_hover_text("数値か auto（シードのビット長に比例させる）");
                 ui.checkbox(&mut self.collect_gpk, "GPK統計");
                 ui.checkbox(&mut self.use_phase1, "u128 Phase1");
                 ui.checkbox(&mut self.use_stopping_time, "停止時間判定");
@@ -379,7 +421,19 @@ impl CollatzApp {
 
     // ─── 区間解析 ──────────────────────────────
     fn ui_range(&mut self, ui: &mut egui::Ui) {
-        let running = self.range_state.lock().unwrap().running;
+        // ワーカースレッドの見積もりが終わっていれば、確認ダイアログに回すか検証を始める
+        let estimate = self.range_state.lock().unwrap().estimate.take();
+        if let Some((config, est_s)) = estimate {
+            if est_s > VERIFY_CONFIRM_THRESHOLD_S {
+                self.range_confirm = Some((config, est_s));
+            } else {
+                self.launch_verify(config);
+            }
+        }
+        let (running, estimating) = {
+            let s = self.range_state.lock().unwrap();
+            (s.running, s.estimating)
+        };
 
         ui.horizontal(|ui| {
             ui.label("開始:");
@@ -393,7 +447,7 @@ impl CollatzApp {
                 ui.colored_label(egui::Color32::GRAY, format!("≈2^{}", approx_bits));
             }
             if !running {
-                if ui.button("検証開始").clicked() {
+                if ui.add_enabled(self.range_confirm.is_none() && !estimating, egui::Button::new("検証開始")).clicked() {
                     self.start_verify();
                 }
             } else {
@@ -403,13 +457,55 @@ impl CollatzApp {
             }
         });
 
+        // 区間サイズのプレビュー（入力検証を兼ねる）
+        match (BigUint::from_str(&self.range_start_input), BigUint::from_str(&self.range_end_input)) {
+            (Ok(start), Ok(end)) => {
+                ui.colored_label(egui::Color32::GRAY, format!("奇数の個数: ≈{:.3e}", odd_count_f64(&start, &end)));
+            }
+            _ => {
+                ui.colored_label(egui::Color32::RED, "開始・終了には非負の整数を入力してください");
+            }
+        }
+
+        // 長時間になりそうな検証の確認ダイアログ
+        if let Some(&(_, est_s)) = self.range_confirm.as_ref() {
+            let mut decision = None;
+            egui::Window::new("長時間の検証")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ui.ctx(), |ui| {
+                    ui.label(format!("見積もり所要時間: {}", format_duration_s(est_s)));
+                    ui.label("このまま開始しますか？");
+                    ui.horizontal(|ui| {
+                        if ui.button("開始").clicked() {
+                            decision = Some(true);
+                        }
+                        if ui.button("キャンセル").clicked() {
+                            decision = Some(false);
+                        }
+                    });
+                });
+            if let Some(go) = decision {
+                if let Some((config, _)) = self.range_confirm.take() {
+                    if go {
+                        self.launch_verify(config);
+                    }
+                }
+            }
+        }
+
         ui.separator();
 
         let state = self.range_state.lock().unwrap();
 
-        if state.running && state.total > 0 {
-            let pct = state.done as f32 / state.total as f32;
-            ui.add(egui::ProgressBar::new(pct).text(format!(
+        if let Some(ref error) = state.error {
+            ui.colored_label(egui::Color32::RED, format!("検証を開始できません: {}", error));
+        }
+
+        if state.running && state.total > BigUint::ZERO {
+            let pct = state.fraction;
+            ui.add(egui::ProgressBar::new(pct as f32).text(format!(
                 "{}/{} ({:.1}%) | {:.0} nums/s | {:.1}s",
                 state.done, state.total, pct * 100.0, state.nps, state.elapsed_s
             )));
@@ -670,6 +766,11 @@ impl CollatzApp {
         });
     }
 
+    /// max_steps 入力欄の値（数値か auto。読めなければ 10000）
+    fn max_steps(&self) -> MaxSteps {
+        self.max_steps_input.parse().unwrap_or(MaxSteps::Fixed(10_000))
+    }
+
     fn start_trace(&mut self) {
         let n = match BigUint::from_str(&self.single_n_input) {
             Ok(n) => n,
@@ -688,7 +789,7 @@ impl CollatzApp {
         }
         let state = self.single_trace_state.clone();
         let cancel = self.single_cancel.clone();
-        let max_steps = self.max_steps_input.parse::<u64>().unwrap_or(10_000);
+        let max_steps = self.max_steps().for_bits(n.bits());
 
         thread::spawn(move || {
             // パニック時も running = false を保証するガード
@@ -744,23 +845,52 @@ impl CollatzApp {
         });
     }
 
+    /// 検証開始ボタン: 先頭の数千個で速度をワーカースレッドで測る。結果は ui_range が取り出し、
+    /// 所要時間が閾値を超えそうなら確認に回す
     fn start_verify(&mut self) {
         let start = match BigUint::from_str(&self.range_start_input) { Ok(n) => n, Err(_) => return };
         let end = match BigUint::from_str(&self.range_end_input) { Ok(n) => n, Err(_) => return };
-        let start_str = self.range_start_input.clone();
-        let end_str = self.range_end_input.clone();
-        let x = self.x_val;
-        let collect_gpk = self.collect_gpk;
-        let use_phase1 = self.use_phase1;
-        let use_stopping_time = self.use_stopping_time;
+        let max_steps = self.max_steps();
+        let config = VerifyConfig {
+            collect_gpk: self.collect_gpk,
+            use_phase1: self.use_phase1,
+            use_stopping_time: self.use_stopping_time,
+            ..VerifyConfig::new(start, end, self.x_val, max_steps)
+        };
+        // 大きな数は1個あたりが重いので標本を減らす
+        let sample = if config.start.bits() > 128 { 100 } else { 2000 };
+        self.range_state.lock().unwrap().estimating = true;
+        let state = self.range_state.clone();
+
+        thread::spawn(move || {
+            // パニック時も estimating = false を保証するガード
+            struct EstimateGuard(Arc<Mutex<RangeState>>);
+            impl Drop for EstimateGuard {
+                fn drop(&mut self) {
+                    if let Ok(mut s) = self.0.lock() {
+                        s.estimating = false;
+                    }
+                }
+            }
+            let _guard = EstimateGuard(state.clone());
+
+            let nps = estimate_throughput(&config, sample);
+            let est_s = odd_count_f64(&config.start, &config.end) / nps;
+            state.lock().unwrap().estimate = Some((config, est_s));
+        });
+    }
+
+    /// start_verify が見積もった設定 config で検証を始める（入力欄は読み直さない）
+    fn launch_verify(&mut self, config: VerifyConfig) {
+        let start_str = config.start.to_string();
+        let end_str = config.end.to_string();
         self.range_cancel.store(false, Ordering::Relaxed);
         {
             let mut s = self.range_state.lock().unwrap();
-            s.running = true; s.done = 0; s.total = 0; s.nps = 0.0; s.elapsed_s = 0.0; s.result = None;
+            s.running = true; s.done = 0; s.total = BigUint::ZERO; s.fraction = 0.0; s.nps = 0.0; s.elapsed_s = 0.0; s.result = None; s.error = None;
         }
         let state = self.range_state.clone();
         let cancel = self.range_cancel.clone();
-        let max_steps = self.max_steps_input.parse::<u64>().unwrap_or(10_000);
 
         thread::spawn(move || {
             // パニック時も running = false を保証するガード
@@ -775,25 +905,37 @@ impl CollatzApp {
             }
             let _guard = RunGuard(state_guard);
 
+            // 進捗の割合はコールバックの u64 の総数（2^64 個で飽和）ではなく正確な総数から出す
+            let total_odd = odd_count(&config.start, &config.end);
             let timer = Instant::now();
             let state_cb = state.clone();
             let last_update = Mutex::new(Instant::now());
-            let result = verify_range_parallel_cancellable(&start, &end, x, max_steps, collect_gpk, use_phase1, use_stopping_time, &cancel, |done, total| {
+            let result = try_verify_range_with_progress(&config, &cancel, |done, _| {
                 let now = Instant::now();
                 if let Ok(mut lu) = last_update.try_lock() {
                     if now.duration_since(*lu).as_millis() >= 200 {
                         let elapsed = timer.elapsed();
                         let mut s = state_cb.lock().unwrap();
-                        s.done = done; s.total = total;
+                        s.done = done; s.total = total_odd.clone(); s.fraction = progress_fraction(done, &total_odd);
                         s.elapsed_s = elapsed.as_secs_f64();
                         s.nps = if elapsed.as_secs_f64() > 0.0 { done as f64 / elapsed.as_secs_f64() } else { 0.0 };
                         *lu = now;
                     }
                 }
             });
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    state.lock().unwrap().error = Some(e.to_string());
+                    return;
+                }
+            };
             let elapsed = timer.elapsed();
             let cancelled = cancel.load(Ordering::Relaxed);
-            let save_path = save_verify_log(&start_str, &end_str, x, max_steps, collect_gpk, use_phase1, use_stopping_time, &result, cancelled, elapsed);
+            let save_path = save_verify_log(
+                &start_str, &end_str, config.x, config.max_steps, config.collect_gpk, config.use_phase1, config.use_stopping_time,
+                &result, cancelled, elapsed,
+            );
             let mut s = state.lock().unwrap();
             s.running = false;
             s.result = Some(VerifyResultDisplay {
@@ -911,7 +1053,7 @@ fn save_trace_log(
 }
 
 fn save_verify_log(
-    start_str: &str, end_str: &str, x: u64, max_steps: u64, collect_gpk: bool, use_phase1: bool, use_stopping_time: bool,
+    start_str: &str, end_str: &str, x: u64, max_steps: MaxSteps, collect_gpk: bool, use_phase1: bool, use_stopping_time: bool,
     result: &VerifyResult, cancelled: bool, elapsed: std::time::Duration,
 ) -> Option<String> {
     let ts = timestamp();
@@ -14,7 +14,11 @@ pub mod scan;
 pub mod trajectory;
 pub mod verify;
 
-pub use pair_number::PairNumber;
-pub use scan::{collatz_step, collatz_step_3n1, collatz_step_5n1, Gpk, GpkInfo, GpkStats, StepResult};
-pub use trajectory::{stopping_time, stopping_time_with_gpk, stopping_time_u64_fast, trace_trajectory, trace_trajectory_with_callback, trace_trajectory_cancellable, words_to_bits_msb, predicate_bits_msb, PREDICATE_NAMES, PairStep, TrajectoryResult};
-pub use verify::{verify_range, verify_range_parallel, verify_range_parallel_cancellable, VerifyResult};
+pub use pair_number::{AlignedPairWords, PackedView, PairNumber, PredicateStream};
+#[cfg(feature = "u32-words")]
+pub use pair_number::PairNumber32;
+pub use scan::{collatz_step, collatz_step_1n1, collatz_step_general, collatz_step_3n1, collatz_step_5n1, collatz_step_for_x, multi_x_step, fused_two_step_3n1, t_inverse_3n1, step_navigable, NavStep, even_trajectory, exchange_stream_u64, collatz_step_trace, estimate_next_bitlen, gpk_rle_decode, verify_theorem_5_1, pair_taxonomy, taxonomy_histogram, Gpk, GpkInfo, PairClass, GpkStats, PairTraceRow, StepResult, TRACE_MAX_PAIRS};
+pub use trajectory::{stopping_time, run_until, stopping_time_with_gpk, stopping_time_u64_fast, trace_trajectory, trace_trajectory_with_callback, trace_trajectory_cancellable, trace_trajectory_decimated, trace_trajectory_bucketed, trace_trajectory_peak, max_stopping_time_seed, stopping_time_below, stopping_time_or_cycle, stopping_time_or_growth, glide_and_total, orbits_to_dot, DOT_MAX_EDGES, trace_segmented, replay, ReplayError, SegmentTransform, SegmentedTrace, StoppingOutcome, StopReason, words_to_bits_msb, predicate_bits_msb, PREDICATE_NAMES, PairStep, PeakTrajectoryResult, TrajectoryResult, OrbitSummary, AggregateReport, aggregate_summaries};
+pub use verify::{verify_range, verify_range_parallel, verify_range_parallel_cancellable, verify_range_parallel_rich, verify_range_parallel_balanced, ProgressInfo, REPORT_EVERY_PARALLEL, REPORT_EVERY_SEQUENTIAL, verify_range_small, verify_seeds, SMALL_RANGE_LIMIT, verify_range_spawn, estimate_throughput, VerifyHandle, VerifySnapshot, verify_range_with_cycles, verify_range_inductive, verify_range_with_growth_limit, verify_range_glide_and_total, verify_range_with, OddStep, XnPlusOne, analyze_seed, SeedAnalysis, convergence_certificate, verify_certificate, write_certificate, verify_range_ndjson, NDJSON_FLUSH_EVERY, verify_range_batches, Certificate, BigUintRangeChunker, VerifyResult, Strategy, VerifyConfig, MaxSteps, AUTO_STEPS_PER_BIT, AUTO_MIN_STEPS, VerifyError, try_verify_range, try_verify_range_with_progress, odd_count, progress_fraction};
+#[cfg(feature = "arrow")]
+pub use verify::write_verify_arrow;
@@ -5,6 +5,8 @@ use std::fs::File;
 use std::io::{BufWriter, Write as IoWrite};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
 fn check_avx2() {
@@ -22,7 +24,7 @@ fn print_usage() {
     eprintln!("使い方:");
     eprintln!("  collatz-m4m6 step <n> [x]              1ステップ計算 (デフォルト x=3)");
     eprintln!("  collatz-m4m6 trace <n> [x]             軌道追跡 (1に到達するまで)");
-    eprintln!("  collatz-m4m6 verify <start> <end> [x]  範囲検証 (停止時間法)");
+    eprintln!("  collatz-m4m6 verify <start> <end> [x] [max_steps|auto]  範囲検証 (停止時間法)");
     eprintln!();
     eprintln!("結果は自動的に output/ フォルダに保存されます。");
     eprintln!();
@@ -31,6 +33,7 @@ fn print_usage() {
     eprintln!("  collatz-m4m6 step 27 5           5*27+1 の1ステップ");
     eprintln!("  collatz-m4m6 trace 27            27から1までの軌道");
     eprintln!("  collatz-m4m6 verify 3 9999       3〜9999の全奇数を検証");
+    eprintln!("  collatz-m4m6 verify 3 9999 3 auto  ステップ上限をシードのビット長に比例させて検証");
 }
 
 fn output_dir() -> PathBuf {
@@ -325,32 +328,61 @@ fn cmd_trace(args: &[String]) {
 
 fn cmd_verify(args: &[String]) {
     if args.len() < 2 {
-        eprintln!("使い方: collatz-m4m6 verify <start> <end> [x]");
+        eprintln!("使い方: collatz-m4m6 verify <start> <end> [x] [max_steps|auto]");
         return;
     }
 
     let start = parse_n(&args[0]);
     let end = parse_n(&args[1]);
     let x = parse_x(&args[2..], 3);
-    let max_steps = 100_000;
+    let max_steps = match args.get(3) {
+        Some(s) => s.parse::<MaxSteps>().unwrap_or_else(|_| {
+            eprintln!("max_steps を解析できません（数値か auto）: {}", s);
+            std::process::exit(1);
+        }),
+        None => MaxSteps::Fixed(100_000),
+    };
 
     let num_threads = rayon::current_num_threads();
     println!("範囲検証 (層2: GPK統計付き): [{}, {}], x={}", start, end, x);
-    println!("(停止時間法、最大 {} ステップ/数、{}スレッド並列)", max_steps, num_threads);
+    match max_steps {
+        MaxSteps::Fixed(steps) => println!("(停止時間法、最大 {} ステップ/数、{}スレッド並列)", steps, num_threads),
+        MaxSteps::Auto => println!(
+            "(停止時間法、最大 max({}×ビット長, {}) ステップ/数、{}スレッド並列)",
+            AUTO_STEPS_PER_BIT, AUTO_MIN_STEPS, num_threads
+        ),
+    }
+    println!("(Enter で中断して途中結果を表示・保存)");
     println!();
 
+    // Enter が押されたら中断する（stdin が閉じているだけなら何もしない）
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).is_ok_and(|read| read > 0) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    // 進捗の割合はコールバックの u64 の総数（2^64 個で飽和）ではなく正確な総数から出す
+    let total_odd = odd_count(&start, &end);
     let timer = Instant::now();
     let last_print = std::sync::Mutex::new(Instant::now());
-    let result = verify_range_parallel(&start, &end, x, max_steps, |done, total| {
+    let config = VerifyConfig::new(start.clone(), end.clone(), x, max_steps);
+    let result = try_verify_range_with_progress(&config, &cancel, |done, total| {
         if total > 0 {
             let now = Instant::now();
             if let Ok(mut lp) = last_print.try_lock() {
                 if now.duration_since(*lp).as_millis() >= 500 {
                     let elapsed = timer.elapsed();
-                    let pct = done as f64 / total as f64 * 100.0;
+                    let fraction = progress_fraction(done, &total_odd);
+                    let pct = fraction * 100.0;
                     let nps = done as f64 / elapsed.as_secs_f64();
                     let remaining = if done > 0 {
-                        let eta_s = (total - done) as f64 / nps;
+                        let eta_s = elapsed.as_secs_f64() * (1.0 - fraction) / fraction;
                         if eta_s > 3600.0 {
                             format!("{:.1}h", eta_s / 3600.0)
                         } else if eta_s > 60.0 {
@@ -363,21 +395,33 @@ fn cmd_verify(args: &[String]) {
                     };
                     eprint!(
                         "\x1b[2K\r  [{:.1}s] {}/{} ({:.1}%) | {:.0} nums/s | 残り約{}",
-                        elapsed.as_secs_f64(), done, total, pct, nps, remaining
+                        elapsed.as_secs_f64(), done, total_odd, pct, nps, remaining
                     );
                     *lp = now;
                 }
             }
         }
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("エラー: {}", e);
+        std::process::exit(1);
     });
     let elapsed = timer.elapsed();
+    let cancelled = cancel.load(Ordering::Relaxed);
 
     eprintln!();
     println!();
     println!("--- 結果 ---");
+    if cancelled {
+        println!("(中断: 途中までの結果)");
+    }
     println!("検証した奇数の数    = {}", result.total_checked);
     println!("全て収束            = {}", if result.all_converged { "はい" } else { "いいえ" });
     println!("最大停止時間        = {} (n={})", result.max_stopping_time, result.max_stopping_time_number);
+    println!("軌道の最大ビット長  = {}", result.max_intermediate_bits);
+    println!("1 に到達            = {}", result.reached_one_count);
+    println!("開始値未満に降下    = {}", result.dropped_below_count);
+    println!("d 奇数/偶数ステップ = {} / {}", result.odd_d_steps(), result.even_d_steps());
 
     // GPK 統計
     let gs = &result.gpk_stats;
@@ -415,10 +459,18 @@ fn cmd_verify(args: &[String]) {
         writeln!(f, "x = {}", x).ok();
         writeln!(f, "max_steps_per_number = {}", max_steps).ok();
         writeln!(f, "threads = {}", num_threads).ok();
+        if cancelled {
+            writeln!(f, "cancelled = true").ok();
+        }
         writeln!(f, "total_checked = {}", result.total_checked).ok();
         writeln!(f, "all_converged = {}", result.all_converged).ok();
         writeln!(f, "max_stopping_time = {}", result.max_stopping_time).ok();
         writeln!(f, "max_stopping_time_number = {}", result.max_stopping_time_number).ok();
+        writeln!(f, "max_intermediate_bits = {}", result.max_intermediate_bits).ok();
+        writeln!(f, "reached_one_count = {}", result.reached_one_count).ok();
+        writeln!(f, "dropped_below_count = {}", result.dropped_below_count).ok();
+        writeln!(f, "odd_d_steps = {}", result.odd_d_steps()).ok();
+        writeln!(f, "even_d_steps = {}", result.even_d_steps()).ok();
         writeln!(f, "failures = {}", result.failures.len()).ok();
         writeln!(f, "").ok();
         writeln!(f, "# GPK Statistics").ok();
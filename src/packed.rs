@@ -12,8 +12,13 @@
 //! Kogge-Stone でワード内64ペア分のキャリーを並列解決し、
 //! ワード間キャリーは逐次伝播する。
 
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
 use crate::pair_number::PairNumber;
+#[cfg(feature = "u32-words")]
+use crate::pair_number::PairNumber32;
 use crate::postprocess;
+use crate::scan::{pair_gpk, Gpk};
 
 /// パックドスキャンの結果
 #[derive(Debug, Clone)]
@@ -31,18 +36,74 @@ pub struct PackedStepResult {
     pub p_masks: Vec<u64>,
 }
 
+/// パックドワード型（u64 / u32）。
+/// 走査のワード単位処理を両方の幅で共有するための最小限の演算集合。
+pub trait PackedWord:
+    Copy
+    + Eq
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+{
+    /// ワードあたりのペア数
+    const BITS: u32;
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn trailing_zeros(self) -> u32;
+    fn leading_zeros(self) -> u32;
+}
+
+impl PackedWord for u64 {
+    const BITS: u32 = 64;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = u64::MAX;
+
+    fn trailing_zeros(self) -> u32 {
+        u64::trailing_zeros(self)
+    }
+    fn leading_zeros(self) -> u32 {
+        u64::leading_zeros(self)
+    }
+}
+
+impl PackedWord for u32 {
+    const BITS: u32 = 32;
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = u32::MAX;
+
+    fn trailing_zeros(self) -> u32 {
+        u32::trailing_zeros(self)
+    }
+    fn leading_zeros(self) -> u32 {
+        u32::leading_zeros(self)
+    }
+}
+
+/// 下位 n ビットが 1 のマスク（n < W::BITS）
+#[inline]
+fn low_mask<W: PackedWord>(n: u32) -> W {
+    !(W::MAX << n)
+}
+
 /// Kogge-Stone 並列プリフィックススキャン（ワード内）。
 ///
-/// 入力: generate (g), propagate (p) の64ペア分のビットマスク
+/// 入力: generate (g), propagate (p) のワード内ペア分のビットマスク
 /// 出力: プリフィックス適用後の (g_prefix, p_prefix)
 ///
 /// g_prefix[i] = 1 は「位置 0..=i のどこかで生成され、
 /// そこから位置 i まで伝播が途切れなかった」ことを意味する。
 /// これにより carry_out[i] = g_prefix[i] | (p_prefix[i] & carry_in_bit0)
 ///
-/// 6イテレーションで64ビット分のプリフィックスを並列解決。
+/// log2(W::BITS) イテレーション（u64 で6回）でワード分のプリフィックスを並列解決。
 #[inline]
-fn kogge_stone_prefix(mut g: u64, mut p: u64) -> (u64, u64) {
+fn kogge_stone_prefix<W: PackedWord>(mut g: W, mut p: W) -> (W, W) {
     // キャリーは低ビット→高ビットに伝播する。
     // ステップ k: 位置 i の (g, p) を位置 i-2^k の (g, p) と合成する。
     // 合成則: (g_hi, p_hi) ∘ (g_lo, p_lo) = (g_hi | (p_hi & g_lo), p_hi & p_lo)
@@ -52,68 +113,69 @@ fn kogge_stone_prefix(mut g: u64, mut p: u64) -> (u64, u64) {
     // (g, p) の単位元は (0, 1) なので、シフトで空いた下位ビットは
     // g_shifted は 0（左シフトのデフォルト）、
     // p_shifted は 1 にパディングする必要がある。
-    for shift in [1u32, 2, 4, 8, 16, 32] {
+    let mut shift = 1u32;
+    while shift < W::BITS {
         let g_shifted = g << shift;  // 位置 i-shift の generate を位置 i に配置
         // p_shifted の下位 shift ビットを 1 で埋める（単位元のp=1）
-        let p_shifted = (p << shift) | ((1u64 << shift) - 1);
+        let p_shifted = (p << shift) | low_mask::<W>(shift);
         g = g | (p & g_shifted);
         p = p & p_shifted;
+        shift *= 2;
     }
     (g, p)
 }
 
 /// m4/m6 ワードから指定オフセットでシフトされたワードを抽出。
 ///
-/// pair_index `start` から64ペア分を抽出する。
+/// pair_index `start` から1ワード分（W::BITS ペア）を抽出する。
 /// start < 0 の場合、下位ビットは0パディング。
-/// start >= pair_count の場合、全ビット0。
+/// start >= pair_count の場合、全ビット0。pair_count 以上のペアは常に 0 として読む。
+/// パックド走査・GPK 集計の参照窓はすべてこの関数で取り出す。
 #[inline]
-fn extract_window(words: &[u64], pair_count: usize, start: isize) -> u64 {
+pub fn extract_window<W: PackedWord>(words: &[W], pair_count: usize, start: isize) -> W {
+    let bits = W::BITS as usize;
     if start >= pair_count as isize {
-        return 0;
+        return W::ZERO;
     }
 
     if start < 0 {
         let abs_start = (-start) as u32;
-        if abs_start >= 64 {
-            return 0;
+        if abs_start >= W::BITS {
+            return W::ZERO;
         }
         // 下位 abs_start ビットが0、残りはワード0からの値
-        let w0 = if words.is_empty() { 0 } else { words[0] };
+        let w0 = if words.is_empty() { W::ZERO } else { words[0] };
         let mut val = w0 << abs_start;
         // 範囲外ビットをマスク
         let effective_end = pair_count as isize - start; // 有効ビット数の上限
-        if effective_end < 64 {
-            let remaining = effective_end as usize;
-            if remaining < 64 {
-                val &= (1u64 << remaining) - 1;
-            }
+        if effective_end < bits as isize {
+            val = val & low_mask::<W>(effective_end as u32);
         }
         return val;
     }
 
     let start_u = start as usize;
-    let word_idx = start_u / 64;
-    let bit_off = start_u % 64;
+    let word_idx = start_u / bits;
+    let bit_off = (start_u % bits) as u32;
 
     if bit_off == 0 {
         if word_idx < words.len() {
             let mut val = words[word_idx];
             let remaining = pair_count.saturating_sub(start_u);
-            if remaining < 64 {
-                val &= (1u64 << remaining) - 1;
+            if remaining < bits {
+                val = val & low_mask::<W>(remaining as u32);
             }
             val
         } else {
-            0
+            W::ZERO
         }
     } else {
-        let lo = if word_idx < words.len() { words[word_idx] } else { 0 };
-        let hi = if word_idx + 1 < words.len() { words[word_idx + 1] } else { 0 };
-        let mut val = (lo >> bit_off) | (hi << (64 - bit_off));
+        let lo = if word_idx < words.len() { words[word_idx] } else { W::ZERO };
+        let hi = if word_idx + 1 < words.len() { words[word_idx + 1] } else { W::ZERO };
+        let mut val = (lo >> bit_off) | (hi << (W::BITS - bit_off));
         let remaining = pair_count.saturating_sub(start_u);
-        if remaining < 64 {
-            val &= (1u64 << remaining) - 1;
+        if remaining < bits {
+            val = val & low_mask::<W>(remaining as u32);
         }
         val
     }
@@ -121,7 +183,7 @@ fn extract_window(words: &[u64], pair_count: usize, start: isize) -> u64 {
 
 /// majority(a, b, c) = (a & b) | (b & c) | (a & c)
 #[inline]
-fn majority(a: u64, b: u64, c: u64) -> u64 {
+fn majority<W: PackedWord>(a: W, b: W, c: W) -> W {
     (a & b) | (b & c) | (a & c)
 }
 
@@ -142,10 +204,10 @@ fn majority(a: u64, b: u64, c: u64) -> u64 {
 ///   G_out = p_l & q_l,  P_out = p_l ^ q_l  (m4段の GPK)
 ///   G_pair = G_out | (P_out & G_mid)
 ///   P_pair = P_out & P_mid
-fn packed_scan_word(
-    p_r: u64, q_r: u64, p_l: u64, q_l: u64,
-    carry_in: u64,  // 前ワードからの入力キャリー (0 or 1)
-) -> (u64, u64, u64, u64, u64) {
+fn packed_scan_word<W: PackedWord>(
+    p_r: W, q_r: W, p_l: W, q_l: W,
+    carry_in: W,  // 前ワードからの入力キャリー (0 or 1)
+) -> (W, W, W, W, W) {
     // m6段のビット単位GPK
     let g_mid = p_r & q_r;
     let p_mid = p_r ^ q_r;
@@ -163,7 +225,7 @@ fn packed_scan_word(
 
     // carry_after[i] = g_pfx[i] | (p_pfx[i] & carry_in)
     // carry_in はこのワードの最初のペアへの入力キャリー
-    let carry_in_broadcast = if carry_in != 0 { u64::MAX } else { 0 };
+    let carry_in_broadcast = if carry_in != W::ZERO { W::MAX } else { W::ZERO };
     let carry_after = g_pfx | (p_pfx & carry_in_broadcast);
 
     // c_in[i]: ペア i への入力キャリー
@@ -183,11 +245,22 @@ fn packed_scan_word(
     let new_m4 = p_out ^ c_mid;
 
     // 次ワードへのキャリー = carry_after の最上位ビット
-    let carry_out = (carry_after >> 63) & 1;
+    let carry_out = (carry_after >> (W::BITS - 1)) & W::ONE;
 
     (new_m4, new_m6, carry_out, g_pair, p_pair)
 }
 
+/// xn+1 の走査で入力の pair_count に足す出力ペア数（ヘッドルーム）。x-1 は 2 の冪であること。
+/// 導出: n < 4^k, x = 2^s + 1 <= 2^(s+1) より xn + 1 <= 2^(s+1) * 4^k。
+/// 2^(s+1) <= 4^ceil((s+1)/2) <= 4^(ceil(s/2) + 1) なので、
+/// k + ceil(s/2) + 1 ペアあれば xn+1 は必ず収まる（各走査で debug_assert）。x=3, 5 では 2。
+#[inline]
+pub fn extra_pairs_for_x(x: u64) -> usize {
+    let xm1 = x - 1;
+    assert!(xm1.is_power_of_two(), "x-1 must be a power of 2");
+    (xm1.trailing_zeros() as usize).div_ceil(2) + 1
+}
+
 /// x=3 専用パックドスキャン。
 pub fn packed_step_3n1(pn: &PairNumber) -> PackedStepResult {
     packed_step_3n1_opt(pn, true)
@@ -195,11 +268,12 @@ pub fn packed_step_3n1(pn: &PairNumber) -> PackedStepResult {
 
 /// x=3 専用パックドスキャン（GPK収集オプション付き）。
 pub fn packed_step_3n1_opt(pn: &PairNumber, collect_gpk: bool) -> PackedStepResult {
+    debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
     let k = pn.pair_count();
     let m4 = pn.m4_words();
     let m6 = pn.m6_words();
 
-    let out_pairs = k + 2;
+    let out_pairs = k + extra_pairs_for_x(3);
     let out_words = (out_pairs + 63) / 64;
     let gpk_word_count = if collect_gpk { (k + 63) / 64 } else { 0 };
 
@@ -237,6 +311,7 @@ pub fn packed_step_3n1_opt(pn: &PairNumber, collect_gpk: bool) -> PackedStepResu
         carry = c_out;
     }
 
+    debug_assert_within_out_pairs(&new_m4, &new_m6, out_pairs, carry);
     // 最上位ワードの余剰ビットをマスク
     mask_top_bits(&mut new_m4, out_pairs);
     mask_top_bits(&mut new_m6, out_pairs);
@@ -266,6 +341,114 @@ pub fn packed_step_3n1_opt(pn: &PairNumber, collect_gpk: bool) -> PackedStepResu
     }
 }
 
+/// packed_step_3n1_small が受け付ける最大ペア数（4 ワード = 512 ビット）
+pub const SMALL_STEP_MAX_PAIRS: usize = 256;
+
+/// x=3 専用パックドスキャンの小さい数向け版（pair_count <= SMALL_STEP_MAX_PAIRS）。
+/// 出力ワード数 N（1〜5）ごとに固定長配列の版へ振り分けるので、走査ループは展開され、
+/// a_prev の窓も extract_window を介さず隣接ワードのシフトで作る。÷2^d のシフトも
+/// postprocess のビット単位の詰め直しではなく、配列上のワードシフトで行う。
+/// 結果は packed_step_3n1_opt と同じ。step_for_x は x=3 で pair_count が小さければこちらを使う。
+pub fn packed_step_3n1_small(pn: &PairNumber, collect_gpk: bool) -> PackedStepResult {
+    debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
+    let k = pn.pair_count();
+    assert!(k <= SMALL_STEP_MAX_PAIRS, "packed_step_3n1_small requires pair_count <= {}", SMALL_STEP_MAX_PAIRS);
+    match (k + extra_pairs_for_x(3)).div_ceil(64) {
+        1 => packed_step_3n1_words::<1>(pn, collect_gpk),
+        2 => packed_step_3n1_words::<2>(pn, collect_gpk),
+        3 => packed_step_3n1_words::<3>(pn, collect_gpk),
+        4 => packed_step_3n1_words::<4>(pn, collect_gpk),
+        _ => packed_step_3n1_words::<5>(pn, collect_gpk),
+    }
+}
+
+/// packed_step_3n1_small の本体。N は出力ワード数（(pair_count + 2) / 64 の切り上げ）
+fn packed_step_3n1_words<const N: usize>(pn: &PairNumber, collect_gpk: bool) -> PackedStepResult {
+    let k = pn.pair_count();
+    let mut a = [0u64; N];
+    let mut b = [0u64; N];
+    for (w, (&m4w, &m6w)) in pn.m4_words().iter().zip(pn.m6_words()).take(N).enumerate() {
+        let valid = k.saturating_sub(w * 64);
+        let mask = if valid >= 64 { u64::MAX } else { low_mask::<u64>(valid as u32) };
+        a[w] = m4w & mask;
+        b[w] = m6w & mask;
+    }
+
+    let mut sum_m4 = [0u64; N];
+    let mut sum_m6 = [0u64; N];
+    let mut g_pair = [0u64; N];
+    let mut p_pair = [0u64; N];
+    let mut carry = 1u64;
+    let mut a_carry = 0u64;
+    for w in 0..N {
+        // x=3: ref_R(i) = (a[i-1], b[i]), ref_L(i) = (b[i], a[i])
+        let a_prev = (a[w] << 1) | a_carry;
+        a_carry = a[w] >> 63;
+        let (m4w, m6w, c_out, g, p) = packed_scan_word(a_prev, b[w], b[w], a[w], carry);
+        sum_m4[w] = m4w;
+        sum_m6[w] = m6w;
+        g_pair[w] = g;
+        p_pair[w] = p;
+        carry = c_out;
+    }
+    debug_assert_within_out_pairs(&sum_m4, &sum_m6, k + extra_pairs_for_x(3), carry);
+
+    // 末尾ゼロ数 d（ファスナー: bit[2i] = m6[i], bit[2i+1] = m4[i]）。3n+1 >= 4 なのでどこかのワードは非ゼロ
+    let w0 = (0..N).find(|&w| sum_m4[w] | sum_m6[w] != 0).unwrap_or(0);
+    let tz = (sum_m4[w0] | sum_m6[w0]).trailing_zeros();
+    let d = 128 * w0 as u64 + 2 * tz as u64 + ((sum_m6[w0] >> tz) & 1 == 0) as u64;
+
+    // d ビット右シフト: d = 2q なら各列を q ペア、d = 2q + 1 なら m6 ← m4 >> q, m4 ← m6 >> (q + 1)（交換）
+    let q = (d / 2) as usize;
+    let exchanged = d % 2 == 1;
+    let (new_m4, new_m6) = if exchanged {
+        (shr_pairs(&sum_m6, q + 1), shr_pairs(&sum_m4, q))
+    } else {
+        (shr_pairs(&sum_m4, q), shr_pairs(&sum_m6, q))
+    };
+    let top = (0..N).rev().find(|&w| new_m4[w] | new_m6[w] != 0);
+    let new_pair_count = top.map_or(1, |w| 64 * w + 64 - (new_m4[w] | new_m6[w]).leading_zeros() as usize);
+    let new_words = new_pair_count.div_ceil(64);
+
+    let (g_count, p_count, k_count, max_carry_chain, g_masks, p_masks) = if collect_gpk {
+        let gpk_word_count = k.div_ceil(64);
+        let mut g_masks = g_pair[..gpk_word_count].to_vec();
+        let mut p_masks = p_pair[..gpk_word_count].to_vec();
+        mask_top_bits(&mut g_masks, k);
+        mask_top_bits(&mut p_masks, k);
+        let (g, p, kc, chain) = compute_gpk_stats(&g_masks, &p_masks, k);
+        (g, p, kc, chain, g_masks, p_masks)
+    } else {
+        (0, 0, 0, 0, Vec::new(), Vec::new())
+    };
+
+    PackedStepResult {
+        new_m4: new_m4[..new_words].to_vec(),
+        new_m6: new_m6[..new_words].to_vec(),
+        new_pair_count,
+        d,
+        exchanged,
+        g_count,
+        p_count,
+        k_count,
+        max_carry_chain,
+        g_masks,
+        p_masks,
+    }
+}
+
+/// 固定長ワード列を shift ペア（ビット）だけ右シフトする（上位は 0 で埋める）
+#[inline]
+fn shr_pairs<const N: usize>(words: &[u64; N], shift: usize) -> [u64; N] {
+    let (ws, bs) = (shift / 64, (shift % 64) as u32);
+    let mut out = [0u64; N];
+    for i in 0..N.saturating_sub(ws) {
+        let hi = if bs > 0 && i + ws + 1 < N { words[i + ws + 1] << (64 - bs) } else { 0 };
+        out[i] = (words[i + ws] >> bs) | hi;
+    }
+    out
+}
+
 /// x=5 専用パックドスキャン。
 pub fn packed_step_5n1(pn: &PairNumber) -> PackedStepResult {
     packed_step_5n1_opt(pn, true)
@@ -273,11 +456,12 @@ pub fn packed_step_5n1(pn: &PairNumber) -> PackedStepResult {
 
 /// x=5 専用パックドスキャン（GPK収集オプション付き）。
 pub fn packed_step_5n1_opt(pn: &PairNumber, collect_gpk: bool) -> PackedStepResult {
+    debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
     let k = pn.pair_count();
     let m4 = pn.m4_words();
     let m6 = pn.m6_words();
 
-    let out_pairs = k + 2;
+    let out_pairs = k + extra_pairs_for_x(5);
     let out_words = (out_pairs + 63) / 64;
     let gpk_word_count = if collect_gpk { (k + 63) / 64 } else { 0 };
 
@@ -316,6 +500,7 @@ pub fn packed_step_5n1_opt(pn: &PairNumber, collect_gpk: bool) -> PackedStepResu
         carry = c_out;
     }
 
+    debug_assert_within_out_pairs(&new_m4, &new_m6, out_pairs, carry);
     mask_top_bits(&mut new_m4, out_pairs);
     mask_top_bits(&mut new_m6, out_pairs);
 
@@ -351,6 +536,7 @@ pub fn packed_step_generic(pn: &PairNumber, x: u64) -> PackedStepResult {
 
 /// 汎用パックドスキャン（GPK収集オプション付き）。
 pub fn packed_step_generic_opt(pn: &PairNumber, x: u64, collect_gpk: bool) -> PackedStepResult {
+    debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
     let xm1 = x - 1;
     assert!(xm1.is_power_of_two(), "x-1 must be a power of 2");
     let s = xm1.trailing_zeros();
@@ -361,54 +547,101 @@ pub fn packed_step_generic_opt(pn: &PairNumber, x: u64, collect_gpk: bool) -> Pa
     let m4 = pn.m4_words();
     let m6 = pn.m6_words();
 
-    let extra_pairs = ((s as usize + 1) / 2) + 1;
-    let out_pairs = k + extra_pairs;
-    let out_words = (out_pairs + 63) / 64;
-    let gpk_word_count = if collect_gpk { (k + 63) / 64 } else { 0 };
+    let out_pairs = k + extra_pairs_for_x(x);
 
-    let mut new_m4 = vec![0u64; out_words];
-    let mut new_m6 = vec![0u64; out_words];
-    let mut g_masks = vec![0u64; gpk_word_count];
-    let mut p_masks = vec![0u64; gpk_word_count];
+    let (new_m4, new_m6, g_masks, p_masks) =
+        scan_generic_words(m4, m6, k, out_pairs, t, s_is_even, collect_gpk);
 
-    let mut carry = 1u64;
+    finish_generic_step(new_m4, new_m6, g_masks, p_masks, k, out_pairs, collect_gpk)
+}
 
-    for w in 0..out_words {
-        let base = (w * 64) as isize;
+/// x に応じたパックドステップ（x=3, 5 は専用版、それ以外は汎用版。x=3 で pair_count が
+/// SMALL_STEP_MAX_PAIRS 以下なら packed_step_3n1_small）。
+pub fn step_for_x(pn: &PairNumber, x: u64, collect_gpk: bool) -> PackedStepResult {
+    match x {
+        3 if pn.pair_count() <= SMALL_STEP_MAX_PAIRS => packed_step_3n1_small(pn, collect_gpk),
+        3 => packed_step_3n1_opt(pn, collect_gpk),
+        5 => packed_step_5n1_opt(pn, collect_gpk),
+        _ => packed_step_generic_opt(pn, x, collect_gpk),
+    }
+}
 
-        let a_cur = extract_window(m4, k, base);
-        let b_cur = extract_window(m6, k, base);
+/// m4/m6 ワード列から x の参照パターンでペア GPK マスク (g, p) を作る。
+/// 走査（和の計算）は行わない。マスクは pair_count で上位マスク済み。
+pub(crate) fn gpk_masks(m4: &[u64], m6: &[u64], pair_count: usize, x: u64) -> (Vec<u64>, Vec<u64>) {
+    let rp = crate::reference::RefPattern::new(x);
+    let t = rp.t;
+    let word_count = pair_count.div_ceil(64);
+    let mut g_masks = vec![0u64; word_count];
+    let mut p_masks = vec![0u64; word_count];
 
-        let (p_r, q_r, p_l, q_l) = if s_is_even {
-            let b_shifted = extract_window(m6, k, base - t);
-            let a_shifted = extract_window(m4, k, base - t);
-            (b_shifted, b_cur, a_shifted, a_cur)
+    for w in 0..word_count {
+        let base = (w * 64) as isize;
+        let a_cur = extract_window(m4, pair_count, base);
+        let b_cur = extract_window(m6, pair_count, base);
+        let (p_r, q_r, p_l, q_l) = if rp.s_is_even {
+            (extract_window(m6, pair_count, base - t), b_cur, extract_window(m4, pair_count, base - t), a_cur)
         } else {
-            let a_shifted = extract_window(m4, k, base - t - 1);
-            let b_shifted = extract_window(m6, k, base - t);
-            (a_shifted, b_cur, b_shifted, a_cur)
+            (extract_window(m4, pair_count, base - t - 1), b_cur, extract_window(m6, pair_count, base - t), a_cur)
         };
+        let g_out = p_l & q_l;
+        let p_out = p_l ^ q_l;
+        g_masks[w] = g_out | (p_out & (p_r & q_r));
+        p_masks[w] = p_out & (p_r ^ q_r);
+    }
 
-        let (m4w, m6w, c_out, g_pair, p_pair) =
-            packed_scan_word(p_r, q_r, p_l, q_l, carry);
-
-        new_m4[w] = m4w;
-        new_m6[w] = m6w;
+    mask_top_bits(&mut g_masks, pair_count);
+    mask_top_bits(&mut p_masks, pair_count);
+    (g_masks, p_masks)
+}
 
-        if collect_gpk && w < gpk_word_count {
-            g_masks[w] = g_pair;
-            p_masks[w] = p_pair;
-        }
+/// m4/m6 ワード列の1ステップ分の GPK 統計 (g, p, k, max_carry_chain)
+pub(crate) fn gpk_stats_from_words(m4: &[u64], m6: &[u64], pair_count: usize, x: u64) -> (u32, u32, u32, u32) {
+    let (g_masks, p_masks) = gpk_masks(m4, m6, pair_count, x);
+    compute_gpk_stats(&g_masks, &p_masks, pair_count)
+}
 
-        carry = c_out;
-    }
+/// GPK 不一致の診断: pn を x で1ステップ進めたパックド版の g_masks / p_masks と、
+/// 参照ビットから pair_gpk で逐次に分類し直した結果を全ペアで比べる。
+/// 戻り値は食い違ったペアの (添字, パックド版の分類, 再計算した分類)。一致していれば空。
+pub fn diff_gpk(pn: &PairNumber, x: u64) -> Vec<(usize, Gpk, Gpk)> {
+    let result = step_for_x(pn, x, true);
+    diff_gpk_masks(pn, x, &result.g_masks, &result.p_masks)
+}
 
-    mask_top_bits(&mut new_m4, out_pairs);
-    mask_top_bits(&mut new_m6, out_pairs);
+/// diff_gpk の比較部分（任意の g/p マスクを参照ビットからの分類と比べる）
+fn diff_gpk_masks(pn: &PairNumber, x: u64, g_masks: &[u64], p_masks: &[u64]) -> Vec<(usize, Gpk, Gpk)> {
+    let rp = crate::reference::RefPattern::new(x);
+    let bit = |masks: &[u64], i: usize| masks.get(i / 64).is_some_and(|w| (w >> (i % 64)) & 1 == 1);
+    (0..pn.pair_count())
+        .filter_map(|i| {
+            let packed = if bit(g_masks, i) {
+                Gpk::Generate
+            } else if bit(p_masks, i) {
+                Gpk::Propagate
+            } else {
+                Gpk::Kill
+            };
+            let ii = i as isize;
+            let (p_r, q_r) = rp.ref_r(pn, ii, pn.get_m6(ii));
+            let (p_l, q_l) = rp.ref_l(pn, ii, pn.get_m4(ii));
+            let expected = pair_gpk(p_r, q_r, p_l, q_l);
+            (packed != expected).then_some((i, packed, expected))
+        })
+        .collect()
+}
 
+/// 汎用パックドスキャンの後半（GPK 集計 + postprocess）
+fn finish_generic_step(
+    new_m4: Vec<u64>,
+    new_m6: Vec<u64>,
+    g_masks: Vec<u64>,
+    p_masks: Vec<u64>,
+    k: usize,
+    out_pairs: usize,
+    collect_gpk: bool,
+) -> PackedStepResult {
     let (g_count, p_count, k_count, max_carry_chain) = if collect_gpk {
-        mask_top_bits(&mut g_masks, k);
-        mask_top_bits(&mut p_masks, k);
         compute_gpk_stats(&g_masks, &p_masks, k)
     } else {
         (0, 0, 0, 0)
@@ -431,6 +664,283 @@ pub fn packed_step_generic_opt(pn: &PairNumber, x: u64, collect_gpk: bool) -> Pa
     }
 }
 
+/// 固定 x 用のステッパー。s, t, s の偶奇、ヘッドルームを一度だけ計算し、
+/// 走査ループは s の偶奇で単相化した本体を直接呼ぶ（ワードごとの分岐なし）。
+/// 同じ x で何度もステップする停止時間ループ向け。
+#[derive(Debug, Clone, Copy)]
+pub struct Stepper {
+    x: u64,
+    t: isize,
+    s_is_even: bool,
+    extra_pairs: usize,
+}
+
+impl Stepper {
+    /// x-1 は 2 の冪であること
+    pub fn new(x: u64) -> Self {
+        let xm1 = x - 1;
+        assert!(xm1.is_power_of_two(), "x-1 must be a power of 2");
+        let s = xm1.trailing_zeros();
+        Stepper {
+            x,
+            t: (s / 2) as isize,
+            s_is_even: s.is_multiple_of(2),
+            extra_pairs: extra_pairs_for_x(x),
+        }
+    }
+
+    pub fn x(&self) -> u64 {
+        self.x
+    }
+
+    /// 1 ステップ（GPK 収集あり）
+    pub fn packed_step(&self, pn: &PairNumber) -> PackedStepResult {
+        self.packed_step_opt(pn, true)
+    }
+
+    /// 1 ステップ（GPK 収集オプション付き）。packed_step_generic_opt と同じ結果を返す。
+    pub fn packed_step_opt(&self, pn: &PairNumber, collect_gpk: bool) -> PackedStepResult {
+        debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
+        let k = pn.pair_count();
+        let out_pairs = k + self.extra_pairs;
+        let (m4, m6) = (pn.m4_words(), pn.m6_words());
+
+        let (new_m4, new_m6, g_masks, p_masks) = if self.s_is_even {
+            scan_generic_words_body::<u64, true>(m4, m6, k, out_pairs, self.t, collect_gpk)
+        } else {
+            scan_generic_words_body::<u64, false>(m4, m6, k, out_pairs, self.t, collect_gpk)
+        };
+
+        finish_generic_step(new_m4, new_m6, g_masks, p_masks, k, out_pairs, collect_gpk)
+    }
+}
+
+/// 汎用 x の走査（ワード幅共通）。s の偶奇で単相化した本体を一度だけ選んで呼ぶ。
+/// (new_m4, new_m6, g_masks, p_masks) を返す。new_m4/new_m6 は out_pairs で、
+/// g_masks/p_masks は k でそれぞれ上位マスク済み（collect_gpk=false なら空）。
+fn scan_generic_words<W: PackedWord>(
+    m4: &[W],
+    m6: &[W],
+    k: usize,
+    out_pairs: usize,
+    t: isize,
+    s_is_even: bool,
+    collect_gpk: bool,
+) -> (Vec<W>, Vec<W>, Vec<W>, Vec<W>) {
+    if s_is_even {
+        scan_generic_words_body::<W, true>(m4, m6, k, out_pairs, t, collect_gpk)
+    } else {
+        scan_generic_words_body::<W, false>(m4, m6, k, out_pairs, t, collect_gpk)
+    }
+}
+
+/// scan_generic_words の本体。S_EVEN は s が偶数か（コンパイル時定数なので参照窓の分岐は消える）。
+fn scan_generic_words_body<W: PackedWord, const S_EVEN: bool>(
+    m4: &[W],
+    m6: &[W],
+    k: usize,
+    out_pairs: usize,
+    t: isize,
+    collect_gpk: bool,
+) -> (Vec<W>, Vec<W>, Vec<W>, Vec<W>) {
+    let bits = W::BITS as usize;
+    let out_words = out_pairs.div_ceil(bits);
+    let gpk_word_count = if collect_gpk { k.div_ceil(bits) } else { 0 };
+
+    let mut new_m4 = vec![W::ZERO; out_words];
+    let mut new_m6 = vec![W::ZERO; out_words];
+    let mut g_masks = vec![W::ZERO; gpk_word_count];
+    let mut p_masks = vec![W::ZERO; gpk_word_count];
+
+    let mut carry = W::ONE;
+
+    // 上位の全ゼロワードでの早期終了は置かない: 入力は正規形（最上位ペアが非ゼロ）なので、
+    // k を超える出力は高々 t+1 ペアで、その窓（base-t-1 起点）は必ず生きた入力に掛かる。
+    // packed_step_3n1_opt も同じ理由で 0..out_words を回し切る。
+    for w in 0..out_words {
+        let (m4w, m6w, c_out, g_pair, p_pair) = scan_generic_word::<W, S_EVEN>(m4, m6, k, t, w, carry);
+
+        new_m4[w] = m4w;
+        new_m6[w] = m6w;
+
+        if collect_gpk && w < gpk_word_count {
+            g_masks[w] = g_pair;
+            p_masks[w] = p_pair;
+        }
+
+        carry = c_out;
+    }
+
+    // 確保領域を超えた桁上がりがないこと（あれば mask_top_bits で上位が黙って失われる）
+    debug_assert!(carry == W::ZERO, "carry overflow past out_pairs={}", out_pairs);
+    #[cfg(debug_assertions)]
+    {
+        let rem = (out_pairs % bits) as u32;
+        if rem > 0 {
+            let above = !low_mask::<W>(rem);
+            let last = out_words - 1;
+            debug_assert!(
+                new_m4[last] & above == W::ZERO && new_m6[last] & above == W::ZERO,
+                "nonzero pairs above out_pairs={}", out_pairs,
+            );
+        }
+    }
+
+    mask_top_bits(&mut new_m4, out_pairs);
+    mask_top_bits(&mut new_m6, out_pairs);
+    mask_top_bits(&mut g_masks, k);
+    mask_top_bits(&mut p_masks, k);
+
+    (new_m4, new_m6, g_masks, p_masks)
+}
+
+/// 汎用 x の出力ワード w 1つ分の走査。参照窓を取り出して packed_scan_word に渡す。
+/// 戻り値は (new_m4, new_m6, carry_out, g_pair, p_pair)（上位マスクはしない）。
+#[inline]
+fn scan_generic_word<W: PackedWord, const S_EVEN: bool>(
+    m4: &[W],
+    m6: &[W],
+    k: usize,
+    t: isize,
+    w: usize,
+    carry: W,
+) -> (W, W, W, W, W) {
+    let base = (w * W::BITS as usize) as isize;
+
+    let a_cur = extract_window(m4, k, base);
+    let b_cur = extract_window(m6, k, base);
+
+    let (p_r, q_r, p_l, q_l) = if S_EVEN {
+        let b_shifted = extract_window(m6, k, base - t);
+        let a_shifted = extract_window(m4, k, base - t);
+        (b_shifted, b_cur, a_shifted, a_cur)
+    } else {
+        let a_shifted = extract_window(m4, k, base - t - 1);
+        let b_shifted = extract_window(m6, k, base - t);
+        (a_shifted, b_cur, b_shifted, a_cur)
+    };
+
+    packed_scan_word(p_r, q_r, p_l, q_l, carry)
+}
+
+/// pn を x で1ステップ進めるときの、postprocess 前の xn+1 のワード数（scan_window で覆う範囲）
+pub fn step_out_words(pn: &PairNumber, x: u64) -> usize {
+    (pn.pair_count() + extra_pairs_for_x(x)).div_ceil(64)
+}
+
+/// xn+1 の出力ワード [w_start, w_end) だけを走査する部分評価（1ステップを分割して並列に回すための基本操作）。
+/// carry_in はワード w_start のペア 0 への入力キャリー（0 または 1。先頭の窓では +1 の 1）。
+/// 戻り値は (new_m4, new_m6, carry_out, g_masks, p_masks) で、いずれも窓の各ワード分。
+/// new_m4/new_m6 は postprocess 前の xn+1 で、全窓を carry を繋いで連結すると
+/// step_out_words 個のワードでパックドステップの走査結果に一致する（上位マスクは呼び出し側）。
+/// g_masks/p_masks は入力の pair_count でマスク済み（それより上のワードは 0）。
+pub fn scan_window(
+    pn: &PairNumber,
+    x: u64,
+    w_start: usize,
+    w_end: usize,
+    carry_in: u64,
+) -> (Vec<u64>, Vec<u64>, u64, Vec<u64>, Vec<u64>) {
+    let rp = crate::reference::RefPattern::new(x);
+    let (m4, m6, k) = (pn.m4_words(), pn.m6_words(), pn.pair_count());
+    let len = w_end.saturating_sub(w_start);
+    let mut new_m4 = Vec::with_capacity(len);
+    let mut new_m6 = Vec::with_capacity(len);
+    let mut g_masks = Vec::with_capacity(len);
+    let mut p_masks = Vec::with_capacity(len);
+
+    let mut carry = carry_in & 1;
+    for w in w_start..w_end {
+        let (m4w, m6w, c_out, g_pair, p_pair) = if rp.s_is_even {
+            scan_generic_word::<u64, true>(m4, m6, k, rp.t, w, carry)
+        } else {
+            scan_generic_word::<u64, false>(m4, m6, k, rp.t, w, carry)
+        };
+        new_m4.push(m4w);
+        new_m6.push(m6w);
+        // ペア k 以上の GPK は数えない（scan_generic_words_body の mask_top_bits と同じ）
+        let remaining = k.saturating_sub(w * 64);
+        let mask = if remaining >= 64 { u64::MAX } else { (1u64 << remaining) - 1 };
+        g_masks.push(g_pair & mask);
+        p_masks.push(p_pair & mask);
+        carry = c_out;
+    }
+
+    (new_m4, new_m6, carry, g_masks, p_masks)
+}
+
+/// u32 ワード版の結果（GPK は収集しない）
+#[cfg(feature = "u32-words")]
+#[derive(Debug, Clone)]
+pub struct PackedStepResult32 {
+    pub next: PairNumber32,
+    pub d: u64,
+    pub exchanged: bool,
+}
+
+/// u32 ワード版の汎用パックドスキャン（`u32-words` feature）。
+/// 走査本体は u64 版と同じ scan_generic_words を u32 で実体化したもの。
+/// 後処理も postprocess_words で u32 ワードのまま行う。
+#[cfg(feature = "u32-words")]
+pub fn packed_step_generic_u32(pn: &PairNumber32, x: u64) -> PackedStepResult32 {
+    debug_assert!(pn.lsb() == 1, "collatz_step requires odd input");
+    let xm1 = x - 1;
+    assert!(xm1.is_power_of_two(), "x-1 must be a power of 2");
+    let s = xm1.trailing_zeros();
+    let t = (s / 2) as isize;
+    let s_is_even = s.is_multiple_of(2);
+
+    let k = pn.pair_count();
+    let out_pairs = k + extra_pairs_for_x(x);
+    let (new_m4, new_m6, _, _) =
+        scan_generic_words(pn.m4_words(), pn.m6_words(), k, out_pairs, t, s_is_even, false);
+
+    let (m4, m6, pair_count, d) = postprocess_words(&new_m4, &new_m6, out_pairs);
+
+    PackedStepResult32 {
+        next: PairNumber32::from_packed(m4, m6, pair_count),
+        d,
+        exchanged: d % 2 == 1,
+    }
+}
+
+/// postprocess の W ワード版（MSB側 (0,0) トリム → 末尾ゼロ計数 → シフトと交換）。
+/// ファスナー展開 bit[2i] = m6[i], bit[2i+1] = m4[i] 上で最下位の 1 がペア j にあるとき、
+/// d = 2j なら両列を j ペア右シフトし、d = 2j+1 なら新 m4 = m6 ≫ (j+1)、新 m6 = m4 ≫ j とする。
+/// 戻り値は (m4, m6, pair_count, d)。
+#[cfg(feature = "u32-words")]
+fn postprocess_words<W: PackedWord>(m4: &[W], m6: &[W], raw_pairs: usize) -> (Vec<W>, Vec<W>, usize, u64) {
+    let bits = W::BITS as usize;
+    let words = raw_pairs.div_ceil(bits).min(m4.len()).min(m6.len());
+    let live = |i: usize| {
+        let w = m4[i] | m6[i];
+        let rem = raw_pairs - i * bits;
+        if rem < bits { w & low_mask::<W>(rem as u32) } else { w }
+    };
+
+    let Some(top) = (0..words).rev().find(|&i| live(i) != W::ZERO) else {
+        return (vec![W::ZERO], vec![W::ZERO], 1, 0);
+    };
+    let pair_count = top * bits + (W::BITS - live(top).leading_zeros()) as usize;
+    let low = (0..words).find(|&i| live(i) != W::ZERO).unwrap_or(top);
+    let j = low * bits + live(low).trailing_zeros() as usize;
+
+    let bit_at = |words: &[W], i: usize| extract_window(words, pair_count, i as isize) & W::ONE != W::ZERO;
+    let bit_len = if bit_at(m4, pair_count - 1) { 2 * pair_count } else { 2 * pair_count - 1 };
+    let d = if bit_at(m6, j) { 2 * j } else { 2 * j + 1 };
+    let new_pairs = (bit_len - d).div_ceil(2);
+
+    let out_words = new_pairs.div_ceil(bits).max(1);
+    let shift = |src: &[W], by: usize| -> Vec<W> {
+        (0..out_words).map(|w| extract_window(src, pair_count, (w * bits + by) as isize)).collect()
+    };
+    if d.is_multiple_of(2) {
+        (shift(m4, j), shift(m6, j), new_pairs, d as u64)
+    } else {
+        (shift(m6, j + 1), shift(m4, j), new_pairs, d as u64)
+    }
+}
+
 /// GPK カウントを popcount で高速計算（max_carry_chain なし）
 fn compute_gpk_counts(g_masks: &[u64], p_masks: &[u64], pair_count: usize) -> (u32, u32, u32) {
     let mut g_count = 0u32;
@@ -440,9 +950,23 @@ fn compute_gpk_counts(g_masks: &[u64], p_masks: &[u64], pair_count: usize) -> (u
         p_count += p_masks[w].count_ones();
     }
     let k_count = pair_count as u32 - g_count - p_count;
+    debug_assert_eq!(count_k_direct(g_masks, p_masks, pair_count), k_count, "K count mismatch (g/p popcount or masking error)");
     (g_count, p_count, k_count)
 }
 
+/// K の数を !(g|p) から直接数える（compute_gpk_counts の引き算の検算用）。
+/// G と P が重なるペアがあれば不正なので debug_assert で検出する。
+fn count_k_direct(g_masks: &[u64], p_masks: &[u64], pair_count: usize) -> u32 {
+    let mut k_count = 0u32;
+    for w in 0..g_masks.len() {
+        debug_assert_eq!(g_masks[w] & p_masks[w], 0, "pair classified as both G and P in word {}", w);
+        let valid = pair_count.saturating_sub(w * 64).min(64);
+        let mask = if valid == 64 { u64::MAX } else { (1u64 << valid) - 1 };
+        k_count += (!(g_masks[w] | p_masks[w]) & mask).count_ones();
+    }
+    k_count
+}
+
 /// GPK 統計を計算（popcount + キャリー連鎖長）
 fn compute_gpk_stats(g_masks: &[u64], p_masks: &[u64], pair_count: usize) -> (u32, u32, u32, u32) {
     let (g_count, p_count, k_count) = compute_gpk_counts(g_masks, p_masks, pair_count);
@@ -474,13 +998,28 @@ fn compute_gpk_stats(g_masks: &[u64], p_masks: &[u64], pair_count: usize) -> (u3
     (g_count, p_count, k_count, max_chain)
 }
 
+/// 走査結果が確保した out_pairs ペアに収まっていること（debug ビルドのみ検査）。
+/// 桁上がりが残るか out_pairs 以上のペアが非ゼロなら、続く mask_top_bits で上位が黙って失われる。
+/// 汎用経路は scan_generic_words_body の中で同じ検査をする
+#[inline]
+fn debug_assert_within_out_pairs(m4: &[u64], m6: &[u64], out_pairs: usize, carry: u64) {
+    debug_assert!(carry == 0, "carry overflow past out_pairs={}", out_pairs);
+    if cfg!(debug_assertions) {
+        for (w, (&a, &b)) in m4.iter().zip(m6).enumerate() {
+            let live = out_pairs.saturating_sub(w * 64);
+            let above = if live >= 64 { 0 } else { u64::MAX << live };
+            debug_assert!((a | b) & above == 0, "nonzero pairs above out_pairs={}", out_pairs);
+        }
+    }
+}
+
 /// 最上位ワードの余剰ビットをマスク
-fn mask_top_bits(words: &mut [u64], pair_count: usize) {
+fn mask_top_bits<W: PackedWord>(words: &mut [W], pair_count: usize) {
     if words.is_empty() { return; }
-    let remainder = pair_count % 64;
+    let remainder = (pair_count % W::BITS as usize) as u32;
     if remainder > 0 {
         let last = words.len() - 1;
-        words[last] &= (1u64 << remainder) - 1;
+        words[last] = words[last] & low_mask::<W>(remainder);
     }
 }
 
@@ -498,7 +1037,7 @@ mod tests {
         assert_eq!(g, u64::MAX);
 
         // 全 kill → プリフィックスは generate なし
-        let (g, p) = kogge_stone_prefix(0, 0);
+        let (g, p) = kogge_stone_prefix(0u64, 0);
         assert_eq!(g, 0);
         assert_eq!(p, 0);
 
@@ -512,6 +1051,66 @@ mod tests {
         assert_eq!(g, u64::MAX); // bit0のgenerateが全位置に伝播
     }
 
+    /// ワード内をビットごとに逐次加算する素朴なリップルキャリー加算器（packed_scan_word の参照実装）。
+    /// ペア i: m6段で p_r + q_r + c、その桁上がりで m4段 p_l + q_l + c_mid、その桁上がりが次のペアへ。
+    fn pair_add_ripple(p_r: u64, q_r: u64, p_l: u64, q_l: u64, c_in: u64) -> (u64, u64, u64) {
+        let mut new_m4 = 0u64;
+        let mut new_m6 = 0u64;
+        let mut c = c_in & 1;
+        for i in 0..64 {
+            let s = ((p_r >> i) & 1) + ((q_r >> i) & 1) + c;
+            new_m6 |= (s & 1) << i;
+            let c_mid = s >> 1;
+            let t = ((p_l >> i) & 1) + ((q_l >> i) & 1) + c_mid;
+            new_m4 |= (t & 1) << i;
+            c = t >> 1;
+        }
+        (new_m4, new_m6, c)
+    }
+
+    /// packed_scan_word（Kogge-Stone）をリップルキャリー参照と総当たり照合する。
+    /// 一様乱数では長い伝播鎖がほぼ出ないので、q ≈ !p に寄せて伝播ペアを多くした入力も混ぜる
+    /// （下位 shift ビットの p=1 パディングが効くのはワード先頭から続く伝播鎖）。
+    #[test]
+    fn test_packed_scan_word_vs_ripple() {
+        let mut next = crate::reference::test_rng(0xD1B54A32D192ED03u64);
+        let check = |p_r: u64, q_r: u64, p_l: u64, q_l: u64, c_in: u64| {
+            let (m4, m6, c_out, _, _) = packed_scan_word(p_r, q_r, p_l, q_l, c_in);
+            assert_eq!(
+                (m4, m6, c_out),
+                pair_add_ripple(p_r, q_r, p_l, q_l, c_in),
+                "mismatch: p_r={:#x} q_r={:#x} p_l={:#x} q_l={:#x} c_in={}", p_r, q_r, p_l, q_l, c_in
+            );
+        };
+
+        for c_in in [0u64, 1] {
+            // 全 generate / 全 propagate / 全 kill と、その境界
+            for (p_r, q_r, p_l, q_l) in [
+                (0, 0, 0, 0),
+                (u64::MAX, u64::MAX, u64::MAX, u64::MAX),
+                (u64::MAX, 0, u64::MAX, 0),
+                (0, u64::MAX, u64::MAX, 0),
+                (u64::MAX, 0, 0, !(1 << 63)),
+                (1, 1, 0, 0),
+                (1 << 63, 1 << 63, 1 << 63, 1 << 63),
+            ] {
+                check(p_r, q_r, p_l, q_l, c_in);
+            }
+            for _ in 0..50_000 {
+                let (p_r, p_l) = (next(), next());
+                // 一様乱数
+                check(p_r, next(), p_l, next(), c_in);
+                // 伝播寄り: ところどころ (1 ビットずつ) 伝播を切る
+                let cut = next() & next() & next();
+                check(p_r, !p_r ^ (cut & next()), p_l, !p_l ^ (cut & next()), c_in);
+                // 下位 2^j ビットだけ伝播、その上は乱数（Kogge-Stone の各段の境界）
+                let j = next() % 64;
+                let low = (1u64 << j) - 1;
+                check(p_r, (!p_r & low) | (next() & !low), p_l, (!p_l & low) | (next() & !low), c_in);
+            }
+        }
+    }
+
     /// extract_window テスト
     #[test]
     fn test_extract_window() {
@@ -524,6 +1123,55 @@ mod tests {
         assert_eq!(extract_window(&words, 128, 128), 0);
     }
 
+    /// extract_window を1ビットずつの素朴な実装と照合する（境界: 負オフセット、
+    /// ワード跨ぎ、pair_count の途中、ワード列の外、空ワード列）
+    #[test]
+    fn test_extract_window_boundaries() {
+        let naive = |words: &[u64], pair_count: usize, start: isize| -> u64 {
+            let mut v = 0u64;
+            for j in 0..64isize {
+                let i = start + j;
+                if i < 0 || i as usize >= pair_count { continue; }
+                let i = i as usize;
+                if i / 64 < words.len() && (words[i / 64] >> (i % 64)) & 1 != 0 {
+                    v |= 1 << j;
+                }
+            }
+            v
+        };
+        let words = vec![0x0123_4567_89AB_CDEFu64, 0xFEDC_BA98_7654_3210, 0xDEAD_BEEF_CAFE_F00D];
+        for pair_count in [0usize, 1, 5, 63, 64, 65, 100, 127, 128, 129, 191, 192] {
+            for start in -70isize..=200 {
+                assert_eq!(
+                    extract_window(&words, pair_count, start),
+                    naive(&words, pair_count, start),
+                    "pair_count={} start={}", pair_count, start,
+                );
+            }
+        }
+        for start in [-64isize, -1, 0, 1, 63] {
+            assert_eq!(extract_window::<u64>(&[], 0, start), 0);
+        }
+    }
+
+    /// gpk_stats_from_words（窓抽出ベース）が逐次版 GpkInfo と一致する
+    #[test]
+    fn test_gpk_stats_from_words_vs_sequential() {
+        let mut inputs: Vec<BigUint> = (1u64..=999).step_by(2).map(BigUint::from).collect();
+        for bits in [63u32, 64, 65, 127, 128, 129, 255, 256] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            inputs.push((BigUint::one() << bits) + BigUint::from(0x5555u64));
+        }
+        for x in [3u64, 5, 9, 17, 33] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let info = crate::scan::collatz_step(&pn, x).gpk;
+                let got = gpk_stats_from_words(pn.m4_words(), pn.m6_words(), pn.pair_count(), x);
+                assert_eq!(got, (info.g_count, info.p_count, info.k_count, info.max_carry_chain), "{}n+1, n={}", x, n);
+            }
+        }
+    }
+
     /// パックド版と逐次版の結果一致テスト（3n+1）
     #[test]
     fn test_packed_3n1_vs_sequential() {
@@ -550,6 +1198,48 @@ mod tests {
         }
     }
 
+    /// 疎な入力・上位が全ゼロワードの非正規形入力でも、汎用版と結果・GPK 統計が一致する
+    #[test]
+    fn test_packed_3n1_sparse() {
+        let one = BigUint::one();
+        let mut cases: Vec<PairNumber> = [
+            (&one << 1000u32) + 1u32,
+            (&one << 1000u32) + (&one << 64u32) - 1u32,
+            (&one << 4000u32) + (&one << 130u32) + 5u32,
+            (&one << 127u32) + 1u32,
+        ]
+        .iter()
+        .map(PairNumber::from_biguint)
+        .collect();
+        // 上位に全ゼロワードを抱えた非正規形（from_raw_parts 由来を想定）
+        for n_val in [1u64, 27, 0xFFFF_FFFF_FFFF_FFFF] {
+            let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+            let mut m4 = pn.m4_words().to_vec();
+            let mut m6 = pn.m6_words().to_vec();
+            m4.resize(4, 0);
+            m6.resize(4, 0);
+            cases.push(unsafe { PairNumber::from_raw_parts(m4.as_ptr(), m6.as_ptr(), 4, 256) });
+        }
+
+        for pn in &cases {
+            let n = pn.to_biguint();
+            let fast = packed_step_3n1(pn);
+            let full = packed_step_generic(pn, 3);
+            assert_eq!(fast.new_m4, full.new_m4, "n' mismatch, n={}", n);
+            assert_eq!(fast.new_m6, full.new_m6, "n' mismatch, n={}", n);
+            assert_eq!(fast.new_pair_count, full.new_pair_count, "pair_count mismatch, n={}", n);
+            assert_eq!(fast.d, full.d, "d mismatch, n={}", n);
+            assert_eq!(fast.exchanged, full.exchanged, "exchanged mismatch, n={}", n);
+            assert_eq!(
+                (fast.g_count, fast.p_count, fast.k_count, fast.max_carry_chain),
+                (full.g_count, full.p_count, full.k_count, full.max_carry_chain),
+                "gpk mismatch, n={}", n
+            );
+            assert_eq!(fast.g_masks, full.g_masks, "g_masks mismatch, n={}", n);
+            assert_eq!(fast.p_masks, full.p_masks, "p_masks mismatch, n={}", n);
+        }
+    }
+
     /// パックド版と逐次版の結果一致テスト（5n+1）
     #[test]
     fn test_packed_5n1_vs_sequential() {
@@ -595,6 +1285,155 @@ mod tests {
         }
     }
 
+    /// Stepper の結果が packed_step_generic_opt と完全に一致する
+    #[test]
+    fn test_stepper_matches_generic() {
+        let mut inputs: Vec<BigUint> = (1u64..=999).step_by(2).map(BigUint::from).collect();
+        for bits in [63u32, 64, 65, 129, 1000] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+        }
+        for x in [3u64, 5, 9, 17, 33, 129] {
+            let stepper = Stepper::new(x);
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                for collect_gpk in [false, true] {
+                    let a = stepper.packed_step_opt(&pn, collect_gpk);
+                    let b = packed_step_generic_opt(&pn, x, collect_gpk);
+                    assert_eq!(
+                        (a.new_m4, a.new_m6, a.new_pair_count, a.d, a.exchanged),
+                        (b.new_m4, b.new_m6, b.new_pair_count, b.d, b.exchanged),
+                        "{}n+1, n={}", x, n,
+                    );
+                    assert_eq!(
+                        (a.g_count, a.p_count, a.k_count, a.max_carry_chain, a.g_masks, a.p_masks),
+                        (b.g_count, b.p_count, b.k_count, b.max_carry_chain, b.g_masks, b.p_masks),
+                    );
+                }
+            }
+        }
+    }
+
+    /// compute_gpk_counts の G/P/K を逐次版 GpkInfo の直接カウントと照合する
+    #[test]
+    fn test_gpk_counts_vs_gpk_info() {
+        let mut inputs: Vec<BigUint> = (1u64..=2001).step_by(2).map(BigUint::from).collect();
+        for bits in [63u32, 64, 65, 128, 129, 300] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            inputs.push((BigUint::one() << bits) + BigUint::from(12345u64));
+        }
+        for x in [3u64, 5, 9, 17] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let info = crate::scan::collatz_step(&pn, x).gpk;
+                let (g, p, k) = compute_gpk_counts(&info.g_masks, &info.p_masks, info.active_pairs);
+                assert_eq!((g, p, k), (info.g_count, info.p_count, info.k_count), "{}n+1, n={}", x, n);
+            }
+        }
+    }
+
+    /// 2^bits - 1 付近の値で、大きい x でも extra_pairs のヘッドルームが足りる
+    /// （上位が切り捨てられない）ことを BigUint と照合する。
+    #[test]
+    fn test_generic_headroom_large_x() {
+        for x in [129u64, 257] {
+            for bits in [1u32, 2, 63, 64, 65, 127, 128, 129, 255, 256] {
+                let top = (BigUint::one() << bits) - BigUint::one();
+                for delta in [0u64, 2, 4, 6] {
+                    if top < BigUint::from(delta + 1) { continue; }
+                    crate::reference::assert_step_matches_reference(&(&top - BigUint::from(delta)), x);
+                }
+            }
+        }
+    }
+
+    /// 大きい x（s が大きく後方参照が遠い）での早期終了テスト。
+    /// 最上位ペアが非ゼロの n で、逐次版の早期 break が伝播中のキャリーを
+    /// 切り捨てていないことを BigUint 演算と照合する。
+    #[test]
+    fn test_early_termination_large_x() {
+        let mut inputs: Vec<BigUint> = Vec::new();
+        for bits in [2u32, 3, 63, 64, 65, 127, 128, 129, 200] {
+            // 全ビット 1（最上位ペアは (1,1) または (0,1)）
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            // 最上位ビットと最下位ビットのみ
+            inputs.push((BigUint::one() << (bits - 1)) + BigUint::one());
+        }
+        for n_val in (1u64..=255).step_by(2) {
+            inputs.push(BigUint::from(n_val));
+        }
+
+        for x in [33u64, 65, 129] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+
+                let xn1 = n * BigUint::from(x) + BigUint::one();
+                let d_arith = xn1.trailing_zeros().unwrap_or(0);
+                let n_next_arith = &xn1 >> d_arith;
+
+                let seq = crate::scan::collatz_step(&pn, x);
+                assert_eq!(seq.next.to_biguint(), n_next_arith, "scalar n' mismatch for {}n+1, n={}", x, n);
+                assert_eq!(seq.d, d_arith, "scalar d mismatch for {}n+1, n={}", x, n);
+
+                let packed = packed_step_generic(&pn, x);
+                let packed_next = PairNumber::from_packed(
+                    packed.new_m4.clone(), packed.new_m6.clone(), packed.new_pair_count);
+                assert_eq!(packed_next.to_biguint(), n_next_arith, "packed n' mismatch for {}n+1, n={}", x, n);
+                assert_eq!(packed.d, d_arith, "packed d mismatch for {}n+1, n={}", x, n);
+            }
+        }
+    }
+
+    /// 偶数入力はデバッグアサーションで検出される
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "collatz_step requires odd input")]
+    fn test_even_input_scalar_asserts() {
+        let pn = PairNumber::from_biguint(&BigUint::from(28u64));
+        crate::scan::collatz_step_3n1(&pn);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "collatz_step requires odd input")]
+    fn test_even_input_packed_asserts() {
+        let pn = PairNumber::from_biguint(&BigUint::from(28u64));
+        packed_step_generic(&pn, 9);
+    }
+
+    /// u32 ワード版 Kogge-Stone / extract_window が u64 版と一致する
+    #[test]
+    fn test_u32_word_primitives() {
+        let (g, p) = kogge_stone_prefix(1u32, !1u32);
+        assert_eq!((g, p), (u32::MAX, 0));
+        let words32 = vec![0xFF00FF00u32, 0x0F0F0F0Fu32, 0x12345678u32];
+        let words64 = vec![0x0F0F0F0FFF00FF00u64, 0x12345678u64];
+        for start in [-40isize, -3, -1, 0, 5, 31, 32, 33, 64, 70, 95, 96] {
+            let w32 = extract_window(&words32, 96, start) as u64;
+            let w64 = extract_window(&words64, 96, start) & 0xFFFF_FFFF;
+            assert_eq!(w32, w64, "extract_window mismatch at start={}", start);
+        }
+    }
+
+    /// u32 ワード版の汎用スキャンが u64 版と一致する（2^10000-1）
+    #[cfg(feature = "u32-words")]
+    #[test]
+    fn test_packed_u32_vs_u64() {
+        let mut inputs = vec![(BigUint::one() << 10000u32) - BigUint::one()];
+        for n_val in (1u64..=199).step_by(2) {
+            inputs.push(BigUint::from(n_val));
+        }
+        for x in [3u64, 5, 9, 17] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let wide = packed_step_generic(&pn, x);
+                let narrow = packed_step_generic_u32(&PairNumber32::from_pair_number(&pn), x);
+                let wide_next = PairNumber::from_packed(wide.new_m4, wide.new_m6, wide.new_pair_count);
+                assert_eq!(narrow.next.to_pair_number(), wide_next, "u32 n' mismatch for {}n+1", x);
+                assert_eq!(narrow.d, wide.d, "u32 d mismatch for {}n+1", x);
+            }
+        }
+    }
+
     /// 大数のパックド一致テスト
     #[test]
     fn test_packed_large_3n1() {
@@ -652,4 +1491,130 @@ mod tests {
         assert_eq!(packed_next.to_biguint(), seq.next.to_biguint(), "large 5n+1 n' mismatch");
         assert_eq!(packed.d, seq.d, "large 5n+1 d mismatch");
     }
+
+    /// diff_gpk: 正しいマスクでは空、壊したマスクでは壊したペアだけを報告する
+    #[test]
+    fn test_diff_gpk() {
+        let mut inputs: Vec<BigUint> = (1u64..=999).step_by(2).map(BigUint::from).collect();
+        for bits in [64u32, 65, 129, 300] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            inputs.push((BigUint::one() << bits) + BigUint::from(12345u64));
+        }
+        for x in [3u64, 5, 9, 17, 129] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                assert!(diff_gpk(&pn, x).is_empty(), "{}n+1, n={}", x, n);
+            }
+        }
+
+        // 27 = 01 10 11: 3n+1 の各ペアを1つずつ別の分類に書き換える
+        let pn = PairNumber::from_biguint(&BigUint::from(27u64));
+        let r = packed_step_3n1_opt(&pn, true);
+        let (mut g, mut p) = (r.g_masks.clone(), r.p_masks.clone());
+        g[0] ^= 1;
+        let diff = diff_gpk_masks(&pn, 3, &g, &p);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].0, 0);
+        assert_ne!(diff[0].1, diff[0].2);
+        g[0] ^= 1;
+        p[0] ^= 0b100;
+        g[0] &= !0b100;
+        let diff = diff_gpk_masks(&pn, 3, &g, &p);
+        assert_eq!(diff.iter().map(|d| d.0).collect::<Vec<_>>(), [2]);
+    }
+
+    /// scan_window: 窓ごとにキャリーを繋いで連結すると、パックドステップと同じ結果になる
+    #[test]
+    fn test_scan_window_concat_matches_step() {
+        let mut inputs: Vec<BigUint> = vec![BigUint::from(27u64), BigUint::from(1u64)];
+        for bits in [64u32, 129, 300, 641] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            inputs.push((BigUint::one() << bits) + BigUint::from(12345u64));
+        }
+        let mut next = crate::reference::test_rng(0x2545_F491_4F6C_DD1Du64);
+        let mut v = BigUint::from(1u64);
+        for _ in 0..20 {
+            v = (v << 64u32) | BigUint::from(next());
+        }
+        inputs.push(v | BigUint::one());
+
+        for x in [3u64, 5, 9, 17, 129] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let total = step_out_words(&pn, x);
+                let out_pairs = pn.pair_count() + extra_pairs_for_x(x);
+                for window in [1usize, 2, 3, total.max(1)] {
+                    let (mut m4, mut m6, mut g, mut p) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+                    let mut carry = 1u64;
+                    for w_start in (0..total).step_by(window) {
+                        let (a, b, c, gm, pm) = scan_window(&pn, x, w_start, (w_start + window).min(total), carry);
+                        m4.extend(a);
+                        m6.extend(b);
+                        g.extend(gm);
+                        p.extend(pm);
+                        carry = c;
+                    }
+                    assert_eq!(carry, 0, "{}n+1, n={}", x, n);
+                    let full = packed_step_generic_opt(&pn, x, true);
+                    let pp = postprocess::postprocess(m4, m6, out_pairs);
+                    assert_eq!(pp.next.m4_words(), &full.new_m4[..], "{}n+1, n={}, window={}", x, n, window);
+                    assert_eq!(pp.next.m6_words(), &full.new_m6[..], "{}n+1, n={}, window={}", x, n, window);
+                    assert_eq!(pp.d, full.d);
+                    g.truncate(full.g_masks.len());
+                    p.truncate(full.p_masks.len());
+                    assert_eq!((g, p), (full.g_masks.clone(), full.p_masks.clone()), "{}n+1, n={}", x, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_packed_step_3n1_small_matches() {
+        let mut seed = 0x2545_F491_4F6C_DD1Du64;
+        let mut values: Vec<BigUint> = Vec::new();
+        for bits in 1..=2 * SMALL_STEP_MAX_PAIRS as u32 {
+            let ones = (BigUint::one() << bits) - 1u32;
+            values.push(ones.clone());
+            if bits > 1 {
+                values.push((BigUint::one() << (bits - 1)) + 1u32);
+            }
+            // 上位ビットが bits 目にある乱数の奇数
+            let mut limbs = Vec::new();
+            for _ in 0..bits.div_ceil(64) {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                limbs.push(seed);
+            }
+            let r = (BigUint::from_slice(&limbs.iter().flat_map(|&l| [l as u32, (l >> 32) as u32]).collect::<Vec<_>>()) & ones)
+                | (BigUint::one() << (bits - 1))
+                | BigUint::one();
+            values.push(r);
+        }
+        // 大きな d（下位が長くゼロになる 3n+1）: n = (4^j - 1) / 3 · 奇数 の形
+        for j in [1u32, 31, 32, 33, 63, 64, 65, 100, 127, 128, 200, 254] {
+            values.push(((BigUint::one() << (2 * j)) - 1u32) / 3u32);
+            values.push((((BigUint::one() << (2 * j)) - 1u32) / 3u32) * 5u32 + ((BigUint::one() << (2 * j)) * 3u32));
+        }
+        for v in values.iter().filter(|v| v.bit(0) && v.bits() <= 2 * SMALL_STEP_MAX_PAIRS as u64) {
+            let pn = PairNumber::from_biguint(v);
+            for collect_gpk in [true, false] {
+                let small = packed_step_3n1_small(&pn, collect_gpk);
+                let full = packed_step_3n1_opt(&pn, collect_gpk);
+                assert_eq!((&small.new_m4, &small.new_m6, small.new_pair_count), (&full.new_m4, &full.new_m6, full.new_pair_count), "n={}", v);
+                assert_eq!((small.d, small.exchanged), (full.d, full.exchanged), "n={}", v);
+                assert_eq!((small.g_count, small.p_count, small.k_count, small.max_carry_chain),
+                    (full.g_count, full.p_count, full.k_count, full.max_carry_chain), "n={}", v);
+                assert_eq!((&small.g_masks, &small.p_masks), (&full.g_masks, &full.p_masks), "n={}", v);
+            }
+        }
+
+        // 小さい奇数は全数
+        for n in (1u64..20_000).step_by(2) {
+            let pn = PairNumber::from_biguint(&BigUint::from(n));
+            let small = packed_step_3n1_small(&pn, true);
+            let full = packed_step_3n1(&pn);
+            assert_eq!((small.new_m4, small.new_m6, small.d), (full.new_m4, full.new_m6, full.d), "n={}", n);
+        }
+    }
 }
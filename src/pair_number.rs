@@ -1,11 +1,105 @@
 use std::cmp::Ordering;
+use std::iter::{Product, Sum};
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Deref, DerefMut, Mul};
 
 use num_bigint::BigUint;
-use num_traits::Zero;
+use num_traits::{One, Zero};
+use rayon::prelude::*;
+
+/// PairNumber の読み取り専用パックドビュー（FFI 向け、ゼロコピー）。
+/// ポインタは元の PairNumber が生存し、かつ変更されない間だけ有効（ライフタイム 'a で保証）。
+/// 各ポインタは word_count 個の u64 を指す。`ffi` feature で #[repr(C)] になる。
+#[cfg_attr(feature = "ffi", repr(C))]
+#[derive(Debug, Clone, Copy)]
+pub struct PackedView<'a> {
+    pub m4_ptr: *const u64,
+    pub m6_ptr: *const u64,
+    pub word_count: usize,
+    pub pair_count: usize,
+    _marker: PhantomData<&'a [u64]>,
+}
+
+/// 32 バイト境界に揃えた 4 ワードのブロック
+#[derive(Clone, Copy, Default)]
+#[repr(C, align(32))]
+struct Block([u64; 4]);
+
+/// 先頭が 32 バイト境界に揃った u64 列。
+/// Vec<Block> を u64 の列として見せる（len は u64 単位、末尾ブロックの余りは 0）。
+struct AlignedWords {
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl AlignedWords {
+    fn zeroed(len: usize) -> Self {
+        AlignedWords { blocks: vec![Block::default(); len.div_ceil(4)], len }
+    }
+
+    fn from_slice(words: &[u64]) -> Self {
+        let mut aligned = AlignedWords::zeroed(words.len());
+        aligned.copy_from_slice(words);
+        aligned
+    }
+}
+
+impl Deref for AlignedWords {
+    type Target = [u64];
+    fn deref(&self) -> &[u64] {
+        // SAFETY: Block は repr(C) の [u64; 4] なので blocks は 4 * blocks.len() 個の
+        // 初期化済み u64 の連続領域で、len <= 4 * blocks.len()。空でも as_ptr は整列済みの非 null。
+        unsafe { std::slice::from_raw_parts(self.blocks.as_ptr() as *const u64, self.len) }
+    }
+}
+
+impl DerefMut for AlignedWords {
+    fn deref_mut(&mut self) -> &mut [u64] {
+        // SAFETY: deref と同じ。&mut self なので他に参照はない
+        unsafe { std::slice::from_raw_parts_mut(self.blocks.as_mut_ptr() as *mut u64, self.len) }
+    }
+}
+
+impl std::fmt::Debug for AlignedWords {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// m4/m6 ワード列を 32 バイト境界に揃えたコピー（PairNumber::to_aligned で作る）。
+/// 先頭から 4 ワード単位で AVX2 の _mm256_load_si256（アラインド）が使える。
+/// 同じ値を何度も SIMD で読むカーネル向け。1 回読むだけなら m4_words / m6_words で十分。
+#[derive(Debug)]
+pub struct AlignedPairWords {
+    m4_words: AlignedWords,
+    m6_words: AlignedWords,
+    pair_count: usize,
+}
+
+impl AlignedPairWords {
+    /// m4 ワード列（先頭が 32 バイト境界）
+    pub fn m4_words(&self) -> &[u64] {
+        &self.m4_words
+    }
+
+    /// m6 ワード列（先頭が 32 バイト境界）
+    pub fn m6_words(&self) -> &[u64] {
+        &self.m6_words
+    }
+
+    /// ペア数
+    pub fn pair_count(&self) -> usize {
+        self.pair_count
+    }
+}
 
 /// 2ビットペア分解された自然数。
 /// 内部表現は LSB順の m4/m6 パックドビット列（Vec<u64>、各ワード64ペア分）。
-#[derive(Debug, Clone, Eq)]
+///
+/// 正規形: 最上位ペアが (0,0) でない（ゼロは pair_count=1, ワード [0]）。
+/// Ord / Eq は正規形を前提に数値順を与えるので、BTreeMap / BTreeSet のキーにできるのは
+/// 正規形の値だけ。from_raw_parts 以外のコンストラクタは常に正規形を返す。
+#[derive(Eq)]
 pub struct PairNumber {
     /// m4 (左ビット列) パックド。ビット位置 i のペアは word[i/64] の (i%64) ビット目
     m4_words: Vec<u64>,
@@ -15,6 +109,151 @@ pub struct PairNumber {
     pair_count: usize,
 }
 
+/// Debug で全ビットを出す上限と、超えたときに上位・下位それぞれ出すビット数
+const DEBUG_FULL_BITS: usize = 128;
+const DEBUG_EDGE_BITS: usize = 32;
+
+/// ワード列を丸ごと出さない Debug。ペア数・ビット長と、2 進表記（MSB 先頭）を出す。
+/// DEBUG_FULL_BITS ビットを超える値は上位・下位 DEBUG_EDGE_BITS ビットだけにして中間を省き、
+/// 巨大な値でもテストの失敗メッセージやログが溢れないようにする。
+impl std::fmt::Debug for PairNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bits = self.bit_len();
+        let bin = |range: std::ops::Range<usize>| -> String {
+            range.rev().map(|pos| if self.bit(pos) == 1 { '1' } else { '0' }).collect()
+        };
+        let mut d = f.debug_struct("PairNumber");
+        d.field("pairs", &self.pair_count).field("bits", &bits);
+        if bits <= DEBUG_FULL_BITS {
+            let value = if bits == 0 { "0".to_string() } else { bin(0..bits) };
+            d.field("value", &value);
+        } else {
+            d.field("high", &format!("{}...", bin(bits - DEBUG_EDGE_BITS..bits)));
+            d.field("low", &format!("...{}", bin(0..DEBUG_EDGE_BITS)));
+        }
+        d.finish()
+    }
+}
+
+impl Clone for PairNumber {
+    fn clone(&self) -> Self {
+        PairNumber {
+            m4_words: self.m4_words.clone(),
+            m6_words: self.m6_words.clone(),
+            pair_count: self.pair_count,
+        }
+    }
+
+    /// 既存のワード列の確保領域を再利用してコピーする。
+    /// ループ内で `pair.clone_from(&result.next)` とすれば、
+    /// ワード数が容量以内なら再確保は発生しない。
+    fn clone_from(&mut self, source: &Self) {
+        self.m4_words.clone_from(&source.m4_words);
+        self.m6_words.clone_from(&source.m6_words);
+        self.pair_count = source.pair_count;
+    }
+}
+
+/// ペア単位の繰り上がり加算。各ペアで m6段 → m4段の順に足す（collatz_step の走査と同じ2段構成）。
+impl AddAssign<&PairNumber> for PairNumber {
+    fn add_assign(&mut self, rhs: &PairNumber) {
+        let k = self.pair_count.max(rhs.pair_count) + 1;
+        let word_count = k.div_ceil(64);
+        self.m4_words.resize(word_count, 0);
+        self.m6_words.resize(word_count, 0);
+        self.pair_count = k;
+
+        let mut c = 0u8;
+        for i in 0..k {
+            let ii = i as isize;
+            let sum_r = self.get_m6(ii) + rhs.get_m6(ii) + c;
+            let c_mid = sum_r >> 1;
+            let sum_l = self.get_m4(ii) + rhs.get_m4(ii) + c_mid;
+            c = sum_l >> 1;
+
+            let mask = 1u64 << (i % 64);
+            let (w6, w4) = (&mut self.m6_words[i / 64], &mut self.m4_words[i / 64]);
+            *w6 = if sum_r & 1 != 0 { *w6 | mask } else { *w6 & !mask };
+            *w4 = if sum_l & 1 != 0 { *w4 | mask } else { *w4 & !mask };
+        }
+        self.trim_top();
+    }
+}
+
+impl Add for PairNumber {
+    type Output = PairNumber;
+
+    fn add(mut self, rhs: PairNumber) -> PairNumber {
+        self += &rhs;
+        self
+    }
+}
+
+/// 積は BigUint の乗算に委ねる（ペア表現上の乗算は未実装）
+impl Mul for PairNumber {
+    type Output = PairNumber;
+
+    fn mul(self, rhs: PairNumber) -> PairNumber {
+        PairNumber::from_biguint(&(self.to_biguint() * rhs.to_biguint()))
+    }
+}
+
+/// ゼロの正規形（pair_count=1, ワード [0]）
+impl Zero for PairNumber {
+    fn zero() -> Self {
+        PairNumber::from_packed(vec![0], vec![0], 1)
+    }
+
+    fn is_zero(&self) -> bool {
+        PairNumber::is_zero(self)
+    }
+}
+
+/// 1 の正規形（a[0]=0, b[0]=1, pair_count=1）。is_one は固有メソッドと同じ判定
+impl One for PairNumber {
+    fn one() -> Self {
+        PairNumber::from_packed(vec![0], vec![1], 1)
+    }
+
+    fn is_one(&self) -> bool {
+        PairNumber::is_one(self)
+    }
+}
+
+impl Sum for PairNumber {
+    fn sum<I: Iterator<Item = PairNumber>>(iter: I) -> Self {
+        iter.fold(PairNumber::zero(), |mut acc, n| {
+            acc += &n;
+            acc
+        })
+    }
+}
+
+impl<'a> Sum<&'a PairNumber> for PairNumber {
+    fn sum<I: Iterator<Item = &'a PairNumber>>(iter: I) -> Self {
+        iter.fold(PairNumber::zero(), |mut acc, n| {
+            acc += n;
+            acc
+        })
+    }
+}
+
+/// 総積は one() から Mul で順に掛けたものと同じ値。Mul と同じく BigUint に委ねるが、
+/// 1 要素ごとにペア表現へ戻さず、BigUint のまま掛け終えてから1回だけ変換する（空なら one()）
+impl Product for PairNumber {
+    fn product<I: Iterator<Item = PairNumber>>(iter: I) -> Self {
+        let p: BigUint = iter.map(|n| n.to_biguint()).product();
+        PairNumber::from_biguint(&p)
+    }
+}
+
+impl<'a> Product<&'a PairNumber> for PairNumber {
+    fn product<I: Iterator<Item = &'a PairNumber>>(iter: I) -> Self {
+        let p: BigUint = iter.map(|n| n.to_biguint()).product();
+        PairNumber::from_biguint(&p)
+    }
+}
+
 impl PartialEq for PairNumber {
     fn eq(&self, other: &Self) -> bool {
         self.cmp(other) == Ordering::Equal
@@ -34,34 +273,165 @@ impl Ord for PairNumber {
             Ordering::Equal => {}
             ord => return ord,
         }
-        // 2. MSBワードから順に、最上位の差分ペアで比較
-        let words = self.m4_words.len();
-        for w in (0..words).rev() {
-            let diff_m4 = self.m4_words[w] ^ other.m4_words[w];
-            let diff_m6 = self.m6_words[w] ^ other.m6_words[w];
-            let diff_any = diff_m4 | diff_m6;
-            if diff_any == 0 {
-                continue;
-            }
-            // 最上位の差分ペア位置
-            let top_bit = 63 - diff_any.leading_zeros();
-            let mask = 1u64 << top_bit;
-            // m4（上位ビット 2i+1）を先に比較
-            let a_m4 = self.m4_words[w] & mask;
-            let b_m4 = other.m4_words[w] & mask;
-            if a_m4 != b_m4 {
-                return if a_m4 != 0 { Ordering::Greater } else { Ordering::Less };
-            }
-            // m4同値ならm6（下位ビット 2i）で決定
-            let a_m6 = self.m6_words[w] & mask;
-            let b_m6 = other.m6_words[w] & mask;
-            if a_m6 != b_m6 {
-                return if a_m6 != 0 { Ordering::Greater } else { Ordering::Less };
-            }
-            unreachable!();
+        // 2. 最上位の差分ワードを探し（AVX2 があれば4ワード単位）、
+        //    そのワード内の最上位の差分ペアで比較
+        let Some(w) = highest_diff_word(
+            &self.m4_words, &self.m6_words, &other.m4_words, &other.m6_words,
+        ) else {
+            return Ordering::Equal;
+        };
+        let diff_any = (self.m4_words[w] ^ other.m4_words[w]) | (self.m6_words[w] ^ other.m6_words[w]);
+        // 最上位の差分ペア位置
+        let top_bit = 63 - diff_any.leading_zeros();
+        let mask = 1u64 << top_bit;
+        // m4（上位ビット 2i+1）を先に比較
+        let a_m4 = self.m4_words[w] & mask;
+        let b_m4 = other.m4_words[w] & mask;
+        if a_m4 != b_m4 {
+            return if a_m4 != 0 { Ordering::Greater } else { Ordering::Less };
+        }
+        // m4同値ならm6（下位ビット 2i）で決定
+        let a_m6 = self.m6_words[w] & mask;
+        let b_m6 = other.m6_words[w] & mask;
+        if a_m6 != b_m6 {
+            return if a_m6 != 0 { Ordering::Greater } else { Ordering::Less };
+        }
+        unreachable!();
+    }
+}
+
+/// to_biguint が to_biguint_parallel に切り替えるワード数（4096 ワード = 262144 ペア）
+const TO_BIGUINT_PARALLEL_WORDS: usize = 4096;
+
+/// to_biguint_parallel で 1 タスクが受け持つワード数
+const TO_BIGUINT_CHUNK_WORDS: usize = 1024;
+
+/// u32 の各ビットを偶数ビット位置に展開する（bit i → bit 2i）
+#[inline]
+fn spread_bits_u32(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+    x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// 16述語の1ワード分: m4/m6 の同じ位置のビットに述語 pred（1〜16, m1=FALSE, m2=AND, ..., m16=TRUE）
+/// を適用する。範囲外の pred は 0
+#[inline]
+pub(crate) fn predicate_word(m4: u64, m6: u64, pred: u8) -> u64 {
+    match pred {
+        1 => 0u64,
+        2 => m4 & m6,
+        3 => m4 & !m6,
+        4 => m4,
+        5 => !m4 & m6,
+        6 => m6,
+        7 => m4 ^ m6,
+        8 => m4 | m6,
+        9 => !m4 & !m6,
+        10 => !(m4 ^ m6),
+        11 => !m6,
+        12 => m4 | !m6,
+        13 => !m4,
+        14 => !m4 | m6,
+        15 => !(m4 & m6),
+        16 => !0u64,
+        _ => 0,
+    }
+}
+
+/// 64 ビット値の偶数位置ビットを下位 32 ビットに詰める（ファスナー展開の逆）
+#[inline]
+pub(crate) fn compact_even_bits(v: u64) -> u64 {
+    let mut v = v & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0F0F_0F0F_0F0F_0F0F;
+    v = (v | (v >> 4)) & 0x00FF_00FF_00FF_00FF;
+    v = (v | (v >> 8)) & 0x0000_FFFF_0000_FFFF;
+    (v | (v >> 16)) & 0x0000_0000_FFFF_FFFF
+}
+
+/// 2 リム (lo, hi) の偶数ビットを m6 ワードに、奇数ビットを m4 ワードに詰める。
+/// 戻り値は (m6, m4)。x86_64 で BMI2 が使える場合は pext を使う。
+#[inline]
+fn deinterleave_two_limbs(lo: u64, hi: u64) -> (u64, u64) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("bmi2") {
+            // SAFETY: BMI2 の実行時検出済み。
+            return unsafe { deinterleave_two_limbs_bmi2(lo, hi) };
         }
-        Ordering::Equal
     }
+    deinterleave_two_limbs_swar(lo, hi)
+}
+
+/// deinterleave_two_limbs の SWAR 版
+#[inline]
+fn deinterleave_two_limbs_swar(lo: u64, hi: u64) -> (u64, u64) {
+    let m6 = compact_even_bits(lo) | (compact_even_bits(hi) << 32);
+    let m4 = compact_even_bits(lo >> 1) | (compact_even_bits(hi >> 1) << 32);
+    (m6, m4)
+}
+
+/// deinterleave_two_limbs の BMI2 版（pext でリムごとに偶数・奇数ビットを抜き出す）
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+unsafe fn deinterleave_two_limbs_bmi2(lo: u64, hi: u64) -> (u64, u64) {
+    use std::arch::x86_64::_pext_u64;
+
+    const EVEN: u64 = 0x5555_5555_5555_5555;
+    const ODD: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+    let m6 = _pext_u64(lo, EVEN) | (_pext_u64(hi, EVEN) << 32);
+    let m4 = _pext_u64(lo, ODD) | (_pext_u64(hi, ODD) << 32);
+    (m6, m4)
+}
+
+/// m4/m6 ワード列で値が異なる最上位ワードの位置を返す（全一致なら None）。
+/// x86_64 で AVX2 が使える場合は4ワード単位で比較する。
+#[inline]
+fn highest_diff_word(a_m4: &[u64], a_m6: &[u64], b_m4: &[u64], b_m6: &[u64]) -> Option<usize> {
+    let words = a_m4.len().min(b_m4.len());
+    #[cfg(target_arch = "x86_64")]
+    {
+        if words >= 4 && std::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 の実行時検出済み。読み出しは各スライスの先頭 words 個の範囲内。
+            return unsafe { highest_diff_word_avx2(a_m4, a_m6, b_m4, b_m6, words) };
+        }
+    }
+    highest_diff_word_scalar(a_m4, a_m6, b_m4, b_m6, words)
+}
+
+/// highest_diff_word のスカラー版（先頭 words ワードのみ比較）
+#[inline]
+fn highest_diff_word_scalar(a_m4: &[u64], a_m6: &[u64], b_m4: &[u64], b_m6: &[u64], words: usize) -> Option<usize> {
+    (0..words).rev().find(|&w| (a_m4[w] ^ b_m4[w]) | (a_m6[w] ^ b_m6[w]) != 0)
+}
+
+/// highest_diff_word の AVX2 版。上位から4ワードずつ m4/m6 を同時比較する。
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn highest_diff_word_avx2(a_m4: &[u64], a_m6: &[u64], b_m4: &[u64], b_m6: &[u64], words: usize) -> Option<usize> {
+    use std::arch::x86_64::*;
+
+    let mut end = words;
+    while end >= 4 {
+        let s = end - 4;
+        let load = |v: &[u64]| _mm256_loadu_si256(v[s..end].as_ptr() as *const __m256i);
+        let eq_m4 = _mm256_cmpeq_epi64(load(a_m4), load(b_m4));
+        let eq_m6 = _mm256_cmpeq_epi64(load(a_m6), load(b_m6));
+        let eq = _mm256_and_si256(eq_m4, eq_m6);
+        // ビット j = 1 ならワード s+j は一致
+        let eq_mask = _mm256_movemask_pd(_mm256_castsi256_pd(eq)) as u32;
+        if eq_mask != 0b1111 {
+            let diff_mask = !eq_mask & 0b1111;
+            return Some(s + (31 - diff_mask.leading_zeros()) as usize);
+        }
+        end = s;
+    }
+    highest_diff_word_scalar(a_m4, a_m6, b_m4, b_m6, end)
 }
 
 impl PairNumber {
@@ -110,35 +480,127 @@ impl PairNumber {
         PairNumber { m4_words, m6_words, pair_count }
     }
 
+    /// from_biguint の上書き版。既存の m4/m6 ワード列を clear して書き直すので、
+    /// 容量が足りていれば再割り当てしない（同じ PairNumber に多数の BigUint を読み込むループ用）。
+    /// リム 2 本がちょうど 1 ワード（64 ペア）になるので、deinterleave_two_limbs で直接詰める。
+    pub fn load_from_biguint(&mut self, n: &BigUint) {
+        self.m4_words.clear();
+        self.m6_words.clear();
+        if n.is_zero() {
+            self.m4_words.push(0);
+            self.m6_words.push(0);
+            self.pair_count = 1;
+            return;
+        }
+
+        let mut limbs = n.iter_u64_digits();
+        while let Some(lo) = limbs.next() {
+            let hi = limbs.next().unwrap_or(0);
+            let (m6, m4) = deinterleave_two_limbs(lo, hi);
+            self.m4_words.push(m4);
+            self.m6_words.push(m6);
+        }
+        self.pair_count = (n.bits() as usize).div_ceil(2);
+    }
+
+    /// 2 つの u64 リム（値 = hi·2^64 + lo）から直接ペア数を作る。
+    /// 128 ビット以下の値は 64 ペア = 1 ワードに収まるので、BigUint を経由せず
+    /// リムごとの偶数・奇数ビット抽出だけで m6/m4 ワードが決まる（u128 → パックドの切り替え用）。
+    pub fn from_two_limbs(lo: u64, hi: u64) -> Self {
+        let (m6, m4) = deinterleave_two_limbs(lo, hi);
+        let bits = if hi != 0 { 128 - hi.leading_zeros() } else { 64 - lo.leading_zeros() };
+        PairNumber {
+            m4_words: vec![m4],
+            m6_words: vec![m6],
+            pair_count: (bits as usize).div_ceil(2).max(1),
+        }
+    }
+
+    /// u128 からペア数に変換（from_two_limbs）
+    pub fn from_u128(v: u128) -> Self {
+        PairNumber::from_two_limbs(v as u64, (v >> 64) as u64)
+    }
+
+    /// 2^bits - 1（下位 bits ビットが全て 1）を BigUint を経由せずに作る。
+    /// ceil(bits/2) ペアの m4/m6 を全て立て、bits が奇数なら最上位ペアの m4 だけ落とす。
+    /// bits = 0 はゼロ。
+    pub fn all_ones(bits: usize) -> Self {
+        if bits == 0 {
+            return PairNumber::zero();
+        }
+        let pair_count = bits.div_ceil(2);
+        let word_count = pair_count.div_ceil(64);
+        let mut m4_words = vec![u64::MAX; word_count];
+        let mut m6_words = vec![u64::MAX; word_count];
+        let top = pair_count % 64;
+        if top != 0 {
+            m4_words[word_count - 1] = (1u64 << top) - 1;
+            m6_words[word_count - 1] = (1u64 << top) - 1;
+        }
+        if bits % 2 == 1 {
+            m4_words[(pair_count - 1) / 64] &= !(1u64 << ((pair_count - 1) % 64));
+        }
+        PairNumber { m4_words, m6_words, pair_count }
+    }
+
+    /// BigUint 列をまとめてペア数に変換（rayon で並列、順序は入力と同じ）
+    pub fn from_biguints(ns: &[BigUint]) -> Vec<PairNumber> {
+        ns.par_iter().map(PairNumber::from_biguint).collect()
+    }
+
     /// BigUint に復元。
     /// ファスナー構造（LSB first）: b[0], a[0], b[1], a[1], ...
+    /// ワード数が TO_BIGUINT_PARALLEL_WORDS 以上なら to_biguint_parallel に切り替える。
     pub fn to_biguint(&self) -> BigUint {
         let k = self.pair_count;
         if k == 0 {
             return BigUint::zero();
         }
+        let word_count = k.div_ceil(64);
+        if word_count >= TO_BIGUINT_PARALLEL_WORDS {
+            return self.to_biguint_parallel();
+        }
 
-        // ビット長 = 2k
-        let total_bits = 2 * k;
-        let byte_count = (total_bits + 7) / 8;
-        let mut bytes = vec![0u8; byte_count];
-
-        for i in 0..k {
-            let word_idx = i / 64;
-            let bit_idx = i % 64;
-            let m6_bit = ((self.m6_words[word_idx] >> bit_idx) & 1) as u8;
-            let m4_bit = ((self.m4_words[word_idx] >> bit_idx) & 1) as u8;
-
-            // m6 → 偶数ビット位置 (2i), m4 → 奇数ビット位置 (2i+1)
-            let pos_m6 = 2 * i;
-            let pos_m4 = 2 * i + 1;
-            bytes[pos_m6 / 8] |= m6_bit << (pos_m6 % 8);
-            bytes[pos_m4 / 8] |= m4_bit << (pos_m4 % 8);
+        // 1 ワード（64 ペア）= 128 ビット = 16 バイト
+        let mut bytes = vec![0u8; word_count * 16];
+        for (w, out) in bytes.chunks_exact_mut(16).enumerate() {
+            out.copy_from_slice(&self.interleave_word(w));
         }
+        BigUint::from_bytes_le(&bytes)
+    }
 
+    /// to_biguint の並列版。バイト列をワード単位の互いに素な範囲に分け、
+    /// 各スレッドが m4/m6 ワードをファスナー展開して書き込む。最後の from_bytes_le だけは逐次。
+    pub fn to_biguint_parallel(&self) -> BigUint {
+        let k = self.pair_count;
+        if k == 0 {
+            return BigUint::zero();
+        }
+        let word_count = k.div_ceil(64);
+        let mut bytes = vec![0u8; word_count * 16];
+        bytes
+            .par_chunks_mut(16 * TO_BIGUINT_CHUNK_WORDS)
+            .enumerate()
+            .for_each(|(chunk, out)| {
+                for (j, dst) in out.chunks_exact_mut(16).enumerate() {
+                    dst.copy_from_slice(&self.interleave_word(chunk * TO_BIGUINT_CHUNK_WORDS + j));
+                }
+            });
         BigUint::from_bytes_le(&bytes)
     }
 
+    /// ワード w の 64 ペアを 128 ビット（m6 → 偶数ビット, m4 → 奇数ビット）に展開した
+    /// リトルエンディアン 16 バイト。pair_count を超えるペアは 0 として扱う。
+    #[inline]
+    fn interleave_word(&self, w: usize) -> [u8; 16] {
+        let valid = self.live_word_mask(w);
+        let m4 = self.m4_words.get(w).copied().unwrap_or(0) & valid;
+        let m6 = self.m6_words.get(w).copied().unwrap_or(0) & valid;
+        let lo = spread_bits_u32(m6 as u32) | (spread_bits_u32(m4 as u32) << 1);
+        let hi = spread_bits_u32((m6 >> 32) as u32) | (spread_bits_u32((m4 >> 32) as u32) << 1);
+        ((hi as u128) << 64 | lo as u128).to_le_bytes()
+    }
+
     /// ペア数 k を返す
     pub fn pair_count(&self) -> usize {
         self.pair_count
@@ -169,15 +631,296 @@ impl PairNumber {
         }
     }
 
+    /// n mod 3。2 ≡ -1 (mod 3) より 4^i ≡ 1 なので、m6 ビット（2^(2i)）は 1、
+    /// m4 ビット（2^(2i+1)）は 2 として数えればよく、ワード単位の popcount だけで求まる。
+    /// pair_type_counts と同じく pair_count 未満のペアだけを数える（非正規形の上位のゴミは無視）。
+    pub fn mod3(&self) -> u8 {
+        let (mut ones_even, mut ones_odd) = (0u64, 0u64);
+        for i in 0..self.pair_count.div_ceil(64) {
+            let mask = self.live_word_mask(i);
+            ones_even += (self.m6_words[i] & mask).count_ones() as u64;
+            ones_odd += (self.m4_words[i] & mask).count_ones() as u64;
+        }
+        ((ones_even + 2 * ones_odd) % 3) as u8
+    }
+
+    /// 生きているワード i（0..pair_count.div_ceil(64)）のうち pair_count 未満のペアのビットマスク。
+    /// 最終ワードだけが部分的で、それ以外は全ビット。
+    #[inline]
+    fn live_word_mask(&self, i: usize) -> u64 {
+        let k = self.pair_count;
+        if i + 1 == k.div_ceil(64) && !k.is_multiple_of(64) { (1u64 << (k % 64)) - 1 } else { u64::MAX }
+    }
+
+    /// 生きているペア（0..pair_count）を (m4, m6) の組で4種に数える:
+    /// [(1,1), (1,0) = m4 のみ, (0,1) = m6 のみ, (0,0)]。ハミング重みの4分割で、
+    /// 述語 AND / L>R / R>L の成立ペア数にそのまま対応する。
+    /// m4&m6, m4&!m6, !m4&m6 の popcount で数え、(0,0) は残り（和は常に pair_count）。
+    pub fn pair_type_counts(&self) -> [u32; 4] {
+        let k = self.pair_count;
+        let live = k.div_ceil(64);
+        let mut counts = [0u32; 4];
+        for i in 0..live {
+            let mask = self.live_word_mask(i);
+            let (a, b) = (self.m4_words[i] & mask, self.m6_words[i] & mask);
+            counts[0] += (a & b).count_ones();
+            counts[1] += (a & !b).count_ones();
+            counts[2] += (!a & b).count_ones();
+        }
+        counts[3] = k as u32 - counts[0] - counts[1] - counts[2];
+        counts
+    }
+
+    /// 述語 pred（1〜16）を全ペアに適用したパックドワード列（predicate_stream の一括版）。
+    /// 最終ワードの pair_count 以上のビットは 0 にマスクする。
+    pub fn predicate_words(&self, pred: u8) -> Vec<u64> {
+        self.predicate_stream(pred).collect()
+    }
+
+    /// 述語 pred（1〜16）のワード列を1ワードずつ遅延計算するイテレータ。
+    /// Vec を作らずに複数の述語ストリームを zip してビット演算をつなげられる。
+    pub fn predicate_stream(&self, pred: u8) -> PredicateStream<'_> {
+        PredicateStream { pn: self, pred, word: 0 }
+    }
+
+    /// 値の指紋（FNV-1a 128 ビット）。プロセスやプラットフォームをまたいで安定で、
+    /// 訪問済み状態の集合や合流の検出に使える。pair_count（u64）と、
+    /// 生きているワードを m4, m6 の順に交互にリトルエンディアンのバイト列として入力する
+    /// （最終ワードは pair_count でマスク）。等しい値は常に等しい指紋になる。
+    pub fn fingerprint(&self) -> u128 {
+        const OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+        const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+        let mut h = OFFSET;
+        let mut feed = |v: u64| {
+            for b in v.to_le_bytes() {
+                h ^= b as u128;
+                h = h.wrapping_mul(PRIME);
+            }
+        };
+        let k = self.pair_count;
+        feed(k as u64);
+        for i in 0..k.div_ceil(64) {
+            let mask = self.live_word_mask(i);
+            feed(self.m4_words[i] & mask);
+            feed(self.m6_words[i] & mask);
+        }
+        h
+    }
+
+    /// 値のビット長（0 なら 0）。最上位ペアの m4 が 0 なら 2k-1、そうでなければ 2k。
+    pub fn bit_len(&self) -> usize {
+        let k = self.pair_count;
+        if k == 0 {
+            return 0;
+        }
+        if self.get_m4(k as isize - 1) == 1 {
+            2 * k
+        } else if self.get_m6(k as isize - 1) == 1 {
+            2 * k - 1
+        } else {
+            // 非正規形（ゼロ）
+            self.to_bits_lsb().iter().rposition(|&b| b == 1).map_or(0, |p| p + 1)
+        }
+    }
+
+    /// ビット長だけでの大小比較（cmp の前半。正規形前提）。
+    /// pair_count を比べ、同じなら最上位ペアの使うビット数（2k か 2k-1 ビットか）で決める。
+    /// ビット長が同じなら Equal なので、大きさで並べる前の安価な前段フィルタとして使い、
+    /// Equal の組だけ cmp で詰める。
+    pub fn cmp_magnitude(&self, other: &PairNumber) -> Ordering {
+        match self.pair_count.cmp(&other.pair_count) {
+            Ordering::Equal => {}
+            ord => return ord,
+        }
+        // 最上位ペアが使うビット数: (1,*) → 2, (0,1) → 1, (0,0) → 0（ゼロのみ）
+        let top = self.pair_count as isize - 1;
+        let top_len = |p: &PairNumber| 2 * p.get_m4(top) + (p.get_m4(top) | p.get_m6(top));
+        top_len(self).cmp(&top_len(other))
+    }
+
+    /// 値の 2 進ビット pos を返す（範囲外は 0）。
+    /// pos はペア pos/2 に対応し、偶数なら m6、奇数なら m4。
+    pub fn bit(&self, pos: usize) -> u8 {
+        let i = (pos / 2) as isize;
+        if pos.is_multiple_of(2) { self.get_m6(i) } else { self.get_m4(i) }
+    }
+
+    /// 値の 2 進ビット pos を設定する。
+    /// 必要ならペア数を伸ばし、上位をクリアした場合は MSB 側の (0,0) ペアを詰め直す。
+    pub fn set_bit(&mut self, pos: usize, val: bool) {
+        let i = pos / 2;
+        if i >= self.pair_count {
+            if !val {
+                return;
+            }
+            self.pair_count = i + 1;
+            let word_count = self.pair_count.div_ceil(64);
+            self.m4_words.resize(word_count, 0);
+            self.m6_words.resize(word_count, 0);
+        }
+        let words = if pos.is_multiple_of(2) { &mut self.m6_words } else { &mut self.m4_words };
+        let mask = 1u64 << (i % 64);
+        if val {
+            words[i / 64] |= mask;
+        } else {
+            words[i / 64] &= !mask;
+            self.trim_top();
+        }
+    }
+
+    /// 同じビット長の数の中での順位 self - 2^(bit_len-1)（最上位ビットを落とした値）。
+    /// ビット長 L の数は 2^(L-1) 個あり、戻り値は 0..2^(L-1) を動く。列挙の添字付け向け。
+    /// 最上位ビットは最上位ペアの m4（L が偶数）か m6（L が奇数）なので、そこを消して詰め直すだけ。
+    /// ゼロはゼロを返す。
+    pub fn offset_in_bitlen_class(&self) -> PairNumber {
+        let mut offset = self.clone();
+        let len = self.bit_len();
+        if len > 0 {
+            offset.set_bit(len - 1, false);
+        }
+        offset
+    }
+
+    /// 2^d による商と剰余 (self >> d, self & (2^d - 1)) をペア表現のまま返す。
+    /// 商は postprocess と同じファスナーシフト（d が奇数なら m4/m6 が入れ替わる）。
+    /// 剰余は下位 floor(d/2) ペアを丸ごと残し、d が奇数なら次のペアの m6（偶数ビット）だけ残す。
+    pub fn divmod_pow2(&self, d: u64) -> (PairNumber, PairNumber) {
+        let k = self.pair_count;
+        let (qm4, qm6, qk) = crate::postprocess::shift_right_bits(&self.m4_words, &self.m6_words, k, d);
+        let quotient = PairNumber::from_packed(qm4, qm6, qk);
+
+        // m4 は floor(d/2) ペア、m6 は ceil(d/2) ペアまで（どちらも pair_count で頭打ち）
+        let m4_pairs = usize::try_from(d / 2).unwrap_or(usize::MAX).min(k);
+        let m6_pairs = usize::try_from(d.div_ceil(2)).unwrap_or(usize::MAX).min(k);
+        let low_words = |words: &[u64], pairs: usize| -> Vec<u64> {
+            let mut out = words[..pairs.div_ceil(64)].to_vec();
+            if !pairs.is_multiple_of(64) {
+                *out.last_mut().unwrap() &= (1u64 << (pairs % 64)) - 1;
+            }
+            out.resize(m6_pairs.div_ceil(64), 0);
+            out
+        };
+        let remainder = PairNumber::from_packed(
+            low_words(&self.m4_words, m4_pairs),
+            low_words(&self.m6_words, m6_pairs),
+            m6_pairs,
+        );
+        (quotient, remainder)
+    }
+
+    /// 正規形にして返す（すでに正規形ならそのまま）。
+    /// from_raw_parts など外部由来の値を Ord / map のキーとして使う前に通す。
+    pub fn canonical(mut self) -> Self {
+        self.normalize();
+        self
+    }
+
+    /// 任意のワード列・pair_count を正規形にする:
+    /// ワード数を pair_count に合わせ、最上位ワードの pair_count 以上のビットを 0 にしてから trim_top。
+    /// pair_count = 0 はゼロ（pair_count=1, ワード [0]）として扱う。
+    fn normalize(&mut self) {
+        self.pair_count = self.pair_count.max(1);
+        let word_count = self.pair_count.div_ceil(64);
+        self.m4_words.resize(word_count, 0);
+        self.m6_words.resize(word_count, 0);
+        let remainder = self.pair_count % 64;
+        if remainder != 0 {
+            let mask = (1u64 << remainder) - 1;
+            self.m4_words[word_count - 1] &= mask;
+            self.m6_words[word_count - 1] &= mask;
+        }
+        self.trim_top();
+    }
+
+    /// MSB側の (0,0) ペアを詰め、ワード列も切り詰める（ゼロは pair_count=1 を保つ）
+    fn trim_top(&mut self) {
+        while self.pair_count > 1 && self.get_m4(self.pair_count as isize - 1) == 0
+            && self.get_m6(self.pair_count as isize - 1) == 0
+        {
+            self.pair_count -= 1;
+        }
+        let word_count = self.pair_count.div_ceil(64);
+        self.m4_words.truncate(word_count);
+        self.m6_words.truncate(word_count);
+    }
+
+    /// 値の最下位ビット（= b[0] = m6_words[0] の bit 0）。
+    /// 1 なら奇数。ステップ関数の入力前提（奇数）の検査に使う。
+    #[inline]
+    pub fn lsb(&self) -> u8 {
+        if self.m6_words.is_empty() { 0 } else { (self.m6_words[0] & 1) as u8 }
+    }
+
     /// n=1 かどうか判定（BigUint変換なし）
     /// 1 = 01₂ → ペア: (a[0]=0, b[0]=1), k=1
+    /// 分岐予測のため pair_count を先に見る（ほぼ常に 1 ペアより大きい）。
+    #[inline]
     pub fn is_one(&self) -> bool {
-        if self.pair_count != 1 {
+        if !self.could_be_one() {
             return false;
         }
         self.m4_words[0] == 0 && self.m6_words[0] == 1
     }
 
+    /// n=0 かどうか判定。非正規形（from_raw_parts 由来）のゼロも真になるよう、
+    /// 生きているワードの OR を取ってまとめて判定する（ビットごとには見ない）。
+    /// eq_small と同じく pair_count より上のビットは見ない。
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        (0..self.pair_count.div_ceil(64))
+            .fold(0u64, |acc, i| acc | ((self.m4_words[i] | self.m6_words[i]) & self.live_word_mask(i)))
+            == 0
+    }
+
+    /// 小さい定数 c と等しいか（BigUint 変換なし）。c をワード 0 の m4/m6 に分けて
+    /// ワード単位で比べ、残りのワードは OR でまとめてゼロ判定する。pair_count より上のビットは見ない。
+    pub fn eq_small(&self, c: u64) -> bool {
+        let live = self.pair_count.div_ceil(64);
+        if live == 0 {
+            return c == 0;
+        }
+        let (m6c, m4c) = (compact_even_bits(c), compact_even_bits(c >> 1));
+        let mask = self.live_word_mask(0);
+        if self.m4_words[0] & mask != m4c || self.m6_words[0] & mask != m6c {
+            return false;
+        }
+        (1..live).fold(0u64, |acc, i| acc | ((self.m4_words[i] | self.m6_words[i]) & self.live_word_mask(i))) == 0
+    }
+
+    /// 1 である可能性があるか（pair_count == 1）。
+    /// false なら確実に 1 ではない。ループ内の is_one の前段フィルタ向け。
+    #[inline]
+    pub fn could_be_one(&self) -> bool {
+        self.pair_count == 1
+    }
+
+    /// u64 に収まるか（pair_count <= 32）。
+    /// true なら to_u64 / lt_u64 が使え、比較を BigUint 的な cmp から u64 比較に切り替えられる。
+    #[inline]
+    pub fn is_small(&self) -> bool {
+        self.pair_count <= 32
+    }
+
+    /// u64 に収まれば値を返す（is_small でなければ None）。
+    /// word 0 の下位 32 ペアを m6 → 偶数ビット, m4 → 奇数ビットに展開する。
+    #[inline]
+    pub fn to_u64(&self) -> Option<u64> {
+        if !self.is_small() {
+            return None;
+        }
+        let (Some(&b), Some(&a)) = (self.m6_words.first(), self.m4_words.first()) else {
+            return Some(0);
+        };
+        Some(spread_bits_u32(b as u32) | (spread_bits_u32(a as u32) << 1))
+    }
+
+    /// self < v を判定（アロケーションなし）。
+    /// is_small でなければ self は 2^64 以上なので常に false。
+    #[inline]
+    pub fn lt_u64(&self, v: u64) -> bool {
+        self.to_u64().is_some_and(|s| s < v)
+    }
+
     /// m4 ワードスライスへのアクセス
     pub fn m4_words(&self) -> &[u64] {
         &self.m4_words
@@ -188,9 +931,50 @@ impl PairNumber {
         &self.m6_words
     }
 
-    /// パックドデータから構築
+    /// ワード列を 32 バイト境界に揃えたコピーを作る（SIMD カーネル用、O(ワード数)）。
+    /// 本体のワード列は Vec<u64> のままで、整列は保証しない
+    /// （ステップごとの from_packed でコピーが増えるため）。
+    pub fn to_aligned(&self) -> AlignedPairWords {
+        AlignedPairWords {
+            m4_words: AlignedWords::from_slice(&self.m4_words),
+            m6_words: AlignedWords::from_slice(&self.m6_words),
+            pair_count: self.pair_count,
+        }
+    }
+
+    /// パックドデータから構築。
+    /// 入力はトリム済みでなくてよい: pair_count より上のビットを落とし、MSB側の (0,0) ペアを詰める。
+    /// Ord / PartialEq は pair_count の比較から入るので、この正規化を前提にしている。
     pub fn from_packed(m4_words: Vec<u64>, m6_words: Vec<u64>, pair_count: usize) -> Self {
-        PairNumber { m4_words, m6_words, pair_count }
+        let mut pn = PairNumber { m4_words, m6_words, pair_count };
+        pn.normalize();
+        pn
+    }
+
+    /// m4/m6 ワード列の読み取り専用ビューを返す（コピーなし）
+    pub fn as_packed_view(&self) -> PackedView<'_> {
+        PackedView {
+            m4_ptr: self.m4_words.as_ptr(),
+            m6_ptr: self.m6_words.as_ptr(),
+            word_count: self.m4_words.len(),
+            pair_count: self.pair_count,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 生ポインタから構築（as_packed_view の逆）。ワード列はコピーされる。
+    /// 入力をそのまま写すので、トリム済みでない入力では正規形にならない（canonical を通すこと）。
+    ///
+    /// # Safety
+    /// m4_ptr / m6_ptr はそれぞれ word_count 個の初期化済み u64 を指す有効なポインタであり、
+    /// 呼び出し中に他から変更されないこと。pair_count <= word_count * 64 であること。
+    pub unsafe fn from_raw_parts(m4_ptr: *const u64, m6_ptr: *const u64, word_count: usize, pair_count: usize) -> Self {
+        debug_assert!(pair_count <= word_count * 64);
+        PairNumber {
+            m4_words: std::slice::from_raw_parts(m4_ptr, word_count).to_vec(),
+            m6_words: std::slice::from_raw_parts(m6_ptr, word_count).to_vec(),
+            pair_count,
+        }
     }
 
     /// 互換用: m4 を Vec<u8> で返す（表示・テスト用）
@@ -281,6 +1065,103 @@ impl PairNumber {
     }
 }
 
+/// PairNumber::predicate_stream が返す、述語のパックドワード列のイテレータ。
+/// 下位ワードから順に m4/m6 から計算し、最終ワードは pair_count でマスクする。
+#[derive(Debug, Clone)]
+pub struct PredicateStream<'a> {
+    pn: &'a PairNumber,
+    pred: u8,
+    word: usize,
+}
+
+impl Iterator for PredicateStream<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let w = self.word;
+        if w >= self.pn.pair_count.div_ceil(64) {
+            return None;
+        }
+        self.word += 1;
+        let v = predicate_word(self.pn.m4_words[w], self.pn.m6_words[w], self.pred);
+        Some(v & self.pn.live_word_mask(w))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self.pn.pair_count.div_ceil(64) - self.word;
+        (rest, Some(rest))
+    }
+}
+
+impl ExactSizeIterator for PredicateStream<'_> {}
+
+/// u32 ワードでパックしたペア数（`u32-words` feature）。
+/// 32bit ターゲットやメモリ制約のある環境向け。各ワード32ペア分。
+#[cfg(feature = "u32-words")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairNumber32 {
+    m4_words: Vec<u32>,
+    m6_words: Vec<u32>,
+    pair_count: usize,
+}
+
+#[cfg(feature = "u32-words")]
+impl PairNumber32 {
+    /// u64 版から変換
+    pub fn from_pair_number(pn: &PairNumber) -> Self {
+        let word_count = pn.pair_count.div_ceil(32).max(1);
+        let split = |words: &[u64]| -> Vec<u32> {
+            let mut out: Vec<u32> = words.iter().flat_map(|&w| [w as u32, (w >> 32) as u32]).collect();
+            out.resize(word_count, 0);
+            out
+        };
+        PairNumber32 {
+            m4_words: split(&pn.m4_words),
+            m6_words: split(&pn.m6_words),
+            pair_count: pn.pair_count,
+        }
+    }
+
+    /// u64 版に変換
+    pub fn to_pair_number(&self) -> PairNumber {
+        let word_count = self.pair_count.div_ceil(64).max(1);
+        let join = |words: &[u32]| -> Vec<u64> {
+            let mut out: Vec<u64> = words
+                .chunks(2)
+                .map(|c| c[0] as u64 | ((c.get(1).copied().unwrap_or(0) as u64) << 32))
+                .collect();
+            out.resize(word_count, 0);
+            out
+        };
+        PairNumber::from_packed(join(&self.m4_words), join(&self.m6_words), self.pair_count)
+    }
+
+    /// パックドデータから構築
+    pub fn from_packed(m4_words: Vec<u32>, m6_words: Vec<u32>, pair_count: usize) -> Self {
+        PairNumber32 { m4_words, m6_words, pair_count }
+    }
+
+    /// ペア数 k を返す
+    pub fn pair_count(&self) -> usize {
+        self.pair_count
+    }
+
+    /// 値の最下位ビット
+    pub fn lsb(&self) -> u8 {
+        if self.m6_words.is_empty() { 0 } else { (self.m6_words[0] & 1) as u8 }
+    }
+
+    /// m4 ワードスライスへのアクセス
+    pub fn m4_words(&self) -> &[u32] {
+        &self.m4_words
+    }
+
+    /// m6 ワードスライスへのアクセス
+    pub fn m6_words(&self) -> &[u32] {
+        &self.m6_words
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,6 +1207,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lsb() {
+        for n in 0u64..=200 {
+            let pair = PairNumber::from_biguint(&BigUint::from(n));
+            assert_eq!(pair.lsb() as u64, n & 1, "lsb mismatch for n={}", n);
+        }
+    }
+
     #[test]
     fn test_get_m4_m6() {
         let pair = PairNumber::from_biguint(&BigUint::from(27u64));
@@ -350,6 +1239,78 @@ mod tests {
         assert_eq!(pair.get_m6(2), 1);
     }
 
+    #[test]
+    fn test_bit_set_bit_vs_biguint() {
+        let mut next = crate::reference::test_rng(0x2545_f491_4f6c_dd1du64);
+        let mut pn = PairNumber::from_biguint(&BigUint::from(27u64));
+        let mut reference = BigUint::from(27u64);
+        for _ in 0..2000 {
+            let pos = (next() % 300) as usize;
+            // 上位ビットを消す操作が多めになるよう val=false を 2/3 に
+            let val = next().is_multiple_of(3);
+            pn.set_bit(pos, val);
+            reference.set_bit(pos as u64, val);
+            assert_eq!(pn.to_biguint(), reference);
+            assert_eq!(pn, PairNumber::from_biguint(&reference), "not canonical after set_bit({}, {})", pos, val);
+            let probe = (next() % 320) as usize;
+            assert_eq!(pn.bit(probe), reference.bit(probe as u64) as u8);
+        }
+    }
+
+    #[test]
+    fn test_from_biguints_matches_serial() {
+        let ns: Vec<BigUint> = (0u32..3000)
+            .map(|i| (BigUint::from(i) << (i % 257)) + BigUint::from(i * 7 + 1))
+            .collect();
+        let batch = PairNumber::from_biguints(&ns);
+        assert_eq!(batch.len(), ns.len());
+        for (pn, n) in batch.iter().zip(&ns) {
+            assert_eq!(*pn, PairNumber::from_biguint(n));
+        }
+        assert!(PairNumber::from_biguints(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_bit_len() {
+        for v in [0u64, 1, 2, 3, 4, 7, 8, 27, 255, 256, u64::MAX] {
+            let n = BigUint::from(v);
+            assert_eq!(PairNumber::from_biguint(&n).bit_len() as u64, n.bits(), "v={}", v);
+        }
+        let big = (BigUint::one() << 1000u32) + BigUint::one();
+        assert_eq!(PairNumber::from_biguint(&big).bit_len(), 1001);
+    }
+
+    #[test]
+    fn test_sum_product() {
+        let pairs: Vec<PairNumber> = (1u64..=100).map(|v| PairNumber::from_biguint(&BigUint::from(v))).collect();
+        assert_eq!(pairs.iter().sum::<PairNumber>(), PairNumber::from_biguint(&BigUint::from(5050u64)));
+        assert_eq!(pairs.clone().into_iter().sum::<PairNumber>(), PairNumber::from_biguint(&BigUint::from(5050u64)));
+        assert_eq!(std::iter::empty::<PairNumber>().sum::<PairNumber>(), PairNumber::from_biguint(&BigUint::zero()));
+
+        let fact: BigUint = (1u64..=30).map(BigUint::from).product();
+        assert_eq!(pairs[..30].iter().product::<PairNumber>(), PairNumber::from_biguint(&fact));
+        assert_eq!(std::iter::empty::<PairNumber>().product::<PairNumber>(), PairNumber::from_biguint(&BigUint::one()));
+
+        // 複数ワードにまたがる繰り上がり
+        let a = (BigUint::one() << 300u32) - BigUint::one();
+        let b = BigUint::from(12345u64);
+        let mut pa = PairNumber::from_biguint(&a);
+        pa += &PairNumber::from_biguint(&b);
+        assert_eq!(pa, PairNumber::from_biguint(&(a + b)));
+    }
+
+    #[test]
+    fn test_packed_view() {
+        let pair = PairNumber::from_biguint(&((BigUint::one() << 200u32) + BigUint::from(27u64)));
+        let view = pair.as_packed_view();
+        assert_eq!(view.m4_ptr, pair.m4_words().as_ptr());
+        assert_eq!(view.m6_ptr, pair.m6_words().as_ptr());
+        assert_eq!(view.word_count, pair.word_count());
+        assert_eq!(view.pair_count, pair.pair_count());
+        let back = unsafe { PairNumber::from_raw_parts(view.m4_ptr, view.m6_ptr, view.word_count, view.pair_count) };
+        assert_eq!(back, pair);
+    }
+
     #[test]
     fn test_large_roundtrip() {
         // 2^100 - 1
@@ -360,6 +1321,26 @@ mod tests {
         assert_eq!(pair.pair_count(), 50);
     }
 
+    #[test]
+    fn test_clone_from_reuses_allocation() {
+        let a = PairNumber::from_biguint(&((BigUint::one() << 300u32) - BigUint::one()));
+        let b = PairNumber::from_biguint(&((BigUint::one() << 299u32) + BigUint::one()));
+        let mut dst = a.clone();
+        let m4_ptr = dst.m4_words().as_ptr();
+        let m6_ptr = dst.m6_words().as_ptr();
+        dst.clone_from(&b);
+        assert_eq!(dst, b);
+        assert_eq!(dst.m4_words().as_ptr(), m4_ptr);
+        assert_eq!(dst.m6_words().as_ptr(), m6_ptr);
+
+        // 小さい値へのコピーも既存領域に収まる
+        let small = PairNumber::from_biguint(&BigUint::from(27u64));
+        dst.clone_from(&small);
+        assert_eq!(dst, small);
+        assert_eq!(dst.word_count(), 1);
+        assert_eq!(dst.m4_words().as_ptr(), m4_ptr);
+    }
+
     #[test]
     fn test_ord_exhaustive_small() {
         // 0..=200 の全ペアで、BigUint比較とPairNumber比較が一致することを確認
@@ -375,6 +1356,40 @@ mod tests {
         }
     }
 
+    /// 同一ペア数の乱数値で、Ord が BigUint 比較と一致し、
+    /// AVX2 版とスカラー版の差分ワード探索が一致することを確認
+    #[test]
+    fn test_ord_random_same_length() {
+        let mut next = crate::reference::test_rng(0x9E3779B97F4A7C15u64);
+        for round in 0..2000 {
+            let words = 1 + (round % 13);
+            let bits = words * 128;
+            let mut a_bytes: Vec<u8> = (0..bits / 8).map(|_| next() as u8).collect();
+            // 最上位バイトを揃えて同じペア数にする
+            *a_bytes.last_mut().unwrap() = 0xC0;
+            let mut b_bytes = a_bytes.clone();
+            // 一部のワードのみ変化させ、上位が一致するケースを多く作る
+            let flips = 1 + (next() % 3) as usize;
+            for _ in 0..flips {
+                let idx = (next() as usize) % (b_bytes.len() - 1);
+                b_bytes[idx] ^= 1 << (next() % 8);
+            }
+            let a = BigUint::from_bytes_le(&a_bytes);
+            let b = BigUint::from_bytes_le(&b_bytes);
+            let pa = PairNumber::from_biguint(&a);
+            let pb = PairNumber::from_biguint(&b);
+            assert_eq!(pa.pair_count(), pb.pair_count());
+            assert_eq!(pa.cmp(&pb), a.cmp(&b), "Ord mismatch in round {}", round);
+
+            let scalar = highest_diff_word_scalar(pa.m4_words(), pa.m6_words(), pb.m4_words(), pb.m6_words(), words);
+            assert_eq!(
+                highest_diff_word(pa.m4_words(), pa.m6_words(), pb.m4_words(), pb.m6_words()),
+                scalar,
+                "highest_diff_word mismatch in round {}", round
+            );
+        }
+    }
+
     #[test]
     fn test_ord_different_pair_count() {
         // 3 (pair_count=1) vs 7 (pair_count=2)
@@ -383,6 +1398,29 @@ mod tests {
         assert!(p3 < p7);
     }
 
+    /// cmp_magnitude はビット長の比較と一致し、cmp と矛盾しない
+    #[test]
+    fn test_cmp_magnitude() {
+        let values: Vec<BigUint> = (0u64..=300)
+            .map(BigUint::from)
+            .chain([(BigUint::one() << 127u32) - 1u32, BigUint::one() << 127u32, BigUint::one() << 128u32,
+                (BigUint::one() << 129u32) + 5u32, (BigUint::one() << 1000u32) - 1u32])
+            .collect();
+        let pns: Vec<PairNumber> = values.iter().map(PairNumber::from_biguint).collect();
+        for (a, pa) in values.iter().zip(&pns) {
+            for (b, pb) in values.iter().zip(&pns) {
+                let mag = pa.cmp_magnitude(pb);
+                assert_eq!(mag, a.bits().cmp(&b.bits()), "a={} b={}", a, b);
+                if pa.pair_count() != pb.pair_count() {
+                    assert_eq!(mag, pa.cmp(pb), "a={} b={}", a, b);
+                }
+                if mag != Ordering::Equal {
+                    assert_eq!(mag, pa.cmp(pb), "a={} b={}", a, b);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_ord_large() {
         let a = (BigUint::one() << 100u32) - BigUint::one();
@@ -393,4 +1431,455 @@ mod tests {
         assert!(pb > pa);
         assert_eq!(pa, pa.clone());
     }
+
+    #[test]
+    fn test_small_u64_helpers() {
+        let mut next = crate::reference::test_rng(0x9E37_79B9_7F4A_7C15);
+        let mut samples = vec![0u64, 1, 2, 3, u32::MAX as u64, u64::MAX, u64::MAX - 1, 1 << 63];
+        for _ in 0..200 {
+            let r = next();
+            samples.push(r >> (r % 64));
+        }
+        for &v in &samples {
+            let pn = PairNumber::from_biguint(&BigUint::from(v));
+            assert!(pn.is_small());
+            assert_eq!(pn.to_u64(), Some(v), "v={}", v);
+            assert_eq!(pn.could_be_one(), v < 4);
+            assert_eq!(pn.is_one(), v == 1);
+            assert!(!pn.lt_u64(v));
+            assert_eq!(pn.lt_u64(v.wrapping_add(1)), v != u64::MAX);
+        }
+
+        // 2^64 は 33 ペア: u64 に収まらず、どの u64 よりも小さくない
+        let big = PairNumber::from_biguint(&(BigUint::one() << 64u32));
+        assert!(!big.is_small());
+        assert!(!big.could_be_one());
+        assert_eq!(big.to_u64(), None);
+        assert!(!big.lt_u64(u64::MAX));
+    }
+
+    #[test]
+    fn test_from_packed_normalizes() {
+        // 0b1011 = 11: ペア (a,b) = (1,1), (1,0)
+        let trimmed = PairNumber::from_biguint(&BigUint::from(11u64));
+        assert_eq!(trimmed.pair_count(), 2);
+
+        // 上位に (0,0) ペアを 100 個足した未トリム表現（ワードも 2 つ）
+        let untrimmed = PairNumber::from_packed(vec![0b11, 0], vec![0b01, 0], 102);
+        assert_eq!(untrimmed.pair_count(), 2);
+        assert_eq!(untrimmed.word_count(), 1);
+        assert_eq!(untrimmed.cmp(&trimmed), Ordering::Equal);
+        assert_eq!(untrimmed, trimmed);
+        assert_eq!(untrimmed.to_biguint(), BigUint::from(11u64));
+
+        // pair_count より上のゴミビットは無視される
+        let garbage = PairNumber::from_packed(vec![0b11 | (1 << 40)], vec![0b01 | (1 << 63)], 2);
+        assert_eq!(garbage, trimmed);
+        assert!(garbage < PairNumber::from_biguint(&BigUint::from(12u64)));
+
+        // ゼロ: 全 (0,0) や pair_count=0 も正規形のゼロになる
+        let zero = PairNumber::from_biguint(&BigUint::zero());
+        for z in [PairNumber::from_packed(vec![0, 0], vec![0, 0], 70), PairNumber::from_packed(Vec::new(), Vec::new(), 0)] {
+            assert_eq!(z.pair_count(), 1);
+            assert_eq!(z.m4_words(), &[0]);
+            assert_eq!(z, zero);
+        }
+    }
+
+    #[test]
+    fn test_btreemap_key_order() {
+        use std::collections::BTreeMap;
+
+        let values = [0u64, 1, 2, 3, 4, 11, 12, 255, 256, 1 << 40, u64::MAX];
+        let mut map: BTreeMap<PairNumber, ()> = BTreeMap::new();
+        // 逆順に、未トリムの表現（上位に (0,0) を足したもの）も混ぜて入れる
+        for &v in values.iter().rev() {
+            let pn = PairNumber::from_biguint(&BigUint::from(v));
+            let mut m4 = pn.m4_words().to_vec();
+            let mut m6 = pn.m6_words().to_vec();
+            m4.resize(3, 0);
+            m6.resize(3, 0);
+            // SAFETY: m4 / m6 はそれぞれ 3 ワード、pair_count = 150 <= 192
+            let raw = unsafe { PairNumber::from_raw_parts(m4.as_ptr(), m6.as_ptr(), 3, 150) };
+            map.insert(raw.canonical(), ());
+            map.insert(pn, ());
+        }
+        let big = (BigUint::one() << 200u32) + 7u32;
+        map.insert(PairNumber::from_biguint(&big), ());
+
+        assert_eq!(map.len(), values.len() + 1);
+        let keys: Vec<BigUint> = map.keys().map(|k| k.to_biguint()).collect();
+        let mut expected: Vec<BigUint> = values.iter().map(|&v| BigUint::from(v)).collect();
+        expected.push(big);
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn test_to_aligned() {
+        for bits in [1u32, 63, 64, 300, 1000, 4097] {
+            let n = (BigUint::one() << bits) + BigUint::from(3u64 * bits as u64 % 7 + 1);
+            let pn = PairNumber::from_biguint(&n);
+            let aligned = pn.to_aligned();
+            assert_eq!(aligned.m4_words().as_ptr() as usize % 32, 0);
+            assert_eq!(aligned.m6_words().as_ptr() as usize % 32, 0);
+            assert_eq!(aligned.m4_words(), pn.m4_words());
+            assert_eq!(aligned.m6_words(), pn.m6_words());
+            assert_eq!(aligned.pair_count(), pn.pair_count());
+        }
+    }
+
+    /// divmod_pow2 を BigUint の >> と & (2^d - 1) と照合（d の奇偶、ワード境界、pair_count 超え）
+    #[test]
+    fn test_divmod_pow2() {
+        let mut next = crate::reference::test_rng(0x2545F4914F6CDD1Du64);
+        let mut values = vec![BigUint::zero(), BigUint::one(), BigUint::from(82u64), (BigUint::one() << 200u32) - 1u32];
+        for _ in 0..60 {
+            let bytes = 1 + (next() % 80) as usize;
+            let v: Vec<u8> = (0..bytes).map(|_| next() as u8).collect();
+            values.push(BigUint::from_bytes_le(&v));
+        }
+        for n in &values {
+            let pn = PairNumber::from_biguint(n);
+            let bits = n.bits();
+            let mut ds: Vec<u64> = vec![0, 1, 2, 63, 64, 127, 128, 129, bits.saturating_sub(1), bits, bits + 1, bits + 200, u64::MAX];
+            for _ in 0..10 {
+                ds.push(next() % (bits + 4));
+            }
+            for d in ds {
+                let (q, r) = pn.divmod_pow2(d);
+                let expected_q = if d >= bits { BigUint::zero() } else { n >> d };
+                let expected_r = if d >= bits { n.clone() } else { n & ((BigUint::one() << d) - 1u32) };
+                assert_eq!(q.to_biguint(), expected_q, "quotient mismatch: n={}, d={}", n, d);
+                assert_eq!(r.to_biguint(), expected_r, "remainder mismatch: n={}, d={}", n, d);
+                // 正規形で返る（from_biguint と同じ表現）
+                assert_eq!(q, PairNumber::from_biguint(&expected_q));
+                assert_eq!(r, PairNumber::from_biguint(&expected_r));
+            }
+        }
+    }
+
+    /// num_traits::Zero / One: 正規形との対応と、汎用アルゴリズム（pow）での利用
+    #[test]
+    fn test_zero_one_traits() {
+        assert!(PairNumber::zero().is_zero());
+        assert!(PairNumber::from_biguint(&BigUint::zero()).is_zero());
+        assert_eq!(PairNumber::zero(), PairNumber::from_biguint(&BigUint::zero()));
+        assert!(!PairNumber::from_biguint(&BigUint::from(4u64)).is_zero());
+        let raw_zero = unsafe { PairNumber::from_raw_parts([0u64; 2].as_ptr(), [0u64; 2].as_ptr(), 2, 100) };
+        assert!(raw_zero.is_zero());
+
+        assert!(One::is_one(&PairNumber::one()));
+        assert_eq!(PairNumber::one(), PairNumber::from_biguint(&BigUint::one()));
+        assert!(!One::is_one(&PairNumber::zero()));
+
+        let three = PairNumber::from_biguint(&BigUint::from(3u64));
+        assert_eq!(num_traits::pow(three.clone(), 5).to_biguint(), BigUint::from(243u64));
+        assert_eq!((three.clone() + PairNumber::zero()), three);
+        assert_eq!((three.clone() * PairNumber::one()), three);
+        assert!((three * PairNumber::zero()).is_zero());
+    }
+
+    /// from_two_limbs / from_u128 を from_biguint と照合（SWAR 版と BMI2 版の一致も見る）
+    #[test]
+    fn test_from_two_limbs() {
+        let mut next = crate::reference::test_rng(0x853C49E6748FEA9Bu64);
+        let mut cases = vec![(0u64, 0u64), (1, 0), (2, 0), (3, 0), (u64::MAX, 0), (0, 1), (0, 2), (0, 1 << 63),
+            (u64::MAX, u64::MAX), (1, 1 << 62), (1 << 63, 0)];
+        for _ in 0..2000 {
+            let (lo, hi) = (next(), next());
+            // 上位を短くしてペア数のばらつきも出す
+            let shift = (next() % 64) as u32;
+            cases.push((lo, hi >> shift));
+            cases.push((lo >> shift, 0));
+        }
+        for (lo, hi) in cases {
+            let v = ((hi as u128) << 64) | lo as u128;
+            let expected = PairNumber::from_biguint(&BigUint::from(v));
+            let pn = PairNumber::from_two_limbs(lo, hi);
+            assert_eq!(pn.pair_count(), expected.pair_count(), "v={:#x}", v);
+            assert_eq!(pn.m4_words(), expected.m4_words(), "v={:#x}", v);
+            assert_eq!(pn.m6_words(), expected.m6_words(), "v={:#x}", v);
+            assert_eq!(PairNumber::from_u128(v), pn);
+            assert_eq!(pn.to_biguint(), BigUint::from(v));
+
+            #[cfg(target_arch = "x86_64")]
+            {
+                if std::is_x86_feature_detected!("bmi2") {
+                    let bmi2 = unsafe { deinterleave_two_limbs_bmi2(lo, hi) };
+                    assert_eq!(bmi2, deinterleave_two_limbs_swar(lo, hi), "v={:#x}", v);
+                }
+            }
+        }
+    }
+
+    /// Debug は巨大な値でも長さが抑えられ、小さい値は全ビットを出す
+    #[test]
+    fn test_debug_bounded() {
+        let small = PairNumber::from_biguint(&BigUint::from(27u64));
+        assert_eq!(format!("{:?}", small), r#"PairNumber { pairs: 3, bits: 5, value: "11011" }"#);
+        assert_eq!(format!("{:?}", PairNumber::zero()), r#"PairNumber { pairs: 1, bits: 0, value: "0" }"#);
+
+        let huge = (BigUint::one() << 200_000u32) - BigUint::from(5u64);
+        let pn = PairNumber::from_biguint(&huge);
+        let s = format!("{:?}", pn);
+        assert!(s.len() < 200, "{}", s);
+        assert!(s.contains("pairs: 100000"));
+        assert!(s.contains("bits: 200000"));
+        assert!(s.contains(&format!("high: \"{}...\"", "1".repeat(32))));
+        assert!(s.contains(&format!("low: \"...{}011\"", "1".repeat(29))));
+        assert!(format!("{:#?}", pn).len() < 300);
+    }
+
+    #[test]
+    fn test_mod3() {
+        for n in 0u64..=5000 {
+            assert_eq!(PairNumber::from_biguint(&BigUint::from(n)).mod3(), (n % 3) as u8, "n={}", n);
+        }
+        let mut next = crate::reference::test_rng(0xA076_1D64_78BD_642Fu64);
+        for bits in [63u32, 64, 127, 128, 129, 1000, 4096, 100_003] {
+            for _ in 0..5 {
+                let mut v = BigUint::one() << bits;
+                for j in 0..bits.div_ceil(64) {
+                    v ^= BigUint::from(next()) << (64 * j);
+                }
+                let expected = (&v % 3u32).to_u64_digits().first().copied().unwrap_or(0) as u8;
+                assert_eq!(PairNumber::from_biguint(&v).mod3(), expected, "bits={}", bits);
+            }
+            let all_ones = (BigUint::one() << bits) - 1u32;
+            let expected = (&all_ones % 3u32).to_u64_digits().first().copied().unwrap_or(0) as u8;
+            assert_eq!(PairNumber::from_biguint(&all_ones).mod3(), expected, "2^{}-1", bits);
+        }
+
+        // 非正規形: pair_count 以上のビットと余分なワードのゴミは数えない（pair_type_counts と同じ）
+        for n in [1u64, 2, 27, 1000, 12345, u64::MAX] {
+            let pn = PairNumber::from_biguint(&BigUint::from(n));
+            let k = pn.pair_count();
+            let mut m4 = pn.m4_words().to_vec();
+            let mut m6 = pn.m6_words().to_vec();
+            m4[0] |= !0u64 << k;
+            m6[0] |= (!0u64 << k) & 0x5555_5555_5555_5555;
+            m4.push(u64::MAX);
+            m6.push(0xF0F0);
+            let raw = unsafe { PairNumber::from_raw_parts(m4.as_ptr(), m6.as_ptr(), 2, k) };
+            assert_eq!(raw.mod3(), (n % 3) as u8, "n={}", n);
+        }
+    }
+
+    /// to_biguint（ワード展開・並列）を 1 ビットずつ組み立てた値と照合
+    #[test]
+    fn test_to_biguint_parallel() {
+        let per_bit = |pn: &PairNumber| -> BigUint {
+            let mut v = BigUint::zero();
+            for i in 0..pn.pair_count() as isize {
+                if pn.get_m6(i) == 1 {
+                    v.set_bit(2 * i as u64, true);
+                }
+                if pn.get_m4(i) == 1 {
+                    v.set_bit(2 * i as u64 + 1, true);
+                }
+            }
+            v
+        };
+
+        let all_ones = (BigUint::one() << 100_000u32) - BigUint::one();
+        let pn = PairNumber::from_biguint(&all_ones);
+        assert_eq!(pn.to_biguint_parallel(), all_ones);
+        assert_eq!(pn.to_biguint(), all_ones);
+        assert_eq!(per_bit(&pn), all_ones);
+
+        let mut next = crate::reference::test_rng(0x243F_6A88_85A3_08D3u64);
+        for bits in [1u32, 63, 64, 127, 128, 129, 8191, 300_001, 600_000] {
+            let mut v = BigUint::one() << (bits - 1);
+            for j in 0..bits.div_ceil(64) {
+                v ^= BigUint::from(next()) << (64 * j);
+            }
+            v &= (BigUint::one() << bits) - 1u32;
+            let pn = PairNumber::from_biguint(&v);
+            assert_eq!(pn.to_biguint_parallel(), v, "bits={}", bits);
+            assert_eq!(pn.to_biguint(), v, "bits={}", bits);
+            assert_eq!(per_bit(&pn), v, "bits={}", bits);
+        }
+        assert_eq!(PairNumber::zero().to_biguint_parallel(), BigUint::zero());
+
+        // 上位ワードに余分なビットがある非正規形でも pair_count までしか読まない
+        let raw = PairNumber { m4_words: vec![u64::MAX; 2], m6_words: vec![u64::MAX; 2], pair_count: 70 };
+        assert_eq!(raw.to_biguint(), (BigUint::one() << 140u32) - 1u32);
+        assert_eq!(raw.to_biguint_parallel(), (BigUint::one() << 140u32) - 1u32);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        // 生成経路が違っても等しい値なら等しい指紋
+        for v in [0u64, 1, 2, 3, 27, 0xFFFF_FFFF, u64::MAX] {
+            let a = PairNumber::from_biguint(&BigUint::from(v));
+            let b = PairNumber::from_two_limbs(v, 0);
+            assert_eq!(a.fingerprint(), b.fingerprint(), "v={}", v);
+        }
+        let big = (BigUint::one() << 200u32) + 12345u32;
+        assert_eq!(PairNumber::from_biguint(&big).fingerprint(), PairNumber::from_biguint(&big.clone()).fingerprint());
+
+        // pair_count 以上のゴミビットは無視する
+        let raw = PairNumber { m4_words: vec![u64::MAX; 2], m6_words: vec![u64::MAX; 2], pair_count: 70 };
+        let canon = PairNumber::from_biguint(&((BigUint::one() << 140u32) - 1u32));
+        assert_eq!(raw.fingerprint(), canon.fingerprint());
+
+        // 値の変更は指紋を変える（小さい範囲で衝突なし）
+        let mut seen = std::collections::HashSet::new();
+        for v in 0u64..20_000 {
+            assert!(seen.insert(PairNumber::from_two_limbs(v, 0).fingerprint()), "collision at {}", v);
+        }
+        for shift in 0..300u32 {
+            assert!(seen.insert(PairNumber::from_biguint(&(BigUint::one() << (shift + 64))).fingerprint()));
+        }
+    }
+
+    #[test]
+    fn test_all_ones() {
+        for bits in [0usize, 1, 2, 3, 63, 64, 127, 128, 129, 130, 1000, 1001] {
+            let pn = PairNumber::all_ones(bits);
+            let expected = (BigUint::one() << bits) - 1u32;
+            assert_eq!(pn.to_biguint(), expected, "bits={}", bits);
+            assert_eq!(pn, PairNumber::from_biguint(&expected), "bits={}", bits);
+            assert_eq!(pn.bit_len(), bits, "bits={}", bits);
+        }
+    }
+
+    #[test]
+    fn test_is_zero_eq_small() {
+        assert!(PairNumber::zero().is_zero());
+        assert!(!PairNumber::one().is_zero());
+        assert!(PairNumber { m4_words: vec![0; 3], m6_words: vec![0; 3], pair_count: 150 }.is_zero());
+        assert!(!PairNumber::from_biguint(&(BigUint::one() << 300u32)).is_zero());
+
+        // 27 の 3n+1 は 82 / 2 = 41
+        let next = crate::scan::collatz_step_3n1(&PairNumber::from_two_limbs(27, 0)).next;
+        assert!(next.eq_small(41));
+        assert!(!next.eq_small(27) && !next.eq_small(41 + 64) && !next.eq_small(0));
+
+        for v in [0u64, 1, 2, 3, 41, 0xFFFF_FFFF, u64::MAX] {
+            let pn = PairNumber::from_two_limbs(v, 0);
+            assert!(pn.eq_small(v), "v={}", v);
+            assert!(!pn.eq_small(v ^ 1), "v={}", v);
+            assert!(!pn.eq_small(v.wrapping_add(1 << 40)), "v={}", v);
+        }
+        assert!(!PairNumber::from_biguint(&((BigUint::one() << 64u32) + 41u32)).eq_small(41));
+        // pair_count より上のゴミビットは値に含めない
+        let raw = PairNumber { m4_words: vec![0, u64::MAX], m6_words: vec![1 | (1 << 40), 0], pair_count: 3 };
+        assert!(raw.eq_small(1));
+        let raw = PairNumber { m4_words: vec![0, u64::MAX], m6_words: vec![1 << 40, 0], pair_count: 3 };
+        assert!(raw.is_zero() && raw.eq_small(0));
+    }
+
+    #[test]
+    fn test_load_from_biguint() {
+        let values = [
+            BigUint::zero(),
+            BigUint::one(),
+            BigUint::from(27u64),
+            BigUint::from(u64::MAX),
+            (BigUint::one() << 128u32) - 1u32,
+            (BigUint::one() << 200u32) + 12345u32,
+            BigUint::from(0xDEAD_BEEF_u64) << 700u32,
+        ];
+        let mut pn = PairNumber::one();
+        for m in &values {
+            pn.load_from_biguint(m);
+            assert_eq!(pn, PairNumber::from_biguint(m), "m={}", m);
+            assert_eq!(pn.pair_count, PairNumber::from_biguint(m).pair_count, "m={}", m);
+            assert_eq!(pn.to_biguint(), *m);
+        }
+
+        // 大きい値のあとに小さい値を読むと、同じバッファを使い回す
+        let mut pn = PairNumber::from_biguint(&(BigUint::one() << 1000u32));
+        let (p4, p6) = (pn.m4_words.as_ptr(), pn.m6_words.as_ptr());
+        pn.load_from_biguint(&((BigUint::one() << 300u32) + 7u32));
+        assert_eq!((pn.m4_words.as_ptr(), pn.m6_words.as_ptr()), (p4, p6));
+        assert_eq!(pn.to_biguint(), (BigUint::one() << 300u32) + 7u32);
+    }
+
+    #[test]
+    fn test_pair_type_counts() {
+        // 27 = 0b01_10_11: (m4, m6) = (1,1), (1,0), (0,1)
+        assert_eq!(PairNumber::from_biguint(&BigUint::from(27u64)).pair_type_counts(), [1, 1, 1, 0]);
+        assert_eq!(PairNumber::zero().pair_type_counts(), [0, 0, 0, 1]);
+        // 0b10_00_00_11: (1,1), (0,0), (0,0), (1,0)
+        assert_eq!(PairNumber::from_biguint(&BigUint::from(0b1000_0011u64)).pair_type_counts(), [1, 1, 0, 2]);
+
+        let mut next = crate::reference::test_rng(0x9E37_79B9_7F4A_7C15);
+        for bits in [1u32, 63, 64, 127, 128, 129, 300, 1000] {
+            let mut v = BigUint::one() << (bits - 1);
+            for i in 0..bits - 1 {
+                if next() >> 63 == 1 {
+                    v.set_bit(i as u64, true);
+                }
+            }
+            let pn = PairNumber::from_biguint(&v);
+            let c = pn.pair_type_counts();
+            assert_eq!(c.iter().sum::<u32>() as usize, pn.pair_count(), "bits={}", bits);
+            let (mut expected, mut i) = ([0u32; 4], 0);
+            while i < pn.pair_count() as u64 {
+                let (a, b) = (v.bit(2 * i + 1), v.bit(2 * i));
+                expected[match (a, b) { (true, true) => 0, (true, false) => 1, (false, true) => 2, _ => 3 }] += 1;
+                i += 1;
+            }
+            assert_eq!(c, expected, "bits={}", bits);
+            assert_eq!(c[0] * 2 + c[1] + c[2], v.count_ones() as u32);
+        }
+        // pair_count より上のゴミビットは数えない
+        let raw = PairNumber { m4_words: vec![u64::MAX], m6_words: vec![u64::MAX], pair_count: 3 };
+        assert_eq!(raw.pair_type_counts(), [3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_predicate_stream() {
+        let values = [
+            BigUint::zero(),
+            BigUint::from(27u64),
+            (BigUint::one() << 128u32) - 1u32,
+            (BigUint::from(0xDEAD_BEEF_u64) << 150u32) + 12345u32,
+        ];
+        for v in &values {
+            let pn = PairNumber::from_biguint(v);
+            for pred in 1..=16u8 {
+                let words = pn.predicate_words(pred);
+                let stream = pn.predicate_stream(pred);
+                assert_eq!(stream.len(), words.len());
+                assert_eq!(stream.collect::<Vec<_>>(), words, "v={} pred={}", v, pred);
+                // ビット文字列は trajectory::predicate_bits_msb と一致する
+                assert_eq!(
+                    crate::trajectory::words_to_bits_msb(&words, pn.pair_count),
+                    crate::trajectory::predicate_bits_msb(&pn.m4_words, &pn.m6_words, pn.pair_count, pred)
+                );
+            }
+            // ストリーム同士の合成: AND | L>R = LEFT、TRUE は pair_count ビット
+            let left: Vec<u64> = pn.predicate_stream(2).zip(pn.predicate_stream(3)).map(|(a, b)| a | b).collect();
+            assert_eq!(left, pn.predicate_words(4));
+            let ones: u32 = pn.predicate_stream(16).map(u64::count_ones).sum();
+            assert_eq!(ones as usize, pn.pair_count);
+        }
+        // 27: (m4, m6) = (1,1), (1,0), (0,1) → AND = 0b001, L>R = 0b010, R>L = 0b100
+        let pn = PairNumber::from_biguint(&BigUint::from(27u64));
+        assert_eq!(pn.predicate_words(2), vec![0b001]);
+        assert_eq!(pn.predicate_words(3), vec![0b010]);
+        assert_eq!(pn.predicate_words(5), vec![0b100]);
+        assert_eq!(pn.predicate_words(9), vec![0]);
+    }
+
+    #[test]
+    fn test_offset_in_bitlen_class() {
+        let mut values: Vec<BigUint> = [1u64, 2, 3, 4, 5, 6, 7, 8, 27, 255, 256, 257, 1 << 63, u64::MAX]
+            .iter()
+            .map(|&v| BigUint::from(v))
+            .collect();
+        values.push((BigUint::one() << 127u32) + 12345u32);
+        values.push((BigUint::one() << 128u32) + 1u32);
+        values.push((BigUint::one() << 300u32) - 1u32);
+        for v in &values {
+            let expected = v - (BigUint::one() << (v.bits() - 1));
+            let offset = PairNumber::from_biguint(v).offset_in_bitlen_class();
+            assert_eq!(offset.to_biguint(), expected, "v={v}");
+            assert_eq!(offset, PairNumber::from_biguint(&expected), "v={v}");
+        }
+        assert!(PairNumber::from_biguint(&BigUint::ZERO).offset_in_bitlen_class().is_zero());
+    }
 }
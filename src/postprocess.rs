@@ -42,6 +42,47 @@ pub fn postprocess(new_m4: Vec<u64>, new_m6: Vec<u64>, raw_pair_count: usize) ->
     }
 }
 
+/// postprocess_no_exchange の結果
+pub struct UnexchangedResult {
+    /// m4/m6 の役割を入力のまま保ってシフトしたペア列。
+    /// pending_exchange が true のとき、これは真の値 (xn+1)/2^d ではない（m4 と m6 が入れ替わった値）
+    pub pairs: PairNumber,
+    pub d: u64,
+    /// d が奇数で、本来なら m4⇔m6 交換が起きていたか
+    pub pending_exchange: bool,
+}
+
+impl UnexchangedResult {
+    /// 保留していた交換を適用し、postprocess と同じ真の値を返す
+    pub fn apply_exchange(self) -> PairNumber {
+        if self.pending_exchange {
+            swap_roles(&self.pairs)
+        } else {
+            self.pairs
+        }
+    }
+}
+
+/// m4⇔m6 交換を抑止した後処理。MSBトリムと d の計算は postprocess と同じだが、
+/// d が奇数でも入力の m4 列は m4 に、m6 列は m6 に残す
+/// （pairs.m4[j] = 入力 m4[j + ⌊d/2⌋], pairs.m6[j] = 入力 m6[j + ⌈d/2⌉]）。
+/// m4/m6 の各ビット列を独立に追う解析向けで、pending_exchange のときの pairs は
+/// 真の値と一致しない。真の値が要るときは apply_exchange で交換を適用する。
+pub fn postprocess_no_exchange(new_m4: Vec<u64>, new_m6: Vec<u64>, raw_pair_count: usize) -> UnexchangedResult {
+    let pp = postprocess(new_m4, new_m6, raw_pair_count);
+    let pairs = if pp.exchanged { swap_roles(&pp.next) } else { pp.next };
+    UnexchangedResult {
+        pairs,
+        d: pp.d,
+        pending_exchange: pp.exchanged,
+    }
+}
+
+/// m4 列と m6 列を入れ替えた PairNumber（最上位ペアが非ゼロなら入れ替えても非ゼロなので正規形のまま）
+fn swap_roles(pn: &PairNumber) -> PairNumber {
+    PairNumber::from_packed(pn.m6_words().to_vec(), pn.m4_words().to_vec(), pn.pair_count())
+}
+
 /// 旧インターフェース互換: Vec<u8> per bit の入力を受け取る版
 pub fn postprocess_legacy(new_m4_bits: Vec<u8>, new_m6_bits: Vec<u8>) -> PostprocessResult {
     // Vec<u8> → パックド変換
@@ -60,6 +101,21 @@ pub fn postprocess_legacy(new_m4_bits: Vec<u8>, new_m6_bits: Vec<u8>) -> Postpro
     postprocess(m4_words, m6_words, pair_count)
 }
 
+/// パックドワード列を MSBトリムして PairNumber に包む（シフトなし）。
+/// 偶数状態 xn+1 など、postprocess 前の値を第一級の値として扱うために使う。
+pub fn trim_to_pair_number(m4: &[u64], m6: &[u64], raw_pair_count: usize) -> PairNumber {
+    let k = trim_pair_count(m4, m6, raw_pair_count);
+    if k == 0 {
+        return PairNumber::from_packed(vec![0], vec![0], 1);
+    }
+    let word_count = k.div_ceil(64);
+    let mut rm4 = m4[..word_count].to_vec();
+    let mut rm6 = m6[..word_count].to_vec();
+    mask_top(&mut rm4, k);
+    mask_top(&mut rm6, k);
+    PairNumber::from_packed(rm4, rm6, k)
+}
+
 /// MSBトリム: 最上位の非ゼロペアまでのペア数を返す
 fn trim_pair_count(m4: &[u64], m6: &[u64], pair_count: usize) -> usize {
     if pair_count == 0 { return 0; }
@@ -111,7 +167,7 @@ fn count_trailing_zeros_packed(m4: &[u64], m6: &[u64], pair_count: usize) -> u64
 /// ファスナー展開して d ビット右シフトし、再ペア化する。
 /// d が偶数: ペア単位でシフト（m4/m6 の位置関係保持）
 /// d が奇数: m4/m6 が交換される
-fn shift_right_bits(
+pub(crate) fn shift_right_bits(
     m4: &[u64], m6: &[u64], pair_count: usize, d: u64,
 ) -> (Vec<u64>, Vec<u64>, usize) {
     if d == 0 {
@@ -235,4 +291,32 @@ mod tests {
         let n_prime = result.next.to_biguint();
         assert_eq!(n_prime, num_bigint::BigUint::from(41u64));
     }
+
+    /// 交換抑止版: 各列が入力の同じ列をシフトしたものになり、apply_exchange で postprocess と一致する
+    #[test]
+    fn test_postprocess_no_exchange() {
+        use crate::scan::collatz_step_3n1;
+        use num_bigint::BigUint;
+
+        let mut saw_pending = false;
+        for n in (1u64..=3001).step_by(2).chain([(1u64 << 62) + 1, u64::MAX]) {
+            let step = collatz_step_3n1(&PairNumber::from_biguint(&BigUint::from(n)));
+            let raw = PairNumber::from_packed(step.raw_m4.clone(), step.raw_m6.clone(), step.raw_pair_count);
+            let r = postprocess_no_exchange(step.raw_m4.clone(), step.raw_m6.clone(), step.raw_pair_count);
+            assert_eq!(r.d, step.d);
+            assert_eq!(r.pending_exchange, step.exchanged);
+            saw_pending |= r.pending_exchange;
+
+            let (s4, s6) = ((r.d / 2) as isize, r.d.div_ceil(2) as isize);
+            for j in 0..r.pairs.pair_count() as isize + 2 {
+                assert_eq!(r.pairs.get_m4(j), raw.get_m4(j + s4), "n={} j={}", n, j);
+                assert_eq!(r.pairs.get_m6(j), raw.get_m6(j + s6), "n={} j={}", n, j);
+            }
+            if !r.pending_exchange {
+                assert_eq!(r.pairs, step.next);
+            }
+            assert_eq!(r.apply_exchange(), step.next, "n={}", n);
+        }
+        assert!(saw_pending);
+    }
 }
@@ -1,3 +1,5 @@
+use num_bigint::BigUint;
+
 use crate::pair_number::PairNumber;
 
 /// 参照パターン（表3.1）の実装。
@@ -9,24 +11,56 @@ use crate::pair_number::PairNumber;
 /// s奇数: ref_R(i) = (a[i-t-1], b[i]), ref_L(i) = (b[i-t], a[i])
 
 /// 参照パターンのパラメータ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RefPattern {
     pub s: u32,
     pub t: isize,
     pub s_is_even: bool,
 }
 
+/// 事前計算表に載せる s の上限（x = 3, 5, 9, ..., 2^20+1）
+pub const REF_PATTERN_TABLE_MAX_S: u32 = 20;
+
+/// s = 1..=REF_PATTERN_TABLE_MAX_S の参照パターン（index = s-1）
+const REF_PATTERN_TABLE: [RefPattern; REF_PATTERN_TABLE_MAX_S as usize] = {
+    let mut table = [RefPattern::from_s(1); REF_PATTERN_TABLE_MAX_S as usize];
+    let mut i = 1;
+    while i < table.len() {
+        table[i] = RefPattern::from_s(i as u32 + 1);
+        i += 1;
+    }
+    table
+};
+
 impl RefPattern {
     /// x から参照パターンのパラメータを計算。
-    /// x-1 は2の冪であること。
+    /// x-1 は2の冪であること。表にある x（s <= REF_PATTERN_TABLE_MAX_S）は for_x の事前計算値を返す。
     pub fn new(x: u64) -> Self {
+        if let Some(rp) = Self::for_x(x) {
+            return rp;
+        }
         assert!(x >= 3, "x must be >= 3");
         let xm1 = x - 1;
         assert!(xm1.is_power_of_two(), "x-1 must be a power of 2");
-        let s = xm1.trailing_zeros();
-        let t = (s / 2) as isize;
+        Self::from_s(xm1.trailing_zeros())
+    }
+
+    /// よく使う x（3, 5, 9, 17, 33, 65, ..., 2^20+1）の事前計算済みパラメータ。
+    /// 表にない x（x-1 が2の冪でないものを含む）は None。
+    #[inline]
+    pub fn for_x(x: u64) -> Option<Self> {
+        let xm1 = x.wrapping_sub(1);
+        if x < 3 || !xm1.is_power_of_two() {
+            return None;
+        }
+        REF_PATTERN_TABLE.get(xm1.trailing_zeros() as usize - 1).copied()
+    }
+
+    /// s = log₂(x-1) からパラメータを計算
+    const fn from_s(s: u32) -> Self {
         RefPattern {
             s,
-            t,
+            t: (s / 2) as isize,
             s_is_even: s % 2 == 0,
         }
     }
@@ -56,9 +90,102 @@ impl RefPattern {
     }
 }
 
+/// 仕様としての1ステップ: T(n) = (xn+1) >> d を num-bigint でそのまま計算し (T(n), d) を返す。
+/// 最適化した経路（scan / packed / u128・U256 フェーズ）はすべてこれと一致しなければならない。
+/// x は任意（x=1 や x-1 が2の冪でない x も可）。xn+1 > 0 なので d は常に定まる。
+pub fn collatz_step_naive(n: &BigUint, x: u64) -> (BigUint, u64) {
+    let xn1 = n * x + 1u32;
+    let d = xn1.trailing_zeros().unwrap_or(0);
+    (xn1 >> d, d)
+}
+
+/// テスト用の決定的な擬似乱数列（64 ビット LCG）。同じ seed なら同じ列を返す。
+/// LCG の下位ビットは周期が短いので、上位 32 ビットを下位に畳み込んでから返す。
+#[cfg(test)]
+pub(crate) fn test_rng(seed: u64) -> impl FnMut() -> u64 {
+    let mut state = seed;
+    move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        state ^ (state >> 32)
+    }
+}
+
+/// 奇数 n の x による1ステップを、実装済みの全経路で collatz_step_naive と照合する。
+/// scan::collatz_step、x=3/5 専用のスキャン、packed_step_*（x=3 で pair_count が小さければ
+/// packed_step_3n1_small も）、u128 / U256 フェーズ（値が収まる場合）を調べる。
+/// 新しいステップ経路を足したらここに加えて、同じ入力集合で検証されるようにする。
+#[cfg(test)]
+pub(crate) fn assert_step_matches_reference(n: &BigUint, x: u64) {
+    use crate::{packed, scan, trajectory};
+
+    let expected = collatz_step_naive(n, x);
+    let pn = PairNumber::from_biguint(n);
+    let check = |path: &str, next: BigUint, d: u64| {
+        assert_eq!((next, d), expected, "{} mismatch for {}n+1, n={}", path, x, n);
+    };
+    let check_packed = |path: &str, r: packed::PackedStepResult| {
+        let next = PairNumber::from_packed(r.new_m4, r.new_m6, r.new_pair_count);
+        check(path, next.to_biguint(), r.d);
+    };
+
+    let r = scan::collatz_step(&pn, x);
+    check("collatz_step", r.next.to_biguint(), r.d);
+    if x == 1 {
+        return;
+    }
+    if x == 3 {
+        let r = scan::collatz_step_3n1(&pn);
+        check("collatz_step_3n1", r.next.to_biguint(), r.d);
+        check_packed("packed_step_3n1", packed::packed_step_3n1(&pn));
+        if pn.pair_count() <= packed::SMALL_STEP_MAX_PAIRS {
+            check_packed("packed_step_3n1_small", packed::packed_step_3n1_small(&pn, true));
+        }
+    }
+    if x == 5 {
+        let r = scan::collatz_step_5n1(&pn);
+        check("collatz_step_5n1", r.next.to_biguint(), r.d);
+        check_packed("packed_step_5n1", packed::packed_step_5n1(&pn));
+    }
+    check_packed("packed_step_generic", packed::packed_step_generic(&pn, x));
+    if let Some((next, d)) = trajectory::u128_odd_step(n, x) {
+        check("u128", next, d);
+    }
+    if let Some((next, d)) = trajectory::u256_odd_step(n, x) {
+        check("U256", next, d);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num_traits::One;
+
+    #[test]
+    fn test_collatz_step_naive() {
+        assert_eq!(collatz_step_naive(&BigUint::from(27u64), 3), (BigUint::from(41u64), 1));
+        assert_eq!(collatz_step_naive(&BigUint::from(7u64), 5), (BigUint::from(9u64), 2));
+        assert_eq!(collatz_step_naive(&BigUint::from(3u64), 1), (BigUint::one(), 2));
+        // 3·5+1 = 16 = 2^4
+        assert_eq!(collatz_step_naive(&BigUint::from(5u64), 3), (BigUint::one(), 4));
+    }
+
+    /// 全経路を同じ入力集合で仕様と照合する（小さい奇数の全数 + ワード・リム境界付近）
+    #[test]
+    fn test_all_paths_match_reference() {
+        let mut inputs: Vec<BigUint> = (1u64..=511).step_by(2).map(BigUint::from).collect();
+        for bits in [63u32, 64, 65, 127, 128, 129, 191, 255, 256, 257, 300, 511, 512, 513] {
+            let top = BigUint::one() << bits;
+            inputs.push(&top - 1u32);
+            inputs.push(&top + 1u32);
+            inputs.push((&top >> 1u32) + 1u32);
+            inputs.push(&top - 0x1234_5679u64);
+        }
+        for x in [1u64, 3, 5, 9, 17, 33, 65, 129, 257, (1 << 20) + 1] {
+            for n in &inputs {
+                assert_step_matches_reference(n, x);
+            }
+        }
+    }
 
     #[test]
     fn test_ref_pattern_x3() {
@@ -91,4 +218,57 @@ mod tests {
         assert_eq!(rp.t, 2);
         assert!(rp.s_is_even);
     }
+
+    /// 事前計算表が x = 2^s+1 (s <= 20) の素の計算と一致し、表の外は None
+    #[test]
+    fn test_ref_pattern_table() {
+        for s in 1..=REF_PATTERN_TABLE_MAX_S {
+            let x = (1u64 << s) + 1;
+            let rp = RefPattern::for_x(x).unwrap();
+            assert_eq!(rp, RefPattern::from_s((x - 1).trailing_zeros()), "x = {}", x);
+            assert_eq!(rp, RefPattern::new(x));
+            assert_eq!((rp.t, rp.s_is_even), ((s / 2) as isize, s % 2 == 0));
+        }
+        let big = (1u64 << 21) + 1;
+        assert_eq!(RefPattern::for_x(big), None);
+        assert_eq!(RefPattern::new(big), RefPattern { s: 21, t: 10, s_is_even: false });
+        for x in [0, 1, 2, 4, 7, 11, 1 << 20] {
+            assert_eq!(RefPattern::for_x(x), None, "x = {}", x);
+        }
+    }
+
+    /// 出力ペア数の見積もり（各経路の debug_assert）を崩しにいく乱数入力。上位ワードを全 1 にした
+    /// 値は桁上がりが最上位まで伝わり、確保したペア数ちょうどまで使う
+    #[test]
+    fn test_out_pairs_bound_fuzz() {
+        let mut next = test_rng(0x9e37_79b9_7f4a_7c15);
+        let mut inputs = Vec::new();
+        for _ in 0..40 {
+            let bits = 1 + (next() % 1100) as u32;
+            let words: Vec<u64> = (0..bits.div_ceil(64)).map(|_| next()).collect();
+            let n = BigUint::from_slice(&words.iter().flat_map(|&w| [w as u32, (w >> 32) as u32]).collect::<Vec<_>>());
+            let n = (n % (BigUint::one() << bits)) | BigUint::one();
+            let ones = (BigUint::one() << bits.min(64)) - 1u32;
+            inputs.push(&n | (ones << (bits - bits.min(64))));
+            inputs.push(n);
+        }
+        for x in [3u64, 5, 9, 17, 33, 65, 129, 257] {
+            for n in &inputs {
+                assert_step_matches_reference(n, x);
+                let pn = PairNumber::from_biguint(n);
+                for b in [3u64, 0xffff_ffff, u64::MAX] {
+                    let v = n * x + b;
+                    let d = v.trailing_zeros().unwrap_or(0);
+                    let r = crate::scan::collatz_step_general(&pn, x, b);
+                    assert_eq!((r.next.to_biguint(), r.d), (&v >> d, d), "{}n+{}, n={}", x, b, n);
+                }
+                if x == 3 {
+                    let (m, d1) = collatz_step_naive(n, 3);
+                    let (m2, d2) = collatz_step_naive(&m, 3);
+                    let r = crate::scan::fused_two_step_3n1(&pn);
+                    assert_eq!((r.next.to_biguint(), r.d), (m2, d1 + d2), "fused, n={}", n);
+                }
+            }
+        }
+    }
 }
@@ -1,4 +1,9 @@
-use crate::pair_number::PairNumber;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use rayon::prelude::*;
+
+use crate::packed;
+use crate::pair_number::{compact_even_bits, PairNumber};
 use crate::postprocess;
 use crate::reference::RefPattern;
 
@@ -28,6 +33,9 @@ pub struct GpkInfo {
     pub k_count: u32,
     /// 最大キャリー伝播距離（キャリーが生存する連続ペア数の最大値）
     pub max_carry_chain: u32,
+    /// 初期キャリー (+1) から始まるキャリーが一度も K で消えずに最上位ペアまで届いたか
+    /// （全ペアが G か P）。2^L-1 のような全ビット 1 の入力で起きる「最大キャリー」ステップ
+    pub full_carry_propagation: bool,
 }
 
 impl GpkInfo {
@@ -41,6 +49,7 @@ impl GpkInfo {
             p_count: 0,
             k_count: 0,
             max_carry_chain: 0,
+            full_carry_propagation: false,
         }
     }
 
@@ -65,10 +74,12 @@ impl GpkInfo {
 
     /// キャリー伝播距離を計算。初期キャリー c=1 からの連鎖と、
     /// G で再生成された後の連鎖の最大長を求める。
+    /// 初期キャリーが最後まで K に当たらなければ full_carry_propagation を立てる。
     fn finalize(&mut self) {
         let mut chain = 0u32;
         let mut max_chain = 0u32;
         let mut carry = true; // 初期キャリー = 1
+        let mut killed = false;
 
         for i in 0..self.active_pairs {
             let word_idx = i / 64;
@@ -90,12 +101,14 @@ impl GpkInfo {
                 }
                 chain = 0;
                 carry = false;
+                killed = true;
             }
         }
         if chain > max_chain {
             max_chain = chain;
         }
         self.max_carry_chain = max_chain;
+        self.full_carry_propagation = self.active_pairs > 0 && !killed;
     }
 
     /// GPK列を文字列で取得（表示用、limit文字まで）
@@ -115,6 +128,24 @@ impl GpkInfo {
         s
     }
 
+    /// GPK列をランレングス符号化して返す（LSB順）: [('G', 2), ('P', 1), ...]
+    /// 長い一様な区間が多い大きな数では gpk_string より大幅に小さい。
+    pub fn gpk_rle(&self) -> Vec<(char, u32)> {
+        let mut runs: Vec<(char, u32)> = Vec::new();
+        for i in 0..self.active_pairs {
+            let word_idx = i / 64;
+            let bit_idx = i % 64;
+            let is_g = (self.g_masks[word_idx] >> bit_idx) & 1 != 0;
+            let is_p = (self.p_masks[word_idx] >> bit_idx) & 1 != 0;
+            let ch = if is_g { 'G' } else if is_p { 'P' } else { 'K' };
+            match runs.last_mut() {
+                Some((last, len)) if *last == ch => *len += 1,
+                _ => runs.push((ch, 1)),
+            }
+        }
+        runs
+    }
+
     /// Vec<Gpk> をオンデマンド生成（テスト互換）
     pub fn to_seq(&self) -> Vec<Gpk> {
         let mut seq = Vec::with_capacity(self.active_pairs);
@@ -133,6 +164,28 @@ impl GpkInfo {
         }
         seq
     }
+
+    /// ステップの支配的な GPK クラス（G/P/K のうち数が最大のもの）。
+    /// 同数なら G, P, K の順に優先する。
+    pub fn dominant_class(&self) -> Gpk {
+        if self.g_count >= self.p_count && self.g_count >= self.k_count {
+            Gpk::Generate
+        } else if self.p_count >= self.k_count {
+            Gpk::Propagate
+        } else {
+            Gpk::Kill
+        }
+    }
+}
+
+/// gpk_rle の復号: ランレングス列を GPK 文字列に戻す
+pub fn gpk_rle_decode(runs: &[(char, u32)]) -> String {
+    let total: usize = runs.iter().map(|&(_, len)| len as usize).sum();
+    let mut s = String::with_capacity(total);
+    for &(ch, len) in runs {
+        s.extend(std::iter::repeat_n(ch, len as usize));
+    }
+    s
 }
 
 /// 1ステップの計算結果
@@ -152,6 +205,13 @@ pub struct StepResult {
     pub raw_pair_count: usize,
 }
 
+impl StepResult {
+    /// postprocess前の偶数状態 xn+1 を（MSBトリム済みの）PairNumber として返す。
+    pub fn raw_even(&self) -> PairNumber {
+        postprocess::trim_to_pair_number(&self.raw_m4, &self.raw_m6, self.raw_pair_count)
+    }
+}
+
 /// GPK 統計情報（メモリ上集約用、verify で使用）
 #[derive(Debug, Clone)]
 pub struct GpkStats {
@@ -167,6 +227,11 @@ pub struct GpkStats {
     pub total_steps: u64,
     /// 最大キャリー伝播距離のヒストグラム (index=距離, value=出現回数)
     pub carry_chain_hist: [u64; 128],
+    /// 全ステップを通じた最大キャリー伝播距離（ヒストグラムと違い 127 で丸めない）
+    pub max_carry_chain: u32,
+    /// max_carry_chain を出したステップを含む軌道のシード（attribute_chain_record で記録）。
+    /// 同じ長さなら小さいシードを残す。シードを記録しない経路では None
+    pub max_carry_chain_seed: Option<BigUint>,
 }
 
 impl GpkStats {
@@ -178,19 +243,38 @@ impl GpkStats {
             total_pairs: 0,
             total_steps: 0,
             carry_chain_hist: [0u64; 128],
+            max_carry_chain: 0,
+            max_carry_chain_seed: None,
         }
     }
 
     /// 1ステップの GPK 情報を集約
     #[inline]
     pub fn accumulate(&mut self, info: &GpkInfo) {
-        self.total_g += info.g_count as u64;
-        self.total_p += info.p_count as u64;
-        self.total_k += info.k_count as u64;
-        self.total_pairs += info.active_pairs as u64;
+        self.record_step(info.g_count, info.p_count, info.k_count, info.active_pairs, info.max_carry_chain);
+    }
+
+    /// 1ステップ分の G/P/K 数・ペア数・最大キャリー伝播距離を集約（GpkInfo を作らない経路用）
+    #[inline]
+    pub fn record_step(&mut self, g_count: u32, p_count: u32, k_count: u32, pairs: usize, max_carry_chain: u32) {
+        self.total_g += g_count as u64;
+        self.total_p += p_count as u64;
+        self.total_k += k_count as u64;
+        self.total_pairs += pairs as u64;
         self.total_steps += 1;
-        let idx = (info.max_carry_chain as usize).min(127);
+        let idx = (max_carry_chain as usize).min(127);
         self.carry_chain_hist[idx] += 1;
+        self.max_carry_chain = self.max_carry_chain.max(max_carry_chain);
+    }
+
+    /// シード1つ分の軌道を集約し終えたところで呼ぶ。その軌道で max_carry_chain が
+    /// before（軌道を始める前の値）から伸びていれば、記録のシードを seed() にする。
+    /// シードを昇順に回せば、同じ長さの記録は最初（最小）のシードが残る。
+    #[inline]
+    pub fn attribute_chain_record(&mut self, before: u32, seed: impl FnOnce() -> BigUint) {
+        if self.max_carry_chain > before {
+            self.max_carry_chain_seed = Some(seed());
+        }
     }
 
     /// 並列処理用: 他の GpkStats をマージ
@@ -203,12 +287,24 @@ impl GpkStats {
         for i in 0..128 {
             self.carry_chain_hist[i] += other.carry_chain_hist[i];
         }
+        // 最長の記録を残す（同じ長さなら小さいシード。チャンクのマージ順に依存しない）
+        let better = other.max_carry_chain > self.max_carry_chain
+            || (other.max_carry_chain == self.max_carry_chain
+                && match (&other.max_carry_chain_seed, &self.max_carry_chain_seed) {
+                    (Some(o), Some(s)) => o < s,
+                    (Some(_), None) => true,
+                    _ => false,
+                });
+        if better {
+            self.max_carry_chain = other.max_carry_chain;
+            self.max_carry_chain_seed = other.max_carry_chain_seed.clone();
+        }
     }
 }
 
 /// 参照ビットペアからペア GPK を計算
 #[inline]
-fn pair_gpk(p_r: u8, q_r: u8, p_l: u8, q_l: u8) -> Gpk {
+pub(crate) fn pair_gpk(p_r: u8, q_r: u8, p_l: u8, q_l: u8) -> Gpk {
     // m6段 GPK
     let g_mid = p_r & q_r;
     let p_mid = p_r ^ q_r;
@@ -228,22 +324,143 @@ fn pair_gpk(p_r: u8, q_r: u8, p_l: u8, q_l: u8) -> Gpk {
     }
 }
 
+/// 段 GPK: 1段の2入力ビット (p, q) を G（両方 1）/ P（片方 1）/ K（両方 0）に分ける
+#[inline]
+fn stage_gpk(p: u8, q: u8) -> Gpk {
+    match p + q {
+        2 => Gpk::Generate,
+        1 => Gpk::Propagate,
+        _ => Gpk::Kill,
+    }
+}
+
+/// ペアの詳細分類: m6段と m4段それぞれの GPK の組（3 × 3 = 9 通り）。
+/// pair_gpk の3分類はこれを直列合成で潰したもの（merged）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairClass {
+    /// m6段（下位ビット側）の GPK
+    pub m6: Gpk,
+    /// m4段（上位ビット側）の GPK
+    pub m4: Gpk,
+}
+
+impl PairClass {
+    /// taxonomy_histogram の添字: m4 × 3 + m6（Kill=0, Propagate=1, Generate=2）
+    pub fn index(self) -> usize {
+        self.m4 as usize * 3 + self.m6 as usize
+    }
+
+    /// index の逆。index >= 9 なら None
+    pub fn from_index(index: usize) -> Option<Self> {
+        const ORDER: [Gpk; 3] = [Gpk::Kill, Gpk::Propagate, Gpk::Generate];
+        (index < 9).then(|| PairClass { m6: ORDER[index % 3], m4: ORDER[index / 3] })
+    }
+
+    /// 2段を直列合成した3分類（pair_gpk と同じ）: m4段が G か、m4段が P で m6段が G なら G、
+    /// 両段 P なら P、それ以外は K
+    pub fn merged(self) -> Gpk {
+        match (self.m4, self.m6) {
+            (Gpk::Generate, _) | (Gpk::Propagate, Gpk::Generate) => Gpk::Generate,
+            (Gpk::Propagate, Gpk::Propagate) => Gpk::Propagate,
+            _ => Gpk::Kill,
+        }
+    }
+}
+
+/// 参照ビットペアからペアの詳細分類（段ごとの GPK の組）を計算する
+#[inline]
+pub fn pair_taxonomy(p_r: u8, q_r: u8, p_l: u8, q_l: u8) -> PairClass {
+    PairClass { m6: stage_gpk(p_r, q_r), m4: stage_gpk(p_l, q_l) }
+}
+
+/// n を x で1ステップ進めるときの有効ペア（0..pair_count）を詳細分類で数える。
+/// 添字は PairClass::index。merged で潰して足すと collatz_step の g/p/k_count に一致する。
+/// x は x-1 が 2 の冪（x >= 3）であること。
+pub fn taxonomy_histogram(n: &PairNumber, x: u64) -> [u64; 9] {
+    let rp = RefPattern::new(x);
+    let mut hist = [0u64; 9];
+    for i in 0..n.pair_count() as isize {
+        let (p_r, q_r) = rp.ref_r(n, i, n.get_m6(i));
+        let (p_l, q_l) = rp.ref_l(n, i, n.get_m4(i));
+        hist[pair_taxonomy(p_r, q_r, p_l, q_l).index()] += 1;
+    }
+    hist
+}
+
 /// 汎用 collatz_step: T(n) = (xn+1) / 2^d
 /// x は x-1 が2の冪であること。x ∈ {3, 5, 9, 17, ...}
-/// n は奇数であること。
+/// x=1（x-1=0 の退化した場合、(n+1)/2^d）は collatz_step_1n1 に回す。
+/// n は奇数であること（debug ビルドでは検査する）。
+/// x が奇数なので n が偶数だと xn+1 は奇数になり d=0 となる。これは奇数→奇数写像
+/// としては誤った結果なので、前提違反は呼び出し側の責任とする。
+/// 定数項を +1 以外に一般化したアフィン写像 (xn+b)/2^d（collatz_step_general）では、
+/// xn+b が偶数になる条件が b の偶奇に依存するため、この前提も変わる。
 pub fn collatz_step(n: &PairNumber, x: u64) -> StepResult {
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
+    if x == 1 {
+        return collatz_step_1n1(n);
+    }
+    collatz_step_injected(n, x, inject_constant(1))
+}
+
+/// x に応じた逐次ステップ（x=3, 5 は専用版、それ以外は collatz_step）。
+pub fn collatz_step_for_x(n: &PairNumber, x: u64) -> StepResult {
+    match x {
+        3 => collatz_step_3n1(n),
+        5 => collatz_step_5n1(n),
+        _ => collatz_step(n, x),
+    }
+}
+
+/// アフィン写像の1ステップ: T(n) = (xn+b) / 2^d（b は任意の u64 定数）。
+/// b は複数の下位ペアにまたがる加数として2段加算器に注入する（inject_constant）。
+/// b=1 なら collatz_step と同じ結果。x は x-1 が 2 の冪（x >= 3）であること。
+/// n が奇数でも b が偶数なら xn+b は奇数で d=0 になる（奇数→奇数写像にするには b を奇数にする）。
+/// GPK は xn 側の参照ビットだけで分類する（b の注入はキャリーとして扱い、分類には入れない）。
+pub fn collatz_step_general(n: &PairNumber, x: u64, b: u64) -> StepResult {
+    collatz_step_injected(n, x, inject_constant(b))
+}
+
+/// 定数 b をペアごとの加数に分けたもの。ペア i の m6段に m6 の i ビット目、
+/// m4段に m4 の i ビット目を足す（b の偶数ビット / 奇数ビット）。
+#[derive(Debug, Clone, Copy)]
+struct ConstantInjection {
+    m6: u64,
+    m4: u64,
+    /// 加数が残っているペア数（これ以降のペアには何も足さない）
+    pairs: usize,
+    /// b のビット長
+    bits: usize,
+}
+
+/// 定数 b を2段加算器への注入の形に分ける。b=1 はペア 0 の m6段への初期キャリー 1 と同じ。
+fn inject_constant(b: u64) -> ConstantInjection {
+    let bits = (64 - b.leading_zeros()) as usize;
+    ConstantInjection {
+        m6: compact_even_bits(b),
+        m4: compact_even_bits(b >> 1),
+        pairs: bits.div_ceil(2),
+        bits,
+    }
+}
+
+/// collatz_step / collatz_step_general の本体。各ペアの2段に定数のビットを足しながら走査する。
+/// 定数が 1 ビットを超えるとキャリーは 2 まで取りうる（p + q + c + b ≤ 5 なので 2 を超えない）。
+fn collatz_step_injected(n: &PairNumber, x: u64, inject: ConstantInjection) -> StepResult {
     let rp = RefPattern::new(x);
     let k = n.pair_count();
 
-    // オーバーフロー分を含む最大インデックス
-    let max_i = k + ((rp.s as usize + 1) / 2);
+    // オーバーフロー分を含む最大インデックス。xn+b < 2^(2k+s+1) + 2^bits(b) なので、
+    // b が xn より短ければ従来どおり k + ceil(s/2)、長ければ b のビット長 + 1 ビット分
+    let max_i = (k + ((rp.s as usize + 1) / 2)).max((inject.bits + 1).div_ceil(2) - 1);
 
     let out_pair_count = max_i + 1;
     let out_word_count = (out_pair_count + 63) / 64;
     let mut new_m4 = vec![0u64; out_word_count];
     let mut new_m6 = vec![0u64; out_word_count];
     let mut gpk_info = GpkInfo::new(k);
-    let mut c: u8 = 1; // 初期キャリー = 1 (+1 の効果)
+    // 初期キャリーは 0。b=1 の +1 は inject.m6 のペア 0 から入る
+    let mut c: u8 = 0;
 
     let mut actual_pairs = 0usize;
 
@@ -261,13 +478,20 @@ pub fn collatz_step(n: &PairNumber, x: u64) -> StepResult {
             gpk_info.set_gpk(i, pair_gpk(p_r, q_r, p_l, q_l));
         }
 
+        // 定数 b のこのペアの加数
+        let (b_r, b_l) = if i < inject.pairs {
+            (((inject.m6 >> i) & 1) as u8, ((inject.m4 >> i) & 1) as u8)
+        } else {
+            (0, 0)
+        };
+
         // m6段
-        let sum_r = p_r + q_r + c;
+        let sum_r = p_r + q_r + c + b_r;
         let m6_bit = (sum_r & 1) as u64;
         let c_mid = sum_r >> 1;
 
         // m4段
-        let sum_l = p_l + q_l + c_mid;
+        let sum_l = p_l + q_l + c_mid + b_l;
         let m4_bit = (sum_l & 1) as u64;
         c = sum_l >> 1;
 
@@ -278,11 +502,18 @@ pub fn collatz_step(n: &PairNumber, x: u64) -> StepResult {
         actual_pairs = i + 1;
 
         // 早期終了: キャリー消滅 かつ 参照パターンの後方参照も範囲外
-        let safe_end = k + (rp.s as usize).saturating_sub(1) / 2;
+        // 次ペア i+1 以降の参照ビットが全て 0 になる条件:
+        //   s偶数 (t=s/2):     b[i+1-t], a[i+1-t] が範囲外 ⇔ i >= k+t-1 = k+(s-1)/2
+        //   s奇数 (t=(s-1)/2): a[i-t] (= a[(i+1)-t-1]) が範囲外 ⇔ i >= k+t = k+(s-1)/2
+        // いずれも safe_end = k + ⌊(s-1)/2⌋ に一致し、c=0 なら以降の出力ペアは全て (0,0)。
+        // 定数 b の加数も使い切っていること
+        let safe_end = (k + (rp.s as usize).saturating_sub(1) / 2).max(inject.pairs.saturating_sub(1));
         if c == 0 && i >= safe_end {
             break;
         }
     }
+    // 確保したペア数を超える桁上がりがないこと（出力ペア数の見積もりが足りないと上位が黙って失われる）
+    debug_assert!(actual_pairs <= out_pair_count && c == 0, "carry overflow past out_pairs={}", out_pair_count);
 
     gpk_info.finalize();
 
@@ -304,16 +535,193 @@ pub fn collatz_step(n: &PairNumber, x: u64) -> StepResult {
     }
 }
 
-/// x=3 専用の最適化版。
-/// s=1, t=0, s奇数。
-/// ref_R(i) = (a[i-1], b[i])
-/// ref_L(i) = (b[i], a[i])  ← 現ペアそのもの
-pub fn collatz_step_3n1(n: &PairNumber) -> StepResult {
+/// 同じ n を複数の x で 1 ステップずつ進める（x ごとの比較研究向け）。
+/// 巨大な n では from_biguint の変換が支配的なので、変換済みの n を共有し、
+/// xs 方向を rayon で並列化する。戻り値は xs と同じ順序。
+/// 各 x のステップは collatz_step_for_x で計算する。n は奇数であること。
+pub fn multi_x_step(n: &PairNumber, xs: &[u64]) -> Vec<StepResult> {
+    xs.par_iter().map(|&x| collatz_step_for_x(n, x)).collect()
+}
+
+/// 偶数領域の軌道: 各ステップの割る前の値 xn+1（raw_even）を順に並べる。
+/// 奇数軌道 n0 → n1 → ... に対し [x·n0+1, x·n1+1, ...] を返し、1 に到達したステップで止まる
+/// （1 に落ちる xn+1 も含む）。のこぎり状の成長の可視化向け。
+/// start は奇数であること。偶数、または 1 なら空。
+pub fn even_trajectory(start: &BigUint, x: u64, max_steps: u64) -> Vec<PairNumber> {
+    let mut evens = Vec::new();
+    if start.is_one() || !start.bit(0) {
+        return evens;
+    }
+
+    let mut n = PairNumber::from_biguint(start);
+    for _ in 0..max_steps {
+        let result = collatz_step_for_x(&n, x);
+        evens.push(result.raw_even());
+        if result.next.is_one() {
+            break;
+        }
+        n = result.next;
+    }
+    evens
+}
+
+/// 軌道の交換ビット列（各ステップの d mod 2）だけを求める最小カーネル。2-adic 解析向け。
+/// 戻り値は (ビット列, ステップ数, 1 に到達したか)。ステップ i の d mod 2 はワード i / 64 の
+/// ビット i % 64（LSB first）で、ステップ数を超える上位ビットは 0。最後のステップが偶数の d に
+/// なることが多く末尾の 0 と区別できないので、ステップ数も返す。
+/// 値は u128 で保持し、x·n+1 が u128 を超えたらそれ以降はパックド走査（GPK 収集なし）で続ける。
+/// n は奇数であること。n = 1 なら空。
+pub fn exchange_stream_u64(n: u64, x: u64, max_steps: u64) -> (Vec<u64>, u64, bool) {
+    debug_assert!(n & 1 == 1, "exchange_stream_u64 requires odd input");
+    let mut words = Vec::new();
+    let mut word = 0u64;
+    let mut steps = 0u64;
+    let mut push = |odd: bool, steps: &mut u64| {
+        word |= (odd as u64) << (*steps % 64);
+        *steps += 1;
+        if (*steps).is_multiple_of(64) {
+            words.push(word);
+            word = 0;
+        }
+    };
+
+    let x128 = x as u128;
+    let limit = (u128::MAX - 1) / x128;
+    let mut v = n as u128;
+    while v != 1 && steps < max_steps && v <= limit {
+        let xn1 = v * x128 + 1;
+        let d = xn1.trailing_zeros();
+        push(d & 1 == 1, &mut steps);
+        v = xn1 >> d;
+    }
+    let mut reached_one = v == 1;
+    if !reached_one && steps < max_steps {
+        let mut pn = PairNumber::from_biguint(&BigUint::from(v));
+        while steps < max_steps {
+            let result = packed::step_for_x(&pn, x, false);
+            push(result.exchanged, &mut steps);
+            pn = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+            if pn.is_one() {
+                reached_one = true;
+                break;
+            }
+        }
+    }
+    if !steps.is_multiple_of(64) {
+        words.push(word);
+    }
+    (words, steps, reached_one)
+}
+
+/// 次の奇数 (xn+1)/2^d のビット長の見積もり（走査せずに求める）。
+/// L = bit_len(n)、x = 2^s + 1 とすると 2^(L+s-1) < xn+1 < 2^(L+s+1) なので
+/// bit_len(xn+1) は L+s か L+s+1。d は n の下位 64 ビットだけで xn+1 の末尾ゼロ数を
+/// 数えて求める（64 以上なら 64 で打ち切り）。
+/// 戻り値 L + s + 1 - d は常に上界で、d が 64 未満なら真値との差は高々 1。
+/// u128 / U256 / パックドのどのフェーズで処理するかの判断や出力バッファの事前確保向け。
+pub fn estimate_next_bitlen(n: &PairNumber, x: u64) -> usize {
+    let rp = RefPattern::new(x);
+    let low = low_u64(n);
+    let d = x.wrapping_mul(low).wrapping_add(1).trailing_zeros() as usize;
+    (n.bit_len() + rp.s as usize + 1).saturating_sub(d)
+}
+
+/// n の下位 64 ビット（m6_words[0] / m4_words[0] の下位 32 ペアを交互に並べる）
+fn low_u64(n: &PairNumber) -> u64 {
+    let (b, a) = match (n.m6_words().first(), n.m4_words().first()) {
+        (Some(&b), Some(&a)) => (b, a),
+        _ => return 0,
+    };
+    let mut v = 0u64;
+    for i in 0..32 {
+        v |= ((b >> i) & 1) << (2 * i);
+        v |= ((a >> i) & 1) << (2 * i + 1);
+    }
+    v
+}
+
+/// collatz_step_trace の 1 ペア分の中間値（教材用の可視化向け）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairTraceRow {
+    /// ペア番号
+    pub i: usize,
+    /// m6段の参照ビット
+    pub p_r: u8,
+    pub q_r: u8,
+    /// ペアへの入力キャリー
+    pub c_in: u8,
+    /// m6段の和ビット（= 出力 m6）
+    pub sum_r: u8,
+    /// m6段 → m4段のキャリー
+    pub c_mid: u8,
+    /// m4段の参照ビット
+    pub p_l: u8,
+    pub q_l: u8,
+    /// m4段の和ビット（= 出力 m4）
+    pub sum_l: u8,
+    /// ペアからの出力キャリー
+    pub c_out: u8,
+    /// ペアの GPK 分類
+    pub gpk: Gpk,
+}
+
+/// collatz_step_trace が受け付ける最大ペア数（行数が pair_count に比例するため）
+pub const TRACE_MAX_PAIRS: usize = 4096;
+
+/// xn+1 の加算をペアごとに追跡し、collatz_step が捨てている中間値を行として返す。
+/// 行は collatz_step と同じ範囲（キャリー消滅で早期終了）まで出力する。
+/// 小さい数専用で、pair_count が TRACE_MAX_PAIRS を超えると panic する。
+pub fn collatz_step_trace(n: &PairNumber, x: u64) -> Vec<PairTraceRow> {
+    assert!(n.pair_count() <= TRACE_MAX_PAIRS, "collatz_step_trace is for small numbers (pair_count <= {})", TRACE_MAX_PAIRS);
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
+    let rp = RefPattern::new(x);
     let k = n.pair_count();
-    let max_i = k + 1;
+    let max_i = k + (rp.s as usize).div_ceil(2);
+    let safe_end = k + (rp.s as usize).saturating_sub(1) / 2;
+
+    let mut rows = Vec::with_capacity(max_i + 1);
+    let mut c: u8 = 1;
+
+    for i in 0..=max_i {
+        let ii = i as isize;
+        let (p_r, q_r) = rp.ref_r(n, ii, n.get_m6(ii));
+        let (p_l, q_l) = rp.ref_l(n, ii, n.get_m4(ii));
+
+        let c_in = c;
+        let sum_r = p_r + q_r + c_in;
+        let c_mid = sum_r >> 1;
+        let sum_l = p_l + q_l + c_mid;
+        c = sum_l >> 1;
+
+        rows.push(PairTraceRow {
+            i,
+            p_r, q_r, c_in,
+            sum_r: sum_r & 1,
+            c_mid,
+            p_l, q_l,
+            sum_l: sum_l & 1,
+            c_out: c,
+            gpk: pair_gpk(p_r, q_r, p_l, q_l),
+        });
+
+        if c == 0 && i >= safe_end {
+            break;
+        }
+    }
+
+    rows
+}
+
+/// x=1 の退化した場合: T(n) = (n+1) / 2^d。
+/// 参照パターンは無く（x-1=0 で乗算項が消える）、各ペアに初期キャリー +1 を流すだけ。
+/// m6段 = b[i] + c, m4段 = a[i] + c_mid なので、GPK は G が現れず、(1,1) ペアが P、それ以外が K。
+pub fn collatz_step_1n1(n: &PairNumber) -> StepResult {
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
+    let k = n.pair_count();
+    let max_i = k;
 
     let out_pair_count = max_i + 1;
-    let out_word_count = (out_pair_count + 63) / 64;
+    let out_word_count = out_pair_count.div_ceil(64);
     let mut new_m4 = vec![0u64; out_word_count];
     let mut new_m6 = vec![0u64; out_word_count];
     let mut gpk_info = GpkInfo::new(k);
@@ -324,20 +732,19 @@ pub fn collatz_step_3n1(n: &PairNumber) -> StepResult {
     for i in 0..=max_i {
         let ai = n.get_m4(i as isize);
         let bi = n.get_m6(i as isize);
-        let a_prev = n.get_m4(i as isize - 1);
 
-        // GPK: ref_R=(a_prev, bi), ref_L=(bi, ai)
+        // GPK: ref_R=(b[i], 0), ref_L=(a[i], 0)
         if i < k {
-            gpk_info.set_gpk(i, pair_gpk(a_prev, bi, bi, ai));
+            gpk_info.set_gpk(i, pair_gpk(bi, 0, ai, 0));
         }
 
-        // m6段: a[i-1] + b[i] + c
-        let sum_r = a_prev + bi + c;
+        // m6段: b[i] + c
+        let sum_r = bi + c;
         let m6_bit = (sum_r & 1) as u64;
         let c_mid = sum_r >> 1;
 
-        // m4段: b[i] + a[i] + c_mid
-        let sum_l = bi + ai + c_mid;
+        // m4段: a[i] + c_mid
+        let sum_l = ai + c_mid;
         let m4_bit = (sum_l & 1) as u64;
         c = sum_l >> 1;
 
@@ -347,10 +754,11 @@ pub fn collatz_step_3n1(n: &PairNumber) -> StepResult {
         new_m4[word_idx] |= m4_bit << bit_idx;
         actual_pairs = i + 1;
 
-        if c == 0 && i >= k {
+        if c == 0 && i + 1 >= k {
             break;
         }
     }
+    debug_assert!(actual_pairs <= out_pair_count && c == 0, "carry overflow past out_pairs={}", out_pair_count);
 
     gpk_info.finalize();
 
@@ -370,11 +778,260 @@ pub fn collatz_step_3n1(n: &PairNumber) -> StepResult {
     }
 }
 
+/// x=3 専用の最適化版。
+/// s=1, t=0, s奇数。
+/// ref_R(i) = (a[i-1], b[i])
+/// ref_L(i) = (b[i], a[i])  ← 現ペアそのもの
+pub fn collatz_step_3n1(n: &PairNumber) -> StepResult {
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
+    if is_all_ones(n) {
+        return collatz_step_3n1_all_ones(n);
+    }
+    let mut gpk_info = GpkInfo::new(n.pair_count());
+    let (new_m4, new_m6, actual_pairs) = scan_3n1(n, Some(&mut gpk_info));
+    gpk_info.finalize();
+
+    let raw_m4 = new_m4.clone();
+    let raw_m6 = new_m6.clone();
+    let raw_pair_count = actual_pairs;
+
+    let pp = postprocess::postprocess(new_m4, new_m6, actual_pairs);
+    StepResult {
+        next: pp.next,
+        d: pp.d,
+        exchanged: pp.exchanged,
+        gpk: gpk_info,
+        raw_m4,
+        raw_m6,
+        raw_pair_count,
+    }
+}
+
+/// 定理5.1 の実行時検査: x=3 の m4段 GPK が入力ペア自身の述語
+/// G=m2(AND), P=m7(XOR), K=m9(NOR) に一致するか。
+/// 実際の走査（collatz_step_3n1）が出したペア GPK マスクと xn+1（postprocess 前の偶数状態）を、
+/// m4段を m2/m7/m9 に置き換えて組み立て直したものと照合する（m6段は x=3 の ref_R = (a[i-1], b[i])）。
+/// ペア GPK は m4段と m6段の直列合成、m4段の和ビットは P ^ c_mid、キャリーは G | (P & c_mid) なので、
+/// 定理が成り立つときに限り走査結果が再現できる。1 ペアでも食い違えば false。
+pub fn verify_theorem_5_1(n: &PairNumber) -> bool {
+    let step = collatz_step_3n1(n);
+    theorem_5_1_matches(n, &step.gpk.g_masks, &step.gpk.p_masks, &step.raw_m4, &step.raw_m6, step.raw_pair_count)
+}
+
+/// verify_theorem_5_1 の照合本体。走査結果を引数で受け取るので、壊した結果で false になることを試せる
+fn theorem_5_1_matches(
+    n: &PairNumber,
+    g_masks: &[u64],
+    p_masks: &[u64],
+    raw_m4: &[u64],
+    raw_m6: &[u64],
+    raw_pairs: usize,
+) -> bool {
+    let word = |v: &[u64], w: usize| v.get(w).copied().unwrap_or(0);
+    let bit = |v: &[u64], i: usize| (word(v, i / 64) >> (i % 64)) & 1;
+    let (a, b) = (n.predicate_words(4), n.predicate_words(6));
+    let (g4, p4, k4) = (n.predicate_words(2), n.predicate_words(7), n.predicate_words(9));
+
+    // ペア GPK: m4段 (m2, m7) と m6段 (a[i-1], b[i]) の直列合成。m2/m7/m9 は生きているペアを3分割する
+    for w in 0..a.len() {
+        let live = g4[w] | p4[w] | k4[w];
+        if g4[w] & p4[w] != 0 || g4[w] & k4[w] != 0 || p4[w] & k4[w] != 0 {
+            return false;
+        }
+        let a_prev = (a[w] << 1) | if w > 0 { a[w - 1] >> 63 } else { 0 };
+        let (g6, p6) = (a_prev & b[w], a_prev ^ b[w]);
+        if word(g_masks, w) != (g4[w] | (p4[w] & g6)) & live || word(p_masks, w) != p4[w] & p6 & live {
+            return false;
+        }
+    }
+
+    // xn+1 の各ペア: m6段は a[i-1] + b[i] + c、m4段は m7 と m2 だけで和とキャリーが決まる
+    let mut c = 1u64;
+    for i in 0..raw_pairs {
+        let a_prev = if i == 0 { 0 } else { bit(&a, i - 1) };
+        let b_i = bit(&b, i);
+        let sum_r = a_prev ^ b_i ^ c;
+        let c_mid = (a_prev & b_i) | (c & (a_prev ^ b_i));
+        let sum_l = bit(&p4, i) ^ c_mid;
+        c = bit(&g4, i) | (bit(&p4, i) & c_mid);
+        if bit(raw_m6, i) != sum_r || bit(raw_m4, i) != sum_l {
+            return false;
+        }
+    }
+    c == 0
+}
+
+/// n = 2^(2k) - 1（全ペアが (1,1)）かどうか。最初の飽和していないワードで打ち切る。
+fn is_all_ones(n: &PairNumber) -> bool {
+    let k = n.pair_count();
+    let full = k / 64;
+    let rem = k % 64;
+    let (m4, m6) = (n.m4_words(), n.m6_words());
+    if m4.len() < k.div_ceil(64) || m6.len() < k.div_ceil(64) {
+        return false;
+    }
+    if !(m4[..full].iter().all(|&w| w == u64::MAX) && m6[..full].iter().all(|&w| w == u64::MAX)) {
+        return false;
+    }
+    if rem == 0 {
+        return true;
+    }
+    let mask = (1u64 << rem) - 1;
+    m4[full] & mask == mask && m6[full] & mask == mask
+}
+
+/// ワード列のペア [lo, hi) のビットを立てる
+fn fill_ones(words: &mut [u64], lo: usize, hi: usize) {
+    for (w, word) in words.iter_mut().enumerate().take(hi.div_ceil(64)).skip(lo / 64) {
+        let base = w * 64;
+        let mut mask = u64::MAX;
+        if lo > base {
+            mask &= u64::MAX << (lo - base);
+        }
+        if hi < base + 64 {
+            mask &= (1u64 << (hi - base)) - 1;
+        }
+        *word |= mask;
+    }
+}
+
+/// collatz_step_3n1 の全ペア (1,1) 用の解析解（走査なし）。
+/// n = 2^L - 1 (L = 2k) なら 3n+1 = 3·2^L - 2 = 2^(L+1) + 2^L - 2 で、
+/// ビット 1..L-1 と L+1 が 1。d = 1 で n' = 3·2^(L-1) - 1（ビット 0..L-2 と L が 1）。
+/// GPK は全ペア G（ペア 0 も m4段で生成する）なので最大キャリー伝播距離は k。
+fn collatz_step_3n1_all_ones(n: &PairNumber) -> StepResult {
+    let k = n.pair_count();
+
+    // 偶数状態 3n+1: m4 はペア 0..=k、m6 はペア 1..k-1
+    let out_word_count = (k + 2).div_ceil(64);
+    let mut raw_m4 = vec![0u64; out_word_count];
+    let mut raw_m6 = vec![0u64; out_word_count];
+    fill_ones(&mut raw_m4, 0, k + 1);
+    fill_ones(&mut raw_m6, 1, k);
+
+    // n' = (3n+1)/2: m6 はペア 0..=k、m4 はペア 0..k-2
+    let next_word_count = (k + 1).div_ceil(64);
+    let mut next_m4 = vec![0u64; next_word_count];
+    let mut next_m6 = vec![0u64; next_word_count];
+    fill_ones(&mut next_m6, 0, k + 1);
+    fill_ones(&mut next_m4, 0, k.saturating_sub(1));
+
+    let mut gpk = GpkInfo::new(k);
+    fill_ones(&mut gpk.g_masks, 0, k);
+    gpk.g_count = k as u32;
+    gpk.max_carry_chain = k as u32;
+    gpk.full_carry_propagation = true;
+
+    StepResult {
+        next: PairNumber::from_packed(next_m4, next_m6, k + 1),
+        d: 1,
+        exchanged: true,
+        gpk,
+        raw_m4,
+        raw_m6,
+        raw_pair_count: k + 1,
+    }
+}
+
+/// x=3 の逆写像: (3n+1)/2^d = m となる奇数 n を求める（2-adic 逆ステップ）。
+/// m は奇数、d >= 1 が前提。v = m·2^d − 1 が 3 で割り切れ、商が奇数のときだけ Some。
+///
+/// ペア領域での計算:
+/// 1. v = m·2^d − 1 は m が奇数なので「下位 d ビットが全て 1、その上に m の bit 0 を落としたもの」
+/// 2. 4 ≡ 1 (mod 3)・2 ≡ −1 (mod 3) より v mod 3 = popcount(m6) − popcount(m4) (mod 3)
+/// 3. 3n = 4n − n より 4n = v + n。ペア i の値（4進1桁）を下位から
+///    n[i] = (n[i-1] − v[i] − c) mod 4, c ← (v[i] + n[i] + c) / 4 で決める
+pub fn t_inverse_3n1(m: &PairNumber, d: u64) -> Option<PairNumber> {
+    if d == 0 || m.lsb() == 0 {
+        return None;
+    }
+
+    // 1. v = m·2^d − 1（減算のボローは下位 d ビットに閉じる）
+    let mut bits = vec![1u8; d as usize];
+    let mut m_bits = m.to_bits_lsb();
+    m_bits[0] = 0;
+    bits.extend_from_slice(&m_bits);
+    let v = PairNumber::from_bits_lsb(&bits);
+
+    // 2. 3 の倍数判定（ワード単位の popcount）
+    if v.mod3() != 0 {
+        return None;
+    }
+
+    // 3. ペア単位の正確な ÷3（n < v なので n のペア数は v 以下）
+    let k = v.pair_count();
+    let word_count = k.div_ceil(64);
+    let mut new_m4 = vec![0u64; word_count];
+    let mut new_m6 = vec![0u64; word_count];
+    let mut prev: u8 = 0;
+    let mut c: u8 = 0;
+    let mut actual_pairs = 1usize;
+
+    for i in 0..k {
+        let vi = 2 * v.get_m4(i as isize) + v.get_m6(i as isize);
+        let ni = prev.wrapping_sub(vi).wrapping_sub(c) & 3;
+        c = (vi + ni + c) >> 2;
+        prev = ni;
+
+        let word_idx = i / 64;
+        let bit_idx = i % 64;
+        new_m6[word_idx] |= ((ni & 1) as u64) << bit_idx;
+        new_m4[word_idx] |= ((ni >> 1) as u64) << bit_idx;
+        if ni != 0 {
+            actual_pairs = i + 1;
+        }
+    }
+
+    new_m4.truncate(actual_pairs.div_ceil(64));
+    new_m6.truncate(actual_pairs.div_ceil(64));
+    let n = PairNumber::from_packed(new_m4, new_m6, actual_pairs);
+    if n.lsb() == 0 {
+        return None;
+    }
+    Some(n)
+}
+
+/// 前後に辿れる1ステップ（step_navigable の戻り値）。
+/// 既知の遷移 n → next の d を覚えているので、back で常に n に戻れる。
+#[derive(Debug, Clone)]
+pub struct NavStep {
+    /// 次の奇数 n'
+    pub next: PairNumber,
+    /// 末尾ゼロ数 d
+    pub d: u64,
+    /// 交換が発生したか (d が奇数)
+    pub exchanged: bool,
+    /// 写像の乗数
+    pub x: u64,
+}
+
+impl NavStep {
+    /// next（このステップの行き先）から元の n = (next·2^d − 1) / x に戻る。
+    /// x=3 はペア領域の逆写像 t_inverse_3n1、それ以外は BigUint で割る。
+    /// next がこのステップの行き先でなく割り切れない場合は panic する。
+    pub fn back(&self, next: &PairNumber) -> PairNumber {
+        if self.x == 3 {
+            return t_inverse_3n1(next, self.d).expect("next is not the target of this step");
+        }
+        let v = (next.to_biguint() << self.d) - 1u32;
+        assert!((&v % self.x).is_zero(), "next is not the target of this step");
+        PairNumber::from_biguint(&(v / self.x))
+    }
+}
+
+/// 前後に辿れる1ステップ: collatz_step の next・d・交換に x を添えて返す。
+/// 軌道を前後に行き来する探索 UI で、back で1つ前の値に戻るのに使う。
+pub fn step_navigable(n: &PairNumber, x: u64) -> NavStep {
+    let r = collatz_step(n, x);
+    NavStep { next: r.next, d: r.d, exchanged: r.exchanged, x }
+}
+
 /// x=5 専用の最適化版。
 /// s=2, t=1, s偶数。
 /// ref_R(i) = (b[i-1], b[i])
 /// ref_L(i) = (a[i-1], a[i])
 pub fn collatz_step_5n1(n: &PairNumber) -> StepResult {
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
     let k = n.pair_count();
     let max_i = k + 1;
 
@@ -418,6 +1075,7 @@ pub fn collatz_step_5n1(n: &PairNumber) -> StepResult {
             break;
         }
     }
+    debug_assert!(actual_pairs <= out_pair_count && c == 0, "carry overflow past out_pairs={}", out_pair_count);
 
     gpk_info.finalize();
 
@@ -436,3 +1094,573 @@ pub fn collatz_step_5n1(n: &PairNumber) -> StepResult {
         raw_pair_count,
     }
 }
+
+/// x=3 の2ステップ融合版: T(T(n))。
+/// 1ステップ目の偶数状態 3n+1 を正規化（MSBトリム・右シフト・再ペア化）せず、
+/// 2ステップ目の走査で 3n+1 のビット列を d₁ ビットずらして直接読む。
+/// MSBトリムと postprocess は最後に1回だけ行う。
+///
+/// 戻り値の d は d₁ + d₂、exchanged は d₁ + d₂ の偶奇、
+/// gpk と raw_* は2ステップ目のもの。
+pub fn fused_two_step_3n1(n: &PairNumber) -> StepResult {
+    debug_assert!(n.lsb() == 1, "collatz_step requires odd input");
+    let first = scan_3n1(n, None);
+    let raw_m4 = &first.0;
+    let raw_m6 = &first.1;
+    let raw_pairs = first.2;
+
+    // 偶数状態のファスナービット: bit[2i] = m6[i], bit[2i+1] = m4[i]
+    let raw_bit = |pos: usize| -> u8 {
+        let pair = pos / 2;
+        if pair >= raw_pairs {
+            return 0;
+        }
+        let words = if pos % 2 == 1 { raw_m4 } else { raw_m6 };
+        ((words[pair / 64] >> (pair % 64)) & 1) as u8
+    };
+
+    // 3n+1 の末尾ゼロ数とビット長（走査範囲の決定のみ、値の再構成はしない）
+    let mut raw_bits = 2 * raw_pairs;
+    while raw_bits > 0 && raw_bit(raw_bits - 1) == 0 {
+        raw_bits -= 1;
+    }
+    let mut d1 = 0usize;
+    while d1 < raw_bits && raw_bit(d1) == 0 {
+        d1 += 1;
+    }
+
+    // n' = (3n+1) >> d₁ のペア i: b[i] = bit[2i+d₁], a[i] = bit[2i+1+d₁]
+    let k = (raw_bits - d1).div_ceil(2);
+    let get_a = |i: isize| -> u8 {
+        if i < 0 || i as usize >= k { 0 } else { raw_bit(2 * i as usize + 1 + d1) }
+    };
+    let get_b = |i: isize| -> u8 {
+        if i < 0 || i as usize >= k { 0 } else { raw_bit(2 * i as usize + d1) }
+    };
+
+    let max_i = k + 1;
+    let out_word_count = (max_i + 1).div_ceil(64);
+    let mut new_m4 = vec![0u64; out_word_count];
+    let mut new_m6 = vec![0u64; out_word_count];
+    let mut gpk_info = GpkInfo::new(k);
+    let mut c: u8 = 1;
+    let mut actual_pairs = 0usize;
+
+    for i in 0..=max_i {
+        let ai = get_a(i as isize);
+        let bi = get_b(i as isize);
+        let a_prev = get_a(i as isize - 1);
+
+        if i < k {
+            gpk_info.set_gpk(i, pair_gpk(a_prev, bi, bi, ai));
+        }
+
+        let sum_r = a_prev + bi + c;
+        let m6_bit = (sum_r & 1) as u64;
+        let c_mid = sum_r >> 1;
+
+        let sum_l = bi + ai + c_mid;
+        let m4_bit = (sum_l & 1) as u64;
+        c = sum_l >> 1;
+
+        new_m6[i / 64] |= m6_bit << (i % 64);
+        new_m4[i / 64] |= m4_bit << (i % 64);
+        actual_pairs = i + 1;
+
+        if c == 0 && i >= k {
+            break;
+        }
+    }
+    debug_assert!(actual_pairs <= max_i + 1 && c == 0, "carry overflow past out_pairs={}", max_i + 1);
+
+    gpk_info.finalize();
+
+    let raw_m4_out = new_m4.clone();
+    let raw_m6_out = new_m6.clone();
+
+    let pp = postprocess::postprocess(new_m4, new_m6, actual_pairs);
+    let d = d1 as u64 + pp.d;
+    StepResult {
+        next: pp.next,
+        d,
+        exchanged: d % 2 == 1,
+        gpk: gpk_info,
+        raw_m4: raw_m4_out,
+        raw_m6: raw_m6_out,
+        raw_pair_count: actual_pairs,
+    }
+}
+
+/// x=3 の走査本体（postprocess なし）。偶数状態 3n+1 の (m4, m6, ペア数) を返す。
+/// gpk が Some ならペア 0..pair_count の GPK を書き込む（finalize は呼び出し側）。
+/// collatz_step_3n1 と fused_two_step_3n1 の1ステップ目が共用する。
+fn scan_3n1(n: &PairNumber, mut gpk: Option<&mut GpkInfo>) -> (Vec<u64>, Vec<u64>, usize) {
+    let k = n.pair_count();
+    let max_i = k + 1;
+    let out_pair_count = max_i + 1;
+    let out_word_count = out_pair_count.div_ceil(64);
+    let mut new_m4 = vec![0u64; out_word_count];
+    let mut new_m6 = vec![0u64; out_word_count];
+    let mut c: u8 = 1;
+    let mut actual_pairs = 0usize;
+
+    for i in 0..=max_i {
+        let ai = n.get_m4(i as isize);
+        let bi = n.get_m6(i as isize);
+        let a_prev = n.get_m4(i as isize - 1);
+
+        // GPK: ref_R=(a_prev, bi), ref_L=(bi, ai)
+        if let Some(gpk) = gpk.as_deref_mut() {
+            if i < k {
+                gpk.set_gpk(i, pair_gpk(a_prev, bi, bi, ai));
+            }
+        }
+
+        // m6段: a[i-1] + b[i] + c
+        let sum_r = a_prev + bi + c;
+        let c_mid = sum_r >> 1;
+
+        // m4段: b[i] + a[i] + c_mid
+        let sum_l = bi + ai + c_mid;
+        c = sum_l >> 1;
+
+        new_m6[i / 64] |= ((sum_r & 1) as u64) << (i % 64);
+        new_m4[i / 64] |= ((sum_l & 1) as u64) << (i % 64);
+        actual_pairs = i + 1;
+
+        if c == 0 && i >= k {
+            break;
+        }
+    }
+    debug_assert!(actual_pairs <= out_pair_count && c == 0, "carry overflow past out_pairs={}", out_pair_count);
+
+    (new_m4, new_m6, actual_pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use num_traits::One;
+
+    #[test]
+    fn test_estimate_next_bitlen() {
+        let mut inputs: Vec<BigUint> = (1u64..=4999).step_by(2).map(BigUint::from).collect();
+        let mut next = crate::reference::test_rng(0x9e37_79b9_7f4a_7c15u64);
+        for bits in [64u32, 100, 200, 1000] {
+            for _ in 0..50 {
+                inputs.push((BigUint::one() << bits) + BigUint::from(next() | 1));
+            }
+        }
+        for x in [3u64, 5, 9, 17, 129] {
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let est = estimate_next_bitlen(&pn, x);
+                let actual = collatz_step(&pn, x).next.to_biguint().bits() as usize;
+                assert!(est >= actual && est <= actual + 1, "{}n+1, n={}: est={} actual={}", x, n, est, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn test_t_inverse_3n1() {
+        let mut inputs: Vec<BigUint> = (1u64..=1999).step_by(2).map(BigUint::from).collect();
+        inputs.push((BigUint::one() << 200u32) + BigUint::from(27u64));
+        inputs.push((BigUint::one() << 127u32) - BigUint::one());
+        for n in &inputs {
+            let r = collatz_step_3n1(&PairNumber::from_biguint(n));
+            let back = t_inverse_3n1(&r.next, r.d).expect("preimage must exist for matching d");
+            assert_eq!(back.to_biguint(), *n);
+            assert_eq!(back, PairNumber::from_biguint(n));
+            // d+1: 2(3n+1) − 1 = 6n+1 は 3 の倍数でない
+            assert!(t_inverse_3n1(&r.next, r.d + 1).is_none());
+            // d+2: 4(3n+1) − 1 = 3(4n+1) なので 4n+1 が戻る
+            let n4 = t_inverse_3n1(&r.next, r.d + 2).unwrap();
+            assert_eq!(n4.to_biguint(), n * 4u64 + 1u64);
+        }
+        // 3 の倍数には逆像がない / 偶数・d=0 は対象外
+        let nine = PairNumber::from_biguint(&BigUint::from(9u64));
+        assert!((1..10).all(|d| t_inverse_3n1(&nine, d).is_none()));
+        assert!(t_inverse_3n1(&PairNumber::from_biguint(&BigUint::from(8u64)), 3).is_none());
+        assert!(t_inverse_3n1(&PairNumber::from_biguint(&BigUint::from(5u64)), 0).is_none());
+        // 1 = (3·1+1)/4, 1 = (3·5+1)/16
+        let one = PairNumber::from_biguint(&BigUint::one());
+        assert_eq!(t_inverse_3n1(&one, 2).unwrap().to_biguint(), BigUint::one());
+        assert_eq!(t_inverse_3n1(&one, 4).unwrap().to_biguint(), BigUint::from(5u64));
+        assert!(t_inverse_3n1(&one, 1).is_none());
+    }
+
+    /// n=27 (11011b), x=3 の手計算トレース: 3*27+1 = 82 (1010010b)
+    #[test]
+    fn test_collatz_step_trace_27() {
+        let rows = collatz_step_trace(&PairNumber::from_biguint(&BigUint::from(27u64)), 3);
+        // (i, p_r, q_r, c_in, sum_r, c_mid, p_l, q_l, sum_l, c_out, gpk)
+        let expected = [
+            (0, 0, 1, 1, 0, 1, 1, 1, 1, 1, Gpk::Generate),
+            (1, 1, 0, 1, 0, 1, 0, 1, 0, 1, Gpk::Propagate),
+            (2, 1, 1, 1, 1, 1, 1, 0, 0, 1, Gpk::Generate),
+            (3, 0, 0, 1, 1, 0, 0, 0, 0, 0, Gpk::Kill),
+        ];
+        let got: Vec<_> = rows
+            .iter()
+            .map(|r| (r.i, r.p_r, r.q_r, r.c_in, r.sum_r, r.c_mid, r.p_l, r.q_l, r.sum_l, r.c_out, r.gpk))
+            .collect();
+        assert_eq!(got, expected);
+
+        // 出力ビットを並べると xn+1 になる
+        let even: u64 = rows.iter().map(|r| ((r.sum_r as u64) << (2 * r.i)) | ((r.sum_l as u64) << (2 * r.i + 1))).sum();
+        assert_eq!(even, 82);
+    }
+
+    #[test]
+    fn test_collatz_step_trace_matches_step() {
+        for x in [3u64, 5, 9, 17] {
+            for n_val in (1u64..=999).step_by(2) {
+                let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+                let rows = collatz_step_trace(&pn, x);
+                let even: u128 = rows.iter().map(|r| ((r.sum_r as u128) << (2 * r.i)) | ((r.sum_l as u128) << (2 * r.i + 1))).sum();
+                assert_eq!(even, x as u128 * n_val as u128 + 1, "{}n+1, n={}", x, n_val);
+                assert!(rows.windows(2).all(|w| w[0].c_out == w[1].c_in));
+                let step = collatz_step(&pn, x);
+                assert_eq!(rows.len(), step.raw_pair_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fused_two_step_3n1() {
+        for n_val in (1u64..=9999).step_by(2) {
+            let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+            let s1 = collatz_step_3n1(&pn);
+            let s2 = collatz_step_3n1(&s1.next);
+            let fused = fused_two_step_3n1(&pn);
+            assert_eq!(fused.next, s2.next, "fused n'' mismatch for n={}", n_val);
+            assert_eq!(fused.d, s1.d + s2.d, "fused d mismatch for n={}", n_val);
+            assert_eq!(fused.gpk.to_seq(), s2.gpk.to_seq(), "fused GPK mismatch for n={}", n_val);
+        }
+    }
+
+    #[test]
+    fn test_gpk_rle_roundtrip() {
+        let pn = PairNumber::from_biguint(&BigUint::from(27u64));
+        let result = collatz_step_3n1(&pn);
+        let gpk_str = result.gpk.gpk_string(usize::MAX);
+        assert_eq!(gpk_str, "GPG");
+        let rle = result.gpk.gpk_rle();
+        assert_eq!(rle, vec![('G', 1), ('P', 1), ('G', 1)]);
+        assert_eq!(gpk_rle_decode(&rle), gpk_str);
+
+        for n_val in (1u64..=999).step_by(2) {
+            let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+            let gpk = collatz_step_3n1(&pn).gpk;
+            assert_eq!(gpk_rle_decode(&gpk.gpk_rle()), gpk.gpk_string(usize::MAX), "RLE roundtrip failed for n={}", n_val);
+        }
+    }
+
+    #[test]
+    fn test_raw_even_is_xn_plus_1() {
+        for x in [3u64, 5, 9, 17] {
+            for n_val in (1u64..=301).step_by(2) {
+                let n = BigUint::from(n_val);
+                let pn = PairNumber::from_biguint(&n);
+                let result = match x {
+                    3 => collatz_step_3n1(&pn),
+                    5 => collatz_step_5n1(&pn),
+                    _ => collatz_step(&pn, x),
+                };
+                let raw = result.raw_even();
+                assert_eq!(raw.to_biguint(), &n * x + 1u64, "raw_even mismatch for {}n+1, n={}", x, n_val);
+                assert_eq!(raw, PairNumber::from_biguint(&(&n * x + 1u64)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_collatz_step_3n1_all_ones_matches_general() {
+        // ベンチマークと同じ 2^1000-1, 2^10000-1, 2^100000-1 に加え、ワード境界付近
+        for bits in [2u32, 4, 126, 128, 130, 254, 256, 1000, 10000, 100000] {
+            let n = (BigUint::one() << bits) - BigUint::one();
+            let pn = PairNumber::from_biguint(&n);
+            assert!(is_all_ones(&pn));
+            let fast = collatz_step_3n1(&pn);
+            let general = collatz_step(&pn, 3);
+            assert_eq!(fast.next, general.next, "2^{}-1", bits);
+            assert_eq!(fast.next.to_biguint(), (&n * 3u32 + 1u32) >> 1u32);
+            assert_eq!((fast.d, fast.exchanged), (general.d, general.exchanged));
+            assert_eq!(fast.raw_even(), general.raw_even());
+            assert_eq!(fast.raw_pair_count, general.raw_pair_count);
+            assert_eq!(fast.gpk.gpk_rle(), general.gpk.gpk_rle());
+            assert_eq!(
+                (fast.gpk.g_count, fast.gpk.p_count, fast.gpk.k_count, fast.gpk.max_carry_chain),
+                (general.gpk.g_count, general.gpk.p_count, general.gpk.k_count, general.gpk.max_carry_chain)
+            );
+            assert_eq!(fast.gpk.full_carry_propagation, general.gpk.full_carry_propagation);
+        }
+        // 奇数ビット長の 2^L-1 や、1 ペアだけ欠けた値は対象外
+        for n in [(BigUint::one() << 7u32) - 1u32, (BigUint::one() << 128u32) - 1u32 - (BigUint::one() << 70u32)] {
+            let pn = PairNumber::from_biguint(&n);
+            assert!(!is_all_ones(&pn));
+            assert_eq!(collatz_step_3n1(&pn).next, collatz_step(&pn, 3).next);
+        }
+    }
+
+    /// full_carry_propagation: 2^L-1 では初期キャリーが最上位まで届き、(0,0) に近いペアを含む数では途中で K に当たる
+    /// （小さい数は K を含まないことが多いので、27 や 11 も立つ）
+    #[test]
+    fn test_full_carry_propagation() {
+        for bits in [1u32, 2, 3, 7, 64, 65, 127, 128, 1000] {
+            let n = (BigUint::one() << bits) - BigUint::one();
+            let pn = PairNumber::from_biguint(&n);
+            let r3 = collatz_step_3n1(&pn);
+            assert!(r3.gpk.full_carry_propagation, "2^{}-1, 3n+1", bits);
+            assert_eq!(r3.gpk.k_count, 0);
+            assert!(collatz_step(&pn, 3).gpk.full_carry_propagation, "2^{}-1, generic", bits);
+        }
+        // (4^j-1)/3 = 0101…01₂ は全ペア P で、3n+1 = 4^j までキャリーが抜ける
+        for n_val in [1u64, 5, 21, 85, 341] {
+            assert!(collatz_step_3n1(&PairNumber::from_biguint(&BigUint::from(n_val))).gpk.full_carry_propagation, "n={}", n_val);
+        }
+        for n_val in [9u64, 33, 123_456_789, 0xDEAD_BEEF_CAFE_F00D, 0x0123_4567_89AB_CDEF] {
+            let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+            let r = collatz_step_3n1(&pn);
+            assert!(!r.gpk.full_carry_propagation, "n={}", n_val);
+            assert!(r.gpk.k_count > 0);
+        }
+        // K がなければ必ず立つ（定義の確認）
+        for n_val in (1u64..2000).step_by(2) {
+            let r = collatz_step(&PairNumber::from_biguint(&BigUint::from(n_val)), 3);
+            assert_eq!(r.gpk.full_carry_propagation, r.gpk.k_count == 0, "n={}", n_val);
+        }
+    }
+
+    #[test]
+    fn test_even_trajectory_matches_odd() {
+        for (start, x) in [(27u64, 3u64), (703, 3), (3, 5), (13, 5), (1u64 << 40 | 3, 3)] {
+            let start = BigUint::from(start);
+            // 13 は 5n+1 のサイクル {13, 33, 83} に入るので max_steps で打ち切られる
+            let evens = even_trajectory(&start, x, 1000);
+            let odd = crate::trajectory::trace_trajectory(&start, x, 1000);
+            let odd_values: Vec<BigUint> = std::iter::once(start.clone())
+                .chain(odd.steps.iter().map(|(v, _)| v.clone()))
+                .collect();
+            assert_eq!(evens.len() + 1, odd_values.len(), "{}n+1 from {}", x, start);
+            for (i, e) in evens.iter().enumerate() {
+                let v = e.to_biguint();
+                // xn+1 = x·(直前の奇数) + 1、末尾ゼロを落とすと次の奇数
+                assert_eq!(v, &odd_values[i] * x + 1u64);
+                let d = v.trailing_zeros().unwrap();
+                assert_eq!(&v >> d, odd_values[i + 1]);
+            }
+        }
+        // 27: 奇数ステップ 41 回、最初の偶数は 82、最後は 16 (= 3·5+1)
+        let evens = even_trajectory(&BigUint::from(27u64), 3, 10_000);
+        assert_eq!(evens.len(), 41);
+        assert_eq!(evens[0].to_biguint(), BigUint::from(82u64));
+        assert_eq!(evens.last().unwrap().to_biguint(), BigUint::from(16u64));
+        // max_steps で打ち切り、偶数・1 は空
+        assert_eq!(even_trajectory(&BigUint::from(27u64), 3, 5).len(), 5);
+        assert!(even_trajectory(&BigUint::from(28u64), 3, 100).is_empty());
+        assert!(even_trajectory(&BigUint::one(), 3, 100).is_empty());
+    }
+
+    #[test]
+    fn test_verify_theorem_5_1() {
+        let big = (BigUint::one() << 1000u32) - BigUint::one();
+        assert!(verify_theorem_5_1(&PairNumber::from_biguint(&big)));
+        for n in (1u64..=4999).step_by(2) {
+            assert!(verify_theorem_5_1(&PairNumber::from_biguint(&BigUint::from(n))), "n={}", n);
+        }
+        // ワード境界をまたぐ大きな数
+        let mut next = crate::reference::test_rng(0x2545_F491_4F6C_DD1Du64);
+        for bits in [127u32, 128, 129, 4097] {
+            let mut v = BigUint::one() << bits;
+            for j in 0..bits / 64 {
+                v += BigUint::from(next()) << (64 * j);
+            }
+            assert!(verify_theorem_5_1(&PairNumber::from_biguint(&(v | BigUint::one()))), "bits={}", bits);
+        }
+    }
+
+    /// 走査結果を1ビットでも壊すと定理5.1 の照合が通らない
+    #[test]
+    fn test_verify_theorem_5_1_rejects_corruption() {
+        for n_val in [27u64, 255, 0xDEAD_BEEF_1234_5677] {
+            let pn = PairNumber::from_biguint(&BigUint::from(n_val));
+            let step = collatz_step_3n1(&pn);
+            let (g, p, m4, m6, pairs) = (&step.gpk.g_masks, &step.gpk.p_masks, &step.raw_m4, &step.raw_m6, step.raw_pair_count);
+            assert!(theorem_5_1_matches(&pn, g, p, m4, m6, pairs), "n={}", n_val);
+            for i in [0usize, 1, pn.pair_count() - 1] {
+                let flip = |v: &[u64]| {
+                    let mut v = v.to_vec();
+                    v[i / 64] ^= 1 << (i % 64);
+                    v
+                };
+                assert!(!theorem_5_1_matches(&pn, &flip(g), p, m4, m6, pairs), "g, n={} i={}", n_val, i);
+                assert!(!theorem_5_1_matches(&pn, g, &flip(p), m4, m6, pairs), "p, n={} i={}", n_val, i);
+                assert!(!theorem_5_1_matches(&pn, g, p, &flip(m4), m6, pairs), "m4, n={} i={}", n_val, i);
+                assert!(!theorem_5_1_matches(&pn, g, p, m4, &flip(m6), pairs), "m6, n={} i={}", n_val, i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_x_step() {
+        let xs = [3u64, 5, 9, 17, 33, 129, 3, 257];
+        let mut inputs: Vec<BigUint> = [1u64, 3, 7, 27, 255, 12_345].iter().map(|&n| BigUint::from(n)).collect();
+        inputs.push((BigUint::one() << 1000u32) - BigUint::one());
+        inputs.push((BigUint::one() << 777u32) + BigUint::from(0xDEAD_BEEFu64));
+        for n in &inputs {
+            let pn = PairNumber::from_biguint(n);
+            let results = multi_x_step(&pn, &xs);
+            assert_eq!(results.len(), xs.len());
+            for (r, &x) in results.iter().zip(&xs) {
+                let expected = collatz_step(&pn, x);
+                assert_eq!(r.next, expected.next, "n={} x={}", n, x);
+                assert_eq!(r.d, expected.d, "n={} x={}", n, x);
+                assert_eq!(r.raw_even(), expected.raw_even(), "n={} x={}", n, x);
+                assert_eq!(r.gpk.to_seq(), expected.gpk.to_seq(), "n={} x={}", n, x);
+            }
+        }
+        assert!(multi_x_step(&PairNumber::one(), &[]).is_empty());
+    }
+
+    /// x=1: (n+1)/2^d を BigUint と照合
+    #[test]
+    fn test_collatz_step_1n1() {
+        let mut inputs: Vec<BigUint> = (1u64..=999).step_by(2).map(BigUint::from).collect();
+        inputs.push((BigUint::one() << 200u32) - BigUint::one());
+        inputs.push((BigUint::one() << 129u32) + BigUint::from(0x1234_5677u64));
+        for n in &inputs {
+            crate::reference::assert_step_matches_reference(n, 1);
+            let r = collatz_step(&PairNumber::from_biguint(n), 1);
+            let (next, _) = crate::reference::collatz_step_naive(n, 1);
+            assert_eq!(r.raw_even().to_biguint(), n + 1u32, "n={}", n);
+            assert_eq!(r.gpk.g_count, 0);
+            assert_eq!(r.next, PairNumber::from_biguint(&next));
+        }
+    }
+
+    /// collatz_step_general: (xn+b)/2^d を BigUint と照合（b が複数ペアにまたがる場合を含む）
+    #[test]
+    fn test_collatz_step_general_vs_biguint() {
+        let mut inputs: Vec<BigUint> = (1u64..=301).step_by(2).map(BigUint::from).collect();
+        for bits in [63u32, 64, 65, 129] {
+            inputs.push((BigUint::one() << bits) - BigUint::one());
+            inputs.push((BigUint::one() << bits) + BigUint::from(12345u64));
+        }
+        for x in [3u64, 5, 9, 17] {
+            for b in [0u64, 1, 2, 3, 5, 7, 13, 255, 1 << 40, (1 << 40) + 1, u64::MAX] {
+                for n in &inputs {
+                    let pn = PairNumber::from_biguint(n);
+                    let v = n * x + b;
+                    let d = v.trailing_zeros().unwrap_or(0);
+                    let r = collatz_step_general(&pn, x, b);
+                    assert_eq!(r.next.to_biguint(), &v >> d, "{}n+{}, n={}", x, b, n);
+                    assert_eq!((r.d, r.exchanged), (d, d % 2 == 1), "{}n+{}, n={}", x, b, n);
+                    assert_eq!(r.raw_even().to_biguint(), v, "{}n+{}, n={}", x, b, n);
+                }
+            }
+            // b=1 は collatz_step と同じ
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let (a, c) = (collatz_step_general(&pn, x, 1), collatz_step(&pn, x));
+                assert_eq!((a.next, a.d, a.raw_pair_count), (c.next, c.d, c.raw_pair_count));
+                assert_eq!(a.gpk.gpk_rle(), c.gpk.gpk_rle());
+            }
+        }
+    }
+
+    #[test]
+    fn test_taxonomy_histogram() {
+        // 27 = 0b01_10_11: m6 = b = [1,0,1], m4 = a = [1,1,0]。x=3 (s=1, t=0) では
+        // ref_R(i) = (a[i-1], b[i]), ref_L(i) = (b[i], a[i]) なので
+        // i=0: m6段 (0,1)=P, m4段 (1,1)=G / i=1: (1,0)=P, (0,1)=P / i=2: (1,1)=G, (1,0)=P
+        let n = PairNumber::from_biguint(&BigUint::from(27u64));
+        let hist = taxonomy_histogram(&n, 3);
+        let class = |m6, m4| PairClass { m6, m4 }.index();
+        let mut expected = [0u64; 9];
+        expected[class(Gpk::Propagate, Gpk::Generate)] = 1;
+        expected[class(Gpk::Propagate, Gpk::Propagate)] = 1;
+        expected[class(Gpk::Generate, Gpk::Propagate)] = 1;
+        assert_eq!(hist, expected);
+
+        for i in 0..9 {
+            assert_eq!(PairClass::from_index(i).unwrap().index(), i);
+        }
+        assert_eq!(PairClass::from_index(9), None);
+
+        // merged で潰すと3分類の GPK 数に一致する
+        for (v, x) in [(27u64, 3u64), (27, 5), (0xDEAD_BEEF, 3), (12345677, 9), (u64::MAX, 17)] {
+            let n = PairNumber::from_biguint(&BigUint::from(v));
+            let hist = taxonomy_histogram(&n, x);
+            let mut gpk = [0u64; 3];
+            for (i, &c) in hist.iter().enumerate() {
+                gpk[PairClass::from_index(i).unwrap().merged() as usize] += c;
+            }
+            let info = collatz_step(&n, x).gpk;
+            assert_eq!(gpk, [info.k_count as u64, info.p_count as u64, info.g_count as u64], "v={} x={}", v, x);
+            assert_eq!(hist.iter().sum::<u64>(), n.pair_count() as u64);
+        }
+
+        for bits in 0..16u8 {
+            let (p_r, q_r, p_l, q_l) = (bits & 1, (bits >> 1) & 1, (bits >> 2) & 1, bits >> 3);
+            assert_eq!(pair_taxonomy(p_r, q_r, p_l, q_l).merged(), pair_gpk(p_r, q_r, p_l, q_l));
+        }
+    }
+
+    #[test]
+    fn test_step_navigable_back() {
+        for x in [1u64, 3, 5, 9, 17] {
+            let mut inputs: Vec<BigUint> = (1u64..2_000).step_by(2).map(BigUint::from).collect();
+            inputs.push((BigUint::one() << 300u32) - 1u32);
+            inputs.push((BigUint::one() << 129u32) + 0x1234_5677u32);
+            for n in &inputs {
+                let pn = PairNumber::from_biguint(n);
+                let step = step_navigable(&pn, x);
+                let r = collatz_step(&pn, x);
+                assert_eq!((&step.next, step.d, step.exchanged), (&r.next, r.d, r.exchanged));
+                assert_eq!(step.back(&step.next), pn, "x={} n={}", x, n);
+            }
+        }
+
+        // 軌道を進めてから同じ数だけ戻る
+        let mut pn = PairNumber::from_biguint(&BigUint::from(27u64));
+        let mut path = Vec::new();
+        while !pn.is_one() {
+            let step = step_navigable(&pn, 3);
+            pn = step.next.clone();
+            path.push(step);
+        }
+        assert_eq!(path.len(), 41);
+        for step in path.iter().rev() {
+            pn = step.back(&pn);
+        }
+        assert_eq!(pn.to_biguint(), BigUint::from(27u64));
+    }
+
+    #[test]
+    fn test_exchange_stream_matches_trace() {
+        let check = |n: u64, x: u64, max_steps: u64| {
+            let (words, steps, reached_one) = exchange_stream_u64(n, x, max_steps);
+            let traj = crate::trajectory::trace_trajectory(&BigUint::from(n), x, max_steps);
+            assert_eq!(steps, traj.total_steps, "n={n} x={x}");
+            assert_eq!(reached_one, traj.reached_one, "n={n} x={x}");
+            assert_eq!(words.len() as u64, steps.div_ceil(64));
+            // pair_steps[0] は初期値、以降が各ステップ
+            for (i, ps) in traj.pair_steps[1..].iter().enumerate() {
+                assert_eq!((words[i / 64] >> (i % 64)) & 1 == 1, ps.exchanged, "n={n} x={x} step {i}");
+            }
+            if !steps.is_multiple_of(64) {
+                assert_eq!(words.last().unwrap() >> (steps % 64), 0);
+            }
+        };
+        for n in (1..2000u64).step_by(2) {
+            check(n, 3, 10_000);
+        }
+        check(27, 3, 20);
+        // 5n+1 / 9n+1 は発散して u128 を超え、パックド走査に切り替わる
+        check(7, 5, 400);
+        check(3, 9, 200);
+        check(u64::MAX, 3, 1000);
+    }
+}
@@ -1,9 +1,10 @@
 use num_bigint::BigUint;
 use num_traits::One;
+use rayon::prelude::*;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
 use crate::packed;
-use crate::pair_number::PairNumber;
+use crate::pair_number::{compact_even_bits, predicate_word, PairNumber};
 use crate::scan::{self, GpkInfo, GpkStats};
 
 // ============================================================
@@ -72,6 +73,14 @@ impl U256 {
         U256(result)
     }
 
+    /// 奇数→奇数の1ステップ (x·self+1) >> d。x·self+1 が 256 ビットに収まらなければ None
+    #[inline]
+    fn odd_step(self, x: u64) -> Option<(Self, u32)> {
+        let xn1 = self.mul_small_checked(x)?.add_one();
+        let d = xn1.trailing_zeros();
+        Some((xn1.shr(d), d))
+    }
+
     #[inline]
     fn is_one(self) -> bool {
         self.0[0] == 1 && self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0
@@ -93,7 +102,6 @@ impl U256 {
     }
 
     #[inline]
-    #[allow(dead_code)]
     fn bit_len(self) -> u32 {
         for i in (0..4).rev() {
             if self.0[i] != 0 {
@@ -104,6 +112,40 @@ impl U256 {
     }
 }
 
+/// u128 での奇数→奇数の1ステップ (x·n+1) >> d と d。n <= (u128::MAX - 1) / x であること
+#[inline]
+fn odd_step_u128(n: u128, x128: u128) -> (u128, u32) {
+    let xn1 = n * x128 + 1;
+    let d = xn1.trailing_zeros();
+    (xn1 >> d, d)
+}
+
+/// u128 フェーズの1ステップ（reference::assert_step_matches_reference 用）。
+/// n が u128 に収まらないか x·n+1 がオーバーフローするなら None
+#[cfg(test)]
+pub(crate) fn u128_odd_step(n: &BigUint, x: u64) -> Option<(BigUint, u64)> {
+    let v = u128::try_from(n).ok()?;
+    if v > (u128::MAX - 1) / x as u128 {
+        return None;
+    }
+    let (next, d) = odd_step_u128(v, x as u128);
+    Some((BigUint::from(next), d as u64))
+}
+
+/// U256 フェーズの1ステップ（reference::assert_step_matches_reference 用）。
+/// n が 256 ビットに収まらないか x·n+1 がオーバーフローするなら None
+#[cfg(test)]
+pub(crate) fn u256_odd_step(n: &BigUint, x: u64) -> Option<(BigUint, u64)> {
+    let digits = n.to_u64_digits();
+    if digits.len() > 4 {
+        return None;
+    }
+    let mut limbs = [0u64; 4];
+    limbs[..digits.len()].copy_from_slice(&digits);
+    let (next, d) = U256(limbs).odd_step(x)?;
+    Some((next.to_biguint(), d as u64))
+}
+
 /// m4/m6 ペアステップ情報
 #[derive(Debug, Clone)]
 pub struct PairStep {
@@ -125,6 +167,45 @@ pub struct PairStep {
     pub raw_pair_count: usize,
 }
 
+impl PairStep {
+    /// 軌道の初期値 n の記録（d = 0、偶数状態なし）
+    fn initial(n: &PairNumber) -> Self {
+        PairStep {
+            m4_words: n.m4_words().to_vec(),
+            m6_words: n.m6_words().to_vec(),
+            pair_count: n.pair_count(),
+            d: 0, exchanged: false,
+            raw_m4_words: Vec::new(), raw_m6_words: Vec::new(), raw_pair_count: 0,
+        }
+    }
+
+    /// 1 ステップの結果の記録（偶数状態のワード列は result から取り出すので、result.raw_m4 / raw_m6 は空になる）
+    fn take_from(result: &mut scan::StepResult) -> Self {
+        PairStep {
+            m4_words: result.next.m4_words().to_vec(),
+            m6_words: result.next.m6_words().to_vec(),
+            pair_count: result.next.pair_count(),
+            d: result.d, exchanged: result.exchanged,
+            raw_m4_words: std::mem::take(&mut result.raw_m4),
+            raw_m6_words: std::mem::take(&mut result.raw_m6),
+            raw_pair_count: result.raw_pair_count,
+        }
+    }
+}
+
+/// 軌道追跡が終了した理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// n=1 に到達した
+    ReachedOne,
+    /// max_steps に達した
+    MaxSteps,
+    /// ペア数上限（MAX_PAIR_COUNT）を超えた（発散とみなして打ち切り）
+    ExceededPairCount,
+    /// キャンセルされた
+    Cancelled,
+}
+
 /// 軌道追跡の結果
 #[derive(Debug, Clone)]
 pub struct TrajectoryResult {
@@ -144,6 +225,199 @@ pub struct TrajectoryResult {
     pub max_value: BigUint,
     /// 1 に到達したか
     pub reached_one: bool,
+    /// 追跡が終了した理由
+    pub stop_reason: StopReason,
+    /// 値の大きさ別の GPK 集約: (バケット番号 floor(log2(n) / bucket_bits), 統計)。
+    /// バケット番号昇順で非空のもののみ。trace_trajectory_bucketed 以外では空。
+    pub gpk_by_bitlen_bucket: Vec<(usize, GpkStats)>,
+}
+
+impl TrajectoryResult {
+    /// 連続するステップの支配的 GPK クラス（GpkInfo::dominant_class）の遷移回数。
+    /// counts[a][b] は クラス a のステップの直後がクラス b だった回数で、添字は Gpk の値
+    /// （K=0, P=1, G=2）。マルコフ連鎖としてのモデル化向け。
+    /// gpk_per_step から数えるので、間引き記録（trace_trajectory_decimated）では記録された
+    /// ステップ同士の遷移になる。合計は gpk_per_step.len() - 1（空なら 0）。
+    pub fn gpk_transition_counts(&self) -> [[u64; 3]; 3] {
+        let mut counts = [[0u64; 3]; 3];
+        for w in self.gpk_per_step.windows(2) {
+            counts[w[0].dominant_class() as usize][w[1].dominant_class() as usize] += 1;
+        }
+        counts
+    }
+
+    /// steps の d 列の正規化自己相関。戻り値の添字 k（0..=max_lag）がラグ k の値で、
+    /// r(k) = Σ_t (d_t - μ)(d_{t+k} - μ) / Σ_t (d_t - μ)²（分母は全長の分散和、r(0) = 1）。
+    /// 2-adic 構造から連続する d は独立でないはずで、その検定用。
+    /// d が一定（分散 0）なら完全相関として 1 を返す。ラグが列の長さ以上なら 0。
+    pub fn d_autocorrelation(&self, max_lag: usize) -> Vec<f64> {
+        let ds: Vec<f64> = self.steps.iter().map(|&(_, d)| d as f64).collect();
+        let len = ds.len();
+        if len == 0 {
+            return vec![0.0; max_lag + 1];
+        }
+        let mean = ds.iter().sum::<f64>() / len as f64;
+        let centered: Vec<f64> = ds.iter().map(|d| d - mean).collect();
+        let variance: f64 = centered.iter().map(|c| c * c).sum();
+        (0..=max_lag)
+            .map(|lag| {
+                if lag >= len {
+                    0.0
+                } else if variance == 0.0 {
+                    1.0
+                } else {
+                    centered.iter().zip(&centered[lag..]).map(|(a, b)| a * b).sum::<f64>() / variance
+                }
+            })
+            .collect()
+    }
+
+    /// 初期値の m4 列を交換をまたいで追った、pair_steps 各状態のビット文字列（MSB first）。
+    /// d が奇数のステップでは m4/m6 の役割が入れ替わるので、それまでの交換回数が奇数の状態では
+    /// この列は m6 側にある。m6_stream と合わせて、交換の累積の偶奇で各状態の m4/m6 に戻せる。
+    /// pair_steps の exchanged から偶奇を数えるので、全ステップを記録した軌道でのみ正しい
+    /// （trace_trajectory_decimated 等の間引き記録では記録されないステップの交換が抜ける）。
+    pub fn m4_stream(&self) -> Vec<String> {
+        self.exchange_adjusted_stream(false)
+    }
+
+    /// 初期値の m6 列を交換をまたいで追った、pair_steps 各状態のビット文字列（MSB first）。
+    /// 詳細は m4_stream を参照。
+    pub fn m6_stream(&self) -> Vec<String> {
+        self.exchange_adjusted_stream(true)
+    }
+
+    /// swapped=false なら初期 m4 列、true なら初期 m6 列を追う
+    fn exchange_adjusted_stream(&self, swapped: bool) -> Vec<String> {
+        let mut parity = swapped;
+        self.pair_steps
+            .iter()
+            .map(|ps| {
+                parity ^= ps.exchanged;
+                let words = if parity { &ps.m6_words } else { &ps.m4_words };
+                words_to_bits_msb(words, ps.pair_count)
+            })
+            .collect()
+    }
+}
+
+/// ピーク値のみ保持する軌道追跡の結果（trace_trajectory_peak）
+#[derive(Debug, Clone)]
+pub struct PeakTrajectoryResult {
+    /// 開始値
+    pub start: BigUint,
+    /// 最大値を取ったステップの m4/m6 情報
+    pub peak: PairStep,
+    /// 最終状態の m4/m6 情報
+    pub final_state: PairStep,
+    /// GPK 集約統計
+    pub gpk_stats: GpkStats,
+    /// 総ステップ数
+    pub total_steps: u64,
+    /// 最大値
+    pub max_value: BigUint,
+    /// 最大値を取ったステップ番号（開始値が最大なら 0、同値なら最初のもの）
+    pub max_value_step: u64,
+    /// 追跡が終了した理由
+    pub stop_reason: StopReason,
+}
+
+/// 軌道をステップ列を持たずに集約した要約（OrbitSummary::from / merge）。
+/// 多数のシードを追跡して統計だけ残すバッチ研究向けで、merge で何本分でも足し合わせられる。
+#[derive(Debug, Clone)]
+pub struct OrbitSummary {
+    /// 要約に含まれる軌道の数（単一軌道なら 1）
+    pub orbits: u64,
+    /// 総ステップ数の合計
+    pub total_steps: u64,
+    /// 各ステップの d の合計。steps から数えるので、間引き記録（trace_trajectory_decimated）
+    /// では記録されたステップの分だけになる
+    pub sum_d: u64,
+    /// 最大値のビット長（複数軌道なら最大のもの）
+    pub max_value_bits: u64,
+    /// 1 に到達した軌道の数（単一軌道なら 0 か 1）
+    pub reached_one: u64,
+    /// GPK 集約統計
+    pub gpk_stats: GpkStats,
+}
+
+impl From<&TrajectoryResult> for OrbitSummary {
+    fn from(r: &TrajectoryResult) -> Self {
+        OrbitSummary {
+            orbits: 1,
+            total_steps: r.total_steps,
+            sum_d: r.steps.iter().map(|&(_, d)| d).sum(),
+            max_value_bits: r.max_value.bits(),
+            reached_one: r.reached_one as u64,
+            gpk_stats: r.gpk_stats.clone(),
+        }
+    }
+}
+
+impl OrbitSummary {
+    /// other の軌道をこの要約に足し込む（件数・合計は加算、最大ビット長は大きい方）
+    pub fn merge(&mut self, other: &OrbitSummary) {
+        self.orbits += other.orbits;
+        self.total_steps += other.total_steps;
+        self.sum_d += other.sum_d;
+        self.max_value_bits = self.max_value_bits.max(other.max_value_bits);
+        self.reached_one += other.reached_one;
+        self.gpk_stats.merge(&other.gpk_stats);
+    }
+}
+
+/// aggregate_summaries の結果
+#[derive(Debug, Clone)]
+pub struct AggregateReport {
+    /// 全要約を merge したもの
+    pub total: OrbitSummary,
+    /// 1 軌道あたりの平均ステップ数（軌道が無ければ 0）
+    pub mean_steps: f64,
+    /// 1 ステップあたりの平均 d（ステップが無ければ 0）
+    pub mean_d: f64,
+    /// 1 に到達した軌道の割合（軌道が無ければ 0）
+    pub reached_one_fraction: f64,
+    /// 要約ごとの total_steps の分布: (ステップ数, 要約の数)。ステップ数昇順
+    pub steps_distribution: Vec<(u64, u64)>,
+    /// 要約ごとの max_value_bits の分布: (ビット長, 要約の数)。ビット長昇順
+    pub max_bits_distribution: Vec<(u64, u64)>,
+}
+
+/// OrbitSummary の列を一つの集計にまとめる（バッチ研究の reduce 段）。
+/// 分布は要約 1 件を 1 標本として数えるので、単一軌道の要約を渡したときに軌道ごとの分布になる。
+pub fn aggregate_summaries(summaries: &[OrbitSummary]) -> AggregateReport {
+    let mut total = OrbitSummary {
+        orbits: 0,
+        total_steps: 0,
+        sum_d: 0,
+        max_value_bits: 0,
+        reached_one: 0,
+        gpk_stats: GpkStats::new(),
+    };
+    for s in summaries {
+        total.merge(s);
+    }
+    let ratio = |num: u64, den: u64| if den == 0 { 0.0 } else { num as f64 / den as f64 };
+    let distribution = |key: fn(&OrbitSummary) -> u64| {
+        let mut keys: Vec<u64> = summaries.iter().map(key).collect();
+        keys.sort_unstable();
+        let mut dist: Vec<(u64, u64)> = Vec::new();
+        for k in keys {
+            match dist.last_mut() {
+                Some((v, count)) if *v == k => *count += 1,
+                _ => dist.push((k, 1)),
+            }
+        }
+        dist
+    };
+    AggregateReport {
+        mean_steps: ratio(total.total_steps, total.orbits),
+        mean_d: ratio(total.sum_d, total.total_steps),
+        reached_one_fraction: ratio(total.reached_one, total.orbits),
+        steps_distribution: distribution(|s| s.total_steps),
+        max_bits_distribution: distribution(|s| s.max_value_bits),
+        total,
+    }
 }
 
 /// パックドワード列からビット文字列を生成 (MSB first)
@@ -160,31 +434,11 @@ pub fn words_to_bits_msb(words: &[u64], pair_count: usize) -> String {
 /// 16述語のビット文字列を生成 (MSB first)
 /// pred: 1〜16 (m1=FALSE, m2=AND, ..., m16=TRUE)
 pub fn predicate_bits_msb(m4_words: &[u64], m6_words: &[u64], pair_count: usize, pred: u8) -> String {
-    let word_count = m4_words.len();
-    let mut pred_words = Vec::with_capacity(word_count);
-    for w in 0..word_count {
-        let m4 = m4_words[w];
-        let m6 = m6_words[w];
-        pred_words.push(match pred {
-            1 => 0u64,
-            2 => m4 & m6,
-            3 => m4 & !m6,
-            4 => m4,
-            5 => !m4 & m6,
-            6 => m6,
-            7 => m4 ^ m6,
-            8 => m4 | m6,
-            9 => !m4 & !m6,
-            10 => !(m4 ^ m6),
-            11 => !m6,
-            12 => m4 | !m6,
-            13 => !m4,
-            14 => !m4 | m6,
-            15 => !(m4 & m6),
-            16 => !0u64,
-            _ => 0,
-        });
-    }
+    let pred_words: Vec<u64> = m4_words
+        .iter()
+        .zip(m6_words)
+        .map(|(&m4, &m6)| predicate_word(m4, m6, pred))
+        .collect();
     words_to_bits_msb(&pred_words, pair_count)
 }
 
@@ -208,47 +462,90 @@ pub fn trace_trajectory_with_callback(
     max_steps: u64,
     callback: impl Fn(u64, usize, u64),
 ) -> TrajectoryResult {
-    let mut pair = PairNumber::from_biguint(start);
+    trace_trajectory_sampled(start, x, max_steps, 1, None, callback)
+}
+
+/// 間引き記録付き軌道追跡。
+/// steps / pair_steps / gpk_per_step には total_steps % every == 0 のステップのみ記録する
+/// （初期値 step 0 は常に記録）。gpk_stats と max_value は全ステップで集計するため正確。
+/// every=0 は every=1 として扱う。
+pub fn trace_trajectory_decimated(start: &BigUint, x: u64, max_steps: u64, every: u64) -> TrajectoryResult {
+    trace_trajectory_sampled(start, x, max_steps, every, None, |_, _, _| {})
+}
+
+/// 値の大きさ別 GPK 集約付き軌道追跡。
+/// 各ステップの GPK をステップ前の値のビット長でバケット分けして gpk_by_bitlen_bucket に積む。
+/// バケット番号は floor(log2(n)) = bit_len - 1 を bucket_bits で割ったもの。bucket_bits=0 は 64 として扱う。
+pub fn trace_trajectory_bucketed(start: &BigUint, x: u64, max_steps: u64, bucket_bits: usize) -> TrajectoryResult {
+    let bucket_bits = if bucket_bits == 0 { 64 } else { bucket_bits };
+    trace_trajectory_sampled(start, x, max_steps, 1, Some(bucket_bits), |_, _, _| {})
+}
+
+/// ピーク値のみ記録する軌道追跡。
+/// 全ステップの PairStep を持たず、最大値のステップと最終状態だけを保持するので
+/// メモリはステップ数によらずピークのサイズ程度で済む。gpk_stats は全ステップで集計する。
+pub fn trace_trajectory_peak(start: &BigUint, x: u64, max_steps: u64) -> PeakTrajectoryResult {
+    let pair = PairNumber::from_biguint(start);
+    let mut peak = PairStep::initial(&pair);
+    let mut final_state = peak.clone();
+    let mut peak_pn = pair.clone();
+    let mut max_value_step = 0u64;
+    let mut gpk_stats = GpkStats::new();
+
+    let (total_steps, stop_reason) = walk_trajectory(pair, x, max_steps, |step, _, mut result| {
+        gpk_stats.accumulate(&result.gpk);
+        final_state = PairStep::take_from(&mut result);
+
+        // PairNumber のまま比較し、BigUint 変換はしない
+        if result.next > peak_pn {
+            peak_pn.clone_from(&result.next);
+            peak = final_state.clone();
+            max_value_step = step;
+        }
+        result.next
+    });
+
+    PeakTrajectoryResult {
+        start: start.clone(),
+        peak,
+        final_state,
+        gpk_stats,
+        total_steps,
+        max_value: peak_pn.to_biguint(),
+        max_value_step,
+        stop_reason,
+    }
+}
+
+/// 軌道追跡の共通処理（every ステップごとに記録、bucket_bits 指定時は大きさ別 GPK も集約）
+fn trace_trajectory_sampled(
+    start: &BigUint,
+    x: u64,
+    max_steps: u64,
+    every: u64,
+    bucket_bits: Option<usize>,
+    callback: impl Fn(u64, usize, u64),
+) -> TrajectoryResult {
+    let every = every.max(1);
+    let mut buckets: Vec<GpkStats> = Vec::new();
+    let pair = PairNumber::from_biguint(start);
     let mut steps: Vec<(BigUint, u64)> = Vec::new();
-    let mut pair_steps: Vec<PairStep> = Vec::new();
     let mut gpk_per_step: Vec<GpkInfo> = Vec::new();
     let mut gpk_stats = GpkStats::new();
-    let mut total_steps = 0u64;
     let mut max_value = start.clone();
-    let mut reached_one = pair.is_one();
 
     // 初期値の m4/m6 を記録
-    pair_steps.push(PairStep {
-        m4_words: pair.m4_words().to_vec(),
-        m6_words: pair.m6_words().to_vec(),
-        pair_count: pair.pair_count(),
-        d: 0, exchanged: false,
-        raw_m4_words: Vec::new(), raw_m6_words: Vec::new(), raw_pair_count: 0,
-    });
-
-    while !reached_one && total_steps < max_steps {
-        let result = if x == 3 {
-            scan::collatz_step_3n1(&pair)
-        } else if x == 5 {
-            scan::collatz_step_5n1(&pair)
-        } else {
-            scan::collatz_step(&pair, x)
-        };
+    let mut pair_steps: Vec<PairStep> = vec![PairStep::initial(&pair)];
 
-        total_steps += 1;
+    let (total_steps, stop_reason) = walk_trajectory(pair, x, max_steps, |step, before, mut result| {
         gpk_stats.accumulate(&result.gpk);
-        gpk_per_step.push(result.gpk);
-
-        // m4/m6 ワードを記録（偶数状態含む）
-        pair_steps.push(PairStep {
-            m4_words: result.next.m4_words().to_vec(),
-            m6_words: result.next.m6_words().to_vec(),
-            pair_count: result.next.pair_count(),
-            d: result.d, exchanged: result.exchanged,
-            raw_m4_words: result.raw_m4,
-            raw_m6_words: result.raw_m6,
-            raw_pair_count: result.raw_pair_count,
-        });
+        if let Some(bb) = bucket_bits {
+            let b = before.bit_len().saturating_sub(1) / bb;
+            if b >= buckets.len() {
+                buckets.resize_with(b + 1, GpkStats::new);
+            }
+            buckets[b].accumulate(&result.gpk);
+        }
 
         let n_val = result.next.to_biguint();
 
@@ -257,21 +554,16 @@ pub fn trace_trajectory_with_callback(
         }
 
         let digits = result.next.pair_count() * 2;
-        callback(total_steps, digits, result.d);
-
-        steps.push((n_val.clone(), result.d));
+        callback(step, digits, result.d);
 
-        if result.next.is_one() {
-            reached_one = true;
+        if step.is_multiple_of(every) {
+            // m4/m6 ワードを記録（偶数状態含む）
+            pair_steps.push(PairStep::take_from(&mut result));
+            steps.push((n_val, result.d));
+            gpk_per_step.push(result.gpk);
         }
-
-        // ビット長制限: 発散防止
-        if result.next.pair_count() > MAX_PAIR_COUNT {
-            break;
-        }
-
-        pair = result.next;
-    }
+        result.next
+    });
 
     TrajectoryResult {
         start: start.clone(),
@@ -281,160 +573,219 @@ pub fn trace_trajectory_with_callback(
         gpk_stats,
         total_steps,
         max_value,
-        reached_one,
+        reached_one: stop_reason == StopReason::ReachedOne,
+        stop_reason,
+        gpk_by_bitlen_bucket: buckets
+            .into_iter()
+            .enumerate()
+            .filter(|(_, st)| st.total_steps > 0)
+            .collect(),
+    }
+}
+
+/// trace_trajectory_sampled と trace_trajectory_peak の共通ループ。start から collatz_step_for_x で
+/// 1 に着くか、max_steps に達するか、ペア数が MAX_PAIR_COUNT を超える（発散防止）まで進める。
+/// 各ステップで on_step(ステップ番号, ステップ前の値, ステップの結果) を呼び、on_step は
+/// 結果の next を返す。戻り値は (総ステップ数, 終了理由)。
+fn walk_trajectory(
+    start: PairNumber,
+    x: u64,
+    max_steps: u64,
+    mut on_step: impl FnMut(u64, &PairNumber, scan::StepResult) -> PairNumber,
+) -> (u64, StopReason) {
+    let mut pair = start;
+    let mut total_steps = 0u64;
+    let mut stop_reason = if pair.is_one() { StopReason::ReachedOne } else { StopReason::MaxSteps };
+
+    while stop_reason != StopReason::ReachedOne && total_steps < max_steps {
+        let result = scan::collatz_step_for_x(&pair, x);
+        total_steps += 1;
+        let next = on_step(total_steps, &pair, result);
+
+        if next.is_one() {
+            stop_reason = StopReason::ReachedOne;
+        }
+
+        // ビット長制限: 発散防止
+        if next.pair_count() > MAX_PAIR_COUNT {
+            stop_reason = StopReason::ExceededPairCount;
+            break;
+        }
+
+        pair = next;
     }
+
+    (total_steps, stop_reason)
 }
 
 /// ビット長制限（ペア数上限）。これを超えたら発散とみなして打ち切る。
 /// 5n+1 等の非収束写像で BigUint がメモリを食い潰すのを防ぐ。
-const MAX_PAIR_COUNT: usize = 10_000;
+pub(crate) const MAX_PAIR_COUNT: usize = 10_000;
+
+/// 停止時間計算（`*_d` ヘルパー）が軌道から集める付随統計。ヘルパーはここに加算していくので、
+/// シードごとに空にして使っても、複数シード分を溜めて使ってもよい。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SeedStats {
+    /// GPK 統計。None なら GPK の計算自体をスキップする
+    pub gpk: Option<GpkStats>,
+    /// d 値ヒストグラム (index=d, value=出現回数)
+    pub d_hist: Vec<u64>,
+    /// 軌道上の値（シード自身を含む）の最大ビット長
+    pub peak_bits: u64,
+    /// 1 への到達で止まったシードの数（シード 1 を含む）
+    pub reached_one: u64,
+    /// 1 以外の値でシード（または下限）を下回って止まったシードの数（下限未満のシードの即時収束を含む）
+    pub dropped_below: u64,
+}
 
-/// u128 値から直接 GPK 統計を計算する。
-fn accumulate_gpk_u128(n: u128, x: u64, stats: &mut GpkStats) {
-    if n == 0 { return; }
-    let bit_len = 128 - n.leading_zeros() as usize;
-    let pair_count = (bit_len + 1) / 2;
+impl SeedStats {
+    pub fn new(collect_gpk: bool) -> Self {
+        SeedStats { gpk: collect_gpk.then(GpkStats::new), ..SeedStats::default() }
+    }
 
-    let xm1 = x - 1;
-    let s = xm1.trailing_zeros();
-    let t = (s / 2) as isize;
-    let s_is_even = s % 2 == 0;
+    /// d 値ヒストグラムに 1 ステップ分を加える（index=d、必要に応じて伸ばす）
+    #[inline]
+    pub fn record_d(&mut self, d: u64) {
+        let d = d as usize;
+        if d >= self.d_hist.len() {
+            self.d_hist.resize(d + 1, 0);
+        }
+        self.d_hist[d] += 1;
+    }
 
-    let get_a = |i: isize| -> u8 {
-        if i < 0 || (i as usize) >= pair_count { return 0; }
-        ((n >> (2 * i as usize + 1)) & 1) as u8
-    };
-    let get_b = |i: isize| -> u8 {
-        if i < 0 || (i as usize) >= pair_count { return 0; }
-        ((n >> (2 * i as usize)) & 1) as u8
-    };
+    /// 軌道上の値のビット長の最大値を更新する
+    #[inline]
+    pub fn record_bits(&mut self, bits: u64) {
+        self.peak_bits = self.peak_bits.max(bits);
+    }
 
-    let mut g_count = 0u32;
-    let mut p_count = 0u32;
-    let mut k_count = 0u32;
-    let mut carry = true;
-    let mut chain = 0u32;
-    let mut max_chain = 0u32;
-
-    for i in 0..pair_count {
-        let ii = i as isize;
-        let ai = get_a(ii);
-        let bi = get_b(ii);
-
-        let (p_r, q_r, p_l, q_l) = if s_is_even {
-            (get_b(ii - t), bi, get_a(ii - t), ai)
-        } else {
-            (get_a(ii - t - 1), bi, get_b(ii - t), ai)
-        };
+    /// GPK 統計の最長キャリー伝播距離（GPK を集めていなければ 0）
+    pub fn max_carry_chain(&self) -> u32 {
+        self.gpk.as_ref().map_or(0, |gpk| gpk.max_carry_chain)
+    }
+
+    /// 別の集計を加える（GPK 統計・d ヒストグラム・停止の種類は加算、最大ビット長は大きい方）
+    pub fn merge(&mut self, other: &SeedStats) {
+        match (self.gpk.as_mut(), &other.gpk) {
+            (Some(gpk), Some(o)) => gpk.merge(o),
+            (None, Some(o)) => self.gpk = Some(o.clone()),
+            _ => {}
+        }
+        merge_d_hist(&mut self.d_hist, &other.d_hist);
+        self.peak_bits = self.peak_bits.max(other.peak_bits);
+        self.reached_one += other.reached_one;
+        self.dropped_below += other.dropped_below;
+    }
+
+    /// 集計を空に戻す（GPK を集めるかどうかと d ヒストグラムの確保領域はそのまま）
+    pub fn clear(&mut self) {
+        if let Some(gpk) = self.gpk.as_mut() {
+            *gpk = GpkStats::new();
+        }
+        self.d_hist.clear();
+        self.peak_bits = 0;
+        self.reached_one = 0;
+        self.dropped_below = 0;
+    }
 
-        let g_mid = p_r & q_r;
-        let p_mid = p_r ^ q_r;
-        let g_out = p_l & q_l;
-        let p_out = p_l ^ q_l;
-        let g_i = g_out | (p_out & g_mid);
-        let p_i = p_out & p_mid;
-
-        if g_i != 0 {
-            g_count += 1;
-            chain += 1;
-            carry = true;
-        } else if p_i != 0 {
-            p_count += 1;
-            if carry { chain += 1; }
-        } else {
-            k_count += 1;
-            if chain > max_chain { max_chain = chain; }
-            chain = 0;
-            carry = false;
-        }
-    }
-    if chain > max_chain { max_chain = chain; }
-
-    stats.total_g += g_count as u64;
-    stats.total_p += p_count as u64;
-    stats.total_k += k_count as u64;
-    stats.total_pairs += pair_count as u64;
-    stats.total_steps += 1;
-    let idx = (max_chain as usize).min(127);
-    stats.carry_chain_hist[idx] += 1;
+    /// GPK を集めているときだけ GpkStats::attribute_chain_record を呼ぶ
+    pub fn attribute_chain_record(&mut self, before: u32, seed: impl FnOnce() -> BigUint) {
+        if let Some(gpk) = self.gpk.as_mut() {
+            gpk.attribute_chain_record(before, seed);
+        }
+    }
+
+    /// 集めた GPK 統計（集めていなければ空）
+    pub fn take_gpk(&mut self) -> GpkStats {
+        self.gpk.take().unwrap_or_else(GpkStats::new)
+    }
+
+    /// パックドステップ 1 回分の GPK を加える（GPK を集めないなら何もしない）
+    #[inline]
+    fn record_packed(&mut self, result: &packed::PackedStepResult, pair_count: usize) {
+        if let Some(stats) = self.gpk.as_mut() {
+            stats.record_step(result.g_count, result.p_count, result.k_count, pair_count, result.max_carry_chain);
+        }
+    }
+}
+
+/// d 値ヒストグラムを加算マージ（長さは長い方に合わせる）
+pub(crate) fn merge_d_hist(dst: &mut Vec<u64>, src: &[u64]) {
+    if src.len() > dst.len() {
+        dst.resize(src.len(), 0);
+    }
+    for (a, b) in dst.iter_mut().zip(src) {
+        *a += b;
+    }
+}
+
+/// 公開版の停止時間関数用: 呼び出し側の gpk_stats を SeedStats に移して f を走らせ、書き戻す
+fn with_seed_stats<R>(gpk_stats: Option<&mut GpkStats>, f: impl FnOnce(&mut SeedStats) -> R) -> R {
+    let mut stats = SeedStats::default();
+    match gpk_stats {
+        Some(gpk) => {
+            stats.gpk = Some(std::mem::replace(gpk, GpkStats::new()));
+            let result = f(&mut stats);
+            if let Some(collected) = stats.gpk.take() {
+                *gpk = collected;
+            }
+            result
+        }
+        None => f(&mut stats),
+    }
+}
+
+/// 中断フラグによって軌道の途中で打ち切られたこと。max_steps 超過などの失敗（Ok(None)）と区別する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cancelled;
+
+/// cancel = None で呼んだ停止時間の結果を取り出す（中断されることはない）
+fn without_cancel(result: Result<Option<u64>, Cancelled>) -> Option<u64> {
+    result.unwrap_or_else(|Cancelled| unreachable!("cancel なしで中断された"))
+}
+
+/// 長い軌道の途中で中断フラグを確認する間隔（ステップ数、2 の冪）
+const CANCEL_CHECK_INTERVAL: u64 = 1 << 16;
+
+/// steps が CANCEL_CHECK_INTERVAL の倍数のときだけ中断フラグを読む
+#[inline]
+fn cancel_requested(cancel: Option<&AtomicBool>, steps: u64) -> bool {
+    steps & (CANCEL_CHECK_INTERVAL - 1) == 0
+        && cancel.is_some_and(|c| c.load(AtomicOrdering::Relaxed))
+}
+
+/// リム列（LSB first、64 ビット単位）を m4/m6 ワード列に分解して1ステップ分の GPK 統計を加える。
+/// GPK は packed::gpk_stats_from_words（extract_window ベース）で計算する。
+fn accumulate_gpk_limbs(limbs: &[u64], bit_len: usize, x: u64, stats: &mut GpkStats) {
+    let pair_count = bit_len.div_ceil(2);
+    let word_count = pair_count.div_ceil(64);
+    let mut m4 = vec![0u64; word_count];
+    let mut m6 = vec![0u64; word_count];
+    // リム j はペア 32j .. 32j+31 を持つ
+    for (j, &limb) in limbs.iter().enumerate() {
+        let (w, shift) = (j / 2, 32 * (j % 2) as u32);
+        if w >= word_count { break; }
+        m6[w] |= compact_even_bits(limb) << shift;
+        m4[w] |= compact_even_bits(limb >> 1) << shift;
+    }
+
+    let (g_count, p_count, k_count, max_chain) = packed::gpk_stats_from_words(&m4, &m6, pair_count, x);
+
+    stats.record_step(g_count, p_count, k_count, pair_count, max_chain);
+}
+
+/// u128 値から直接 GPK 統計を計算する。
+fn accumulate_gpk_u128(n: u128, x: u64, stats: &mut GpkStats) {
+    if n == 0 { return; }
+    let bit_len = 128 - n.leading_zeros() as usize;
+    accumulate_gpk_limbs(&[n as u64, (n >> 64) as u64], bit_len, x, stats);
 }
 
 /// U256 値から直接 GPK 統計を計算する。
 fn accumulate_gpk_u256(n: &U256, x: u64, stats: &mut GpkStats) {
     let bl = n.bit_len();
     if bl == 0 { return; }
-    let bit_len = bl as usize;
-    let pair_count = (bit_len + 1) / 2;
-
-    let xm1 = x - 1;
-    let s = xm1.trailing_zeros();
-    let t = (s / 2) as isize;
-    let s_is_even = s % 2 == 0;
-
-    // U256 からビット取得
-    let get_bit = |pos: usize| -> u8 {
-        if pos >= 256 { return 0; }
-        let limb = pos / 64;
-        let bit = pos % 64;
-        ((n.0[limb] >> bit) & 1) as u8
-    };
-    let get_a = |i: isize| -> u8 {
-        if i < 0 || (i as usize) >= pair_count { return 0; }
-        get_bit(2 * i as usize + 1)
-    };
-    let get_b = |i: isize| -> u8 {
-        if i < 0 || (i as usize) >= pair_count { return 0; }
-        get_bit(2 * i as usize)
-    };
-
-    let mut g_count = 0u32;
-    let mut p_count = 0u32;
-    let mut k_count = 0u32;
-    let mut carry = true;
-    let mut chain = 0u32;
-    let mut max_chain = 0u32;
-
-    for i in 0..pair_count {
-        let ii = i as isize;
-        let ai = get_a(ii);
-        let bi = get_b(ii);
-
-        let (p_r, q_r, p_l, q_l) = if s_is_even {
-            (get_b(ii - t), bi, get_a(ii - t), ai)
-        } else {
-            (get_a(ii - t - 1), bi, get_b(ii - t), ai)
-        };
-
-        let g_mid = p_r & q_r;
-        let p_mid = p_r ^ q_r;
-        let g_out = p_l & q_l;
-        let p_out = p_l ^ q_l;
-        let g_i = g_out | (p_out & g_mid);
-        let p_i = p_out & p_mid;
-
-        if g_i != 0 {
-            g_count += 1;
-            chain += 1;
-            carry = true;
-        } else if p_i != 0 {
-            p_count += 1;
-            if carry { chain += 1; }
-        } else {
-            k_count += 1;
-            if chain > max_chain { max_chain = chain; }
-            chain = 0;
-            carry = false;
-        }
-    }
-    if chain > max_chain { max_chain = chain; }
-
-    stats.total_g += g_count as u64;
-    stats.total_p += p_count as u64;
-    stats.total_k += k_count as u64;
-    stats.total_pairs += pair_count as u64;
-    stats.total_steps += 1;
-    let idx = (max_chain as usize).min(127);
-    stats.carry_chain_hist[idx] += 1;
+    accumulate_gpk_limbs(&n.0, bl as usize, x, stats);
 }
 
 /// 停止時間法: n 未満の値に到達するまでのステップ数を返す。
@@ -450,56 +801,483 @@ pub fn stopping_time_with_gpk(
     n: &BigUint,
     x: u64,
     max_steps: u64,
-    mut gpk_stats: Option<&mut GpkStats>,
+    gpk_stats: Option<&mut GpkStats>,
+    use_stopping_time: bool,
+) -> Option<u64> {
+    with_seed_stats(gpk_stats, |stats| stopping_time_with_gpk_d(n, x, max_steps, use_stopping_time, stats))
+}
+
+/// stopping_time_with_gpk の本体。GPK・d・最大ビット長・停止の種類を stats に加える
+pub(crate) fn stopping_time_with_gpk_d(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
     use_stopping_time: bool,
+    stats: &mut SeedStats,
 ) -> Option<u64> {
+    without_cancel(stopping_time_with_gpk_cancellable_d(n, x, max_steps, use_stopping_time, stats, None))
+}
+
+/// stopping_time_with_gpk_d の中断対応版。cancel が Some なら CANCEL_CHECK_INTERVAL ステップごとに
+/// 確認し、立っていれば Err(Cancelled) で打ち切る（stats にはそこまでのステップが入っている）
+pub(crate) fn stopping_time_with_gpk_cancellable_d(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    use_stopping_time: bool,
+    stats: &mut SeedStats,
+    cancel: Option<&AtomicBool>,
+) -> Result<Option<u64>, Cancelled> {
+    stats.record_bits(n.bits());
     if *n == BigUint::one() {
+        stats.reached_one += 1;
+        return Ok(Some(0));
+    }
+
+    let initial_pn = PairNumber::from_biguint(n);
+    let bound = if use_stopping_time { Some(&initial_pn) } else { None };
+    stopping_time_packed(&initial_pn, x, max_steps, bound, stats, cancel)
+}
+
+/// 大域下限付き停止時間: 軌道が bound 未満（または 1）に到達するまでのステップ数。
+/// bound 未満の奇数が全て収束済みと分かっていれば、これで帰納的に検証できる。
+/// n 自身が bound 未満なら Some(0)。bound = n なら stopping_time と一致する。
+pub fn stopping_time_below(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    bound: &BigUint,
+    gpk_stats: Option<&mut GpkStats>,
+) -> Option<u64> {
+    with_seed_stats(gpk_stats, |stats| stopping_time_below_d(n, x, max_steps, bound, stats))
+}
+
+/// stopping_time_below の本体。stats は stopping_time_with_gpk_d と同じ
+pub(crate) fn stopping_time_below_d(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    bound: &BigUint,
+    stats: &mut SeedStats,
+) -> Option<u64> {
+    stats.record_bits(n.bits());
+    if *n == BigUint::one() {
+        stats.reached_one += 1;
+        return Some(0);
+    }
+    if n < bound {
+        stats.dropped_below += 1;
         return Some(0);
     }
 
-    let collect_gpk = gpk_stats.is_some();
     let initial_pn = PairNumber::from_biguint(n);
+    let bound_pn = PairNumber::from_biguint(bound);
+    without_cancel(stopping_time_packed(&initial_pn, x, max_steps, Some(&bound_pn), stats, None))
+}
+
+/// 停止時間計算の共通ループ（パックドスキャン）。
+/// bound が Some なら bound 未満への到達で停止、None なら n=1 まで追跡する。
+fn stopping_time_packed(
+    initial_pn: &PairNumber,
+    x: u64,
+    max_steps: u64,
+    bound: Option<&PairNumber>,
+    stats: &mut SeedStats,
+    cancel: Option<&AtomicBool>,
+) -> Result<Option<u64>, Cancelled> {
+    let collect_gpk = stats.gpk.is_some();
     let mut pn = initial_pn.clone();
     let mut steps = 0u64;
+    // bound が u64 に収まるなら、ループ内の比較は lt_u64（ワード列の cmp を避ける）
+    let bound_u64 = bound.and_then(PairNumber::to_u64);
 
     while steps < max_steps {
-        let result = if x == 3 {
-            packed::packed_step_3n1_opt(&pn, collect_gpk)
-        } else if x == 5 {
-            packed::packed_step_5n1_opt(&pn, collect_gpk)
-        } else {
-            packed::packed_step_generic_opt(&pn, x, collect_gpk)
+        if cancel_requested(cancel, steps) { return Err(Cancelled); }
+        let result = packed::step_for_x(&pn, x, collect_gpk);
+        stats.record_packed(&result, pn.pair_count());
+
+        let next = PairNumber::from_packed(
+            result.new_m4, result.new_m6, result.new_pair_count);
+        steps += 1;
+
+        stats.record_d(result.d);
+        stats.record_bits(next.bit_len() as u64);
+
+        if next.is_one() {
+            stats.reached_one += 1;
+            return Ok(Some(steps));
+        }
+        let below = match bound_u64 {
+            Some(b) => next.lt_u64(b),
+            None => bound.is_some_and(|b| next < *b),
         };
+        if below {
+            stats.dropped_below += 1;
+            return Ok(Some(steps));
+        }
+        // ビット長制限: 発散防止
+        if next.pair_count() > MAX_PAIR_COUNT {
+            return Ok(None);
+        }
+
+        pn = next;
+    }
+
+    Ok(None)
+}
+
+/// start から stop が真になるまで（または max_steps ステップまで）パックドスキャンで進め、
+/// (最終状態, ステップ数) を返す。GPK・d・軌道は一切記録しない。
+/// stop は各ステップの前に評価する（start が満たせば 0 ステップ）。ステップ数が max_steps なら
+/// stop を満たさずに打ち切られた可能性があるので、必要なら最終状態に stop をもう一度当てる。
+/// ビット長の上限はないので、発散しうる写像では `|pn| pn.pair_count() > limit` などを stop に含める。
+/// start は奇数であること。
+pub fn run_until(
+    start: PairNumber,
+    x: u64,
+    stop: impl Fn(&PairNumber) -> bool,
+    max_steps: u64,
+) -> (PairNumber, u64) {
+    let mut pn = start;
+    let mut steps = 0u64;
+    while steps < max_steps && !stop(&pn) {
+        let result = packed::step_for_x(&pn, x, false);
+        pn = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+        steps += 1;
+    }
+    (pn, steps)
+}
+
+/// 1 回の走査で停止時間（glide: n 未満への初到達）と全停止時間（1 への到達）を同時に求める。
+/// n 未満に落ちた後も 1 まで追跡を続ける。max_steps 以内に届かなかった方は None。
+/// n が u128 演算で扱える間は u128、溢れたらパックドスキャンで続ける。
+pub fn glide_and_total(n: &BigUint, x: u64, max_steps: u64) -> (Option<u64>, Option<u64>) {
+    if n.is_one() {
+        return (Some(0), Some(0));
+    }
+
+    let mut glide: Option<u64> = None;
+    let mut steps = 0u64;
+
+    // u128 フェーズ
+    let start_digits = n.to_u64_digits();
+    let mut big_current: Option<BigUint> = None;
+    if start_digits.len() <= 1 {
+        let n128 = start_digits.first().copied().unwrap_or(0) as u128;
+        let x128 = x as u128;
+        let overflow_limit = (u128::MAX - 1) / x128;
+        let mut current = n128;
+        while steps < max_steps {
+            if current > overflow_limit {
+                big_current = Some(BigUint::from(current));
+                break;
+            }
+            current = odd_step_u128(current, x128).0;
+            steps += 1;
+            if glide.is_none() && current < n128 {
+                glide = Some(steps);
+            }
+            if current == 1 {
+                return (glide, Some(steps));
+            }
+        }
+        if big_current.is_none() {
+            return (glide, None);
+        }
+    }
+
+    // パックドスキャン フェーズ
+    let initial_pn = PairNumber::from_biguint(n);
+    let mut pn = match big_current {
+        Some(c) => PairNumber::from_biguint(&c),
+        None => initial_pn.clone(),
+    };
+    while steps < max_steps {
+        let result = packed::step_for_x(&pn, x, false);
+        let next = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+        steps += 1;
+
+        if glide.is_none() && next < initial_pn {
+            glide = Some(steps);
+        }
+        if next.is_one() {
+            return (glide, Some(steps));
+        }
+        // ビット長制限: 発散防止
+        if next.pair_count() > MAX_PAIR_COUNT {
+            return (glide, None);
+        }
+        pn = next;
+    }
+
+    (glide, None)
+}
+
+/// 連続する奇数→奇数ステップの合成: n -> (a·n + b) / 2^d。
+/// 各ステップの d が分かれば値を知らなくても合成でき、割り算は常に割り切れる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentTransform {
+    pub a: BigUint,
+    pub b: BigUint,
+    /// 区間内の d の合計
+    pub d: u64,
+    /// 区間のステップ数
+    pub steps: u64,
+}
+
+impl SegmentTransform {
+    /// d 列 d_1, d_2, ... の順に (xn+1)/2^d_i を合成する。
+    /// 1 ステップ (x·(a·n + b)/2^D + 1)/2^d_i = (x·a·n + x·b + 2^D)/2^(D+d_i) より
+    /// a ← x·a, b ← x·b + 2^D, D ← D + d_i。
+    pub fn from_d_values(x: u64, d_values: &[u64]) -> Self {
+        let mut a = BigUint::one();
+        let mut b = BigUint::ZERO;
+        let mut d = 0u64;
+        for &di in d_values {
+            a *= x;
+            b = b * x + (BigUint::one() << d);
+            d += di;
+        }
+        SegmentTransform { a, b, d, steps: d_values.len() as u64 }
+    }
+
+    /// 区間の先頭の値 n から末尾の値を求める
+    pub fn apply(&self, n: &BigUint) -> BigUint {
+        (&self.a * n + &self.b) >> self.d
+    }
+}
+
+/// trace_segmented の結果
+#[derive(Debug, Clone)]
+pub struct SegmentedTrace {
+    /// 各ステップの d
+    pub d_values: Vec<u64>,
+    /// 区間ごとのアフィン変換（segment ステップずつ、最後は端数）
+    pub segments: Vec<SegmentTransform>,
+    /// 区間の境界の値: [開始値, 区間1の末尾, 区間2の末尾, ...]。最後が最終値
+    pub boundary_values: Vec<BigUint>,
+    pub total_steps: u64,
+    pub reached_one: bool,
+}
+
+/// 区間分割による1シードの軌道追跡。
+/// 1. d 列だけを逐次に求める（u128 → パックドの停止時間と同じ経路、値は記録しない）
+/// 2. d 列を segment ステップずつに区切り、各区間のアフィン変換を rayon で並列に合成する
+/// 3. 変換を順に当てて区間境界の値を得る（区間あたり大きな乗算 1 回）
+///
+/// 1 は逐次のままなので軌道全体が速くなるわけではないが、境界値だけが欲しいときに
+/// 全ステップの値を BigUint で持たずに済む。segment=0 は 1 として扱う。
+pub fn trace_segmented(start: &BigUint, x: u64, max_steps: u64, segment: u64) -> SegmentedTrace {
+    let segment = segment.max(1) as usize;
+    let (d_values, reached_one) = d_sequence(start, x, max_steps);
+
+    let segments: Vec<SegmentTransform> = d_values
+        .par_chunks(segment)
+        .map(|ds| SegmentTransform::from_d_values(x, ds))
+        .collect();
+
+    let mut boundary_values = Vec::with_capacity(segments.len() + 1);
+    boundary_values.push(start.clone());
+    for seg in &segments {
+        let next = seg.apply(boundary_values.last().unwrap());
+        boundary_values.push(next);
+    }
+
+    SegmentedTrace {
+        total_steps: d_values.len() as u64,
+        d_values,
+        segments,
+        boundary_values,
+        reached_one,
+    }
+}
+
+/// replay で、記録された d が xn+1 の実際の 2 の指数と食い違った
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayError {
+    /// 食い違ったステップの添字（ds の位置）
+    pub step: usize,
+    /// ds に記録されていた d
+    pub expected_d: u64,
+    /// そのステップの xn+1 の末尾ゼロの数
+    pub actual_d: u64,
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "step {}: recorded d = {} but xn+1 has {} trailing zeros", self.step, self.expected_d, self.actual_d)
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// 開始値と d 列だけから軌道を復元する（d 列だけの軌道保存の検算付き再生）。
+/// 各ステップで xn+1 の末尾ゼロの数が記録された d と一致することを確かめてから (xn+1)>>d を当てる。
+/// 戻り値は各ステップ後の値（ds と同じ長さ、開始値は含まない）。一致しなければ最初の食い違いを返す。
+pub fn replay(start: &BigUint, x: u64, ds: &[u64]) -> Result<Vec<BigUint>, ReplayError> {
+    let mut values = Vec::with_capacity(ds.len());
+    let mut n = start.clone();
+    for (step, &d) in ds.iter().enumerate() {
+        let xn1 = &n * x + 1u32;
+        let actual_d = xn1.trailing_zeros().unwrap_or(0);
+        if actual_d != d {
+            return Err(ReplayError { step, expected_d: d, actual_d });
+        }
+        n = xn1 >> d;
+        values.push(n.clone());
+    }
+    Ok(values)
+}
+
+/// 1 に到達するまで（または max_steps まで）の d 列と、1 に到達したか。
+fn d_sequence(start: &BigUint, x: u64, max_steps: u64) -> (Vec<u64>, bool) {
+    let mut d_values = Vec::new();
+    if start.is_one() {
+        return (d_values, true);
+    }
+
+    // u128 フェーズ
+    let digits = start.to_u64_digits();
+    let mut pn = if digits.len() <= 1 {
+        let x128 = x as u128;
+        let overflow_limit = (u128::MAX - 1) / x128;
+        let mut current = digits.first().copied().unwrap_or(0) as u128;
+        while (d_values.len() as u64) < max_steps && current <= overflow_limit {
+            let (next, d) = odd_step_u128(current, x128);
+            current = next;
+            d_values.push(d as u64);
+            if current == 1 {
+                return (d_values, true);
+            }
+        }
+        PairNumber::from_u128(current)
+    } else {
+        PairNumber::from_biguint(start)
+    };
 
-        if let Some(ref mut stats) = gpk_stats {
-            stats.total_g += result.g_count as u64;
-            stats.total_p += result.p_count as u64;
-            stats.total_k += result.k_count as u64;
-            stats.total_pairs += pn.pair_count() as u64;
-            stats.total_steps += 1;
-            let idx = (result.max_carry_chain as usize).min(127);
-            stats.carry_chain_hist[idx] += 1;
+    // パックドスキャン フェーズ
+    while (d_values.len() as u64) < max_steps {
+        let result = packed::step_for_x(&pn, x, false);
+        d_values.push(result.d);
+        let next = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+        if next.is_one() {
+            return (d_values, true);
+        }
+        // ビット長制限: 発散防止
+        if next.pair_count() > MAX_PAIR_COUNT {
+            return (d_values, false);
         }
+        pn = next;
+    }
+    (d_values, false)
+}
+
+/// 既知サイクル判定付き停止時間の結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoppingOutcome {
+    /// n 未満（または 1）に到達した。値はステップ数
+    Stopped(u64),
+    /// 既知サイクルの要素に到達した。値はステップ数
+    EnteredCycle(u64),
+    /// max_steps 以内に判定できなかった（またはビット長制限超過）
+    Unresolved,
+    /// ビット長が開始値より growth_limit_bits を超えて伸びた（発散の疑い）。値はステップ数
+    LikelyDivergent(u64),
+}
+
+/// 停止時間法（既知サイクル判定付き）。
+/// 軌道が known_cycles のいずれかのサイクルの要素に到達し、かつそのサイクルの
+/// 最小元が n 以上（= サイクル内で n 未満に落ちることがない）なら EnteredCycle を返す。
+/// 最小元が n 未満のサイクルは通常どおり追跡を続けるので、結果は
+/// stopping_time_with_gpk の None（失敗）の一部を EnteredCycle に振り替えたものになる。
+pub fn stopping_time_or_cycle(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    known_cycles: &[Vec<u64>],
+    gpk_stats: Option<&mut GpkStats>,
+) -> StoppingOutcome {
+    with_seed_stats(gpk_stats, |stats| stopping_time_or_cycle_d(n, x, max_steps, known_cycles, None, stats))
+}
+
+/// 停止時間法（成長上限付き）。
+/// 軌道のビット長が n のビット長 + growth_limit_bits を超えたら LikelyDivergent を返す
+/// （n * 2^growth_limit_bits 超えのビット長による安価な近似）。5n+1 等の発散しそうな
+/// シードを、max_steps 超過の失敗（Unresolved）と分けて扱うための経験的な判定。
+pub fn stopping_time_or_growth(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    growth_limit_bits: u64,
+    gpk_stats: Option<&mut GpkStats>,
+) -> StoppingOutcome {
+    with_seed_stats(gpk_stats, |stats| stopping_time_or_cycle_d(n, x, max_steps, &[], Some(growth_limit_bits), stats))
+}
+
+/// stopping_time_or_cycle / stopping_time_or_growth の共通ループ。
+/// growth_limit_bits が Some なら成長上限も判定する。stats は stopping_time_with_gpk_d と同じ。
+pub(crate) fn stopping_time_or_cycle_d(
+    n: &BigUint,
+    x: u64,
+    max_steps: u64,
+    known_cycles: &[Vec<u64>],
+    growth_limit_bits: Option<u64>,
+    stats: &mut SeedStats,
+) -> StoppingOutcome {
+    stats.record_bits(n.bits());
+    if *n == BigUint::one() {
+        stats.reached_one += 1;
+        return StoppingOutcome::Stopped(0);
+    }
+
+    // n 未満に落ちないサイクル（最小元 >= n）の要素のみを判定対象にする
+    let mut members: Vec<PairNumber> = known_cycles
+        .iter()
+        .filter(|cycle| cycle.iter().all(|&c| BigUint::from(c) >= *n))
+        .flatten()
+        .map(|&c| PairNumber::from_biguint(&BigUint::from(c)))
+        .collect();
+    members.sort();
+
+    let collect_gpk = stats.gpk.is_some();
+    let initial_pn = PairNumber::from_biguint(n);
+    let growth_cap = growth_limit_bits.map(|g| initial_pn.bit_len() as u64 + g);
+    let mut pn = initial_pn.clone();
+    let mut steps = 0u64;
+
+    while steps < max_steps {
+        let result = packed::step_for_x(&pn, x, collect_gpk);
+        stats.record_packed(&result, pn.pair_count());
 
         let next = PairNumber::from_packed(
             result.new_m4, result.new_m6, result.new_pair_count);
         steps += 1;
 
+        stats.record_d(result.d);
+        stats.record_bits(next.bit_len() as u64);
+
         if next.is_one() {
-            return Some(steps);
+            stats.reached_one += 1;
+            return StoppingOutcome::Stopped(steps);
         }
-        if use_stopping_time && next < initial_pn {
-            return Some(steps);
+        if next < initial_pn {
+            stats.dropped_below += 1;
+            return StoppingOutcome::Stopped(steps);
+        }
+        if members.binary_search(&next).is_ok() {
+            return StoppingOutcome::EnteredCycle(steps);
+        }
+        if growth_cap.is_some_and(|cap| next.bit_len() as u64 > cap) {
+            return StoppingOutcome::LikelyDivergent(steps);
         }
-        // ビット長制限: 発散防止
         if next.pair_count() > MAX_PAIR_COUNT {
-            return None;
+            return StoppingOutcome::Unresolved;
         }
 
         pn = next;
     }
 
-    None
+    StoppingOutcome::Unresolved
 }
 
 /// u64 入力の高速停止時間計算。u128 演算を使い、オーバーフロー時はパックドスキャンにフォールバック。
@@ -509,34 +1287,88 @@ pub fn stopping_time_u64_fast(
     n: u64,
     x: u64,
     max_steps: u64,
-    mut gpk_stats: Option<&mut GpkStats>,
+    gpk_stats: Option<&mut GpkStats>,
     use_phase1: bool,
     use_stopping_time: bool,
 ) -> Option<u64> {
-    if n == 1 { return Some(0); }
+    with_seed_stats(gpk_stats, |stats| stopping_time_u64_fast_d(n, x, max_steps, use_phase1, use_stopping_time, stats))
+}
+
+/// stopping_time_u64_fast の本体。stats は stopping_time_with_gpk_d と同じ
+pub(crate) fn stopping_time_u64_fast_d(
+    n: u64,
+    x: u64,
+    max_steps: u64,
+    use_phase1: bool,
+    use_stopping_time: bool,
+    stats: &mut SeedStats,
+) -> Option<u64> {
+    stopping_time_u64_below_d(n, n, x, max_steps, use_phase1, use_stopping_time, stats)
+}
+
+/// stopping_time_u64_fast_d の下限指定版: use_stopping_time なら n 自身ではなく bound 未満への
+/// 到達で停止する（bound = n で通常の停止時間法）。n < bound なら 0。
+/// stopping_time_below_d の u64 版で、u128 / U256 フェーズをそのまま使える。
+pub(crate) fn stopping_time_u64_below_d(
+    n: u64,
+    bound: u64,
+    x: u64,
+    max_steps: u64,
+    use_phase1: bool,
+    use_stopping_time: bool,
+    stats: &mut SeedStats,
+) -> Option<u64> {
+    without_cancel(stopping_time_u64_cancellable_d(n, bound, x, max_steps, use_phase1, use_stopping_time, stats, None))
+}
+
+/// stopping_time_u64_below_d の中断対応版。cancel の扱いは stopping_time_with_gpk_cancellable_d と同じ
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn stopping_time_u64_cancellable_d(
+    n: u64,
+    bound: u64,
+    x: u64,
+    max_steps: u64,
+    use_phase1: bool,
+    use_stopping_time: bool,
+    stats: &mut SeedStats,
+    cancel: Option<&AtomicBool>,
+) -> Result<Option<u64>, Cancelled> {
+    stats.record_bits((64 - n.leading_zeros()) as u64);
+    if n == 1 {
+        stats.reached_one += 1;
+        return Ok(Some(0));
+    }
+    if use_stopping_time && n < bound {
+        stats.dropped_below += 1;
+        return Ok(Some(0));
+    }
 
     let x128 = x as u128;
-    let n128 = n as u128;
-    let mut current = n128;
+    let bound128 = bound as u128;
+    let mut current = n as u128;
     let overflow_limit = (u128::MAX - 1) / x128;
     let mut steps = 0u64;
 
     // Phase 1: u128 演算（use_phase1=false ならスキップ）
     while use_phase1 && steps < max_steps && current <= overflow_limit {
-        if let Some(ref mut stats) = gpk_stats {
-            accumulate_gpk_u128(current, x, stats);
+        if cancel_requested(cancel, steps) { return Err(Cancelled); }
+        if let Some(gpk) = stats.gpk.as_mut() {
+            accumulate_gpk_u128(current, x, gpk);
         }
 
-        let xn1 = current * x128 + 1;
-        let d = xn1.trailing_zeros();
-        current = xn1 >> d;
+        let (next, d) = odd_step_u128(current, x128);
+        current = next;
         steps += 1;
+        stats.record_d(d as u64);
+        stats.record_bits((128 - current.leading_zeros()) as u64);
 
         if current == 1 {
-            return Some(steps);
+            stats.reached_one += 1;
+            return Ok(Some(steps));
         }
-        if use_stopping_time && current < n128 {
-            return Some(steps);
+        if use_stopping_time && current < bound128 {
+            stats.dropped_below += 1;
+            return Ok(Some(steps));
         }
     }
 
@@ -545,104 +1377,107 @@ pub fn stopping_time_u64_fast(
         let mut cur256 = U256::from_u128(current);
 
         while steps < max_steps {
-            if let Some(ref mut stats) = gpk_stats {
-                accumulate_gpk_u256(&cur256, x, stats);
-            }
-
-            let Some(xn1) = cur256.mul_small_checked(x).map(|v| v.add_one()) else {
+            if cancel_requested(cancel, steps) { return Err(Cancelled); }
+            let Some((next256, d)) = cur256.odd_step(x) else {
                 // U256 もオーバーフロー → Phase 2 へ
                 let _ = current; // Phase 2 で cur256 から変換する
                 let big_current = cur256.to_biguint();
-                let collect_gpk = gpk_stats.is_some();
-                let initial_pn = PairNumber::from_biguint(&BigUint::from(n));
+                let collect_gpk = stats.gpk.is_some();
+                let bound_pn = PairNumber::from_two_limbs(bound, 0);
                 let mut pn = PairNumber::from_biguint(&big_current);
 
                 while steps < max_steps {
-                    let result = if x == 3 {
-                        packed::packed_step_3n1_opt(&pn, collect_gpk)
-                    } else if x == 5 {
-                        packed::packed_step_5n1_opt(&pn, collect_gpk)
-                    } else {
-                        packed::packed_step_generic_opt(&pn, x, collect_gpk)
-                    };
-
-                    if let Some(ref mut stats) = gpk_stats {
-                        stats.total_g += result.g_count as u64;
-                        stats.total_p += result.p_count as u64;
-                        stats.total_k += result.k_count as u64;
-                        stats.total_pairs += pn.pair_count() as u64;
-                        stats.total_steps += 1;
-                        let idx = (result.max_carry_chain as usize).min(127);
-                        stats.carry_chain_hist[idx] += 1;
-                    }
+                    if cancel_requested(cancel, steps) { return Err(Cancelled); }
+                    let result = packed::step_for_x(&pn, x, collect_gpk);
+                    stats.record_packed(&result, pn.pair_count());
 
                     let next = PairNumber::from_packed(
                         result.new_m4, result.new_m6, result.new_pair_count);
                     steps += 1;
 
-                    if next.is_one() { return Some(steps); }
-                    if use_stopping_time && next < initial_pn { return Some(steps); }
-                    if next.pair_count() > MAX_PAIR_COUNT { return None; }
+                    stats.record_d(result.d);
+                    stats.record_bits(next.bit_len() as u64);
+
+                    if next.is_one() {
+                        stats.reached_one += 1;
+                        return Ok(Some(steps));
+                    }
+                    if use_stopping_time && next < bound_pn {
+                        stats.dropped_below += 1;
+                        return Ok(Some(steps));
+                    }
+                    if next.pair_count() > MAX_PAIR_COUNT { return Ok(None); }
                     pn = next;
                 }
-                return None;
+                return Ok(None);
             };
 
-            let d = xn1.trailing_zeros();
-            cur256 = xn1.shr(d);
+            // GPK はオーバーフローしないと分かってから集計する（Phase 2 で同じステップを再計算するため）
+            if let Some(gpk) = stats.gpk.as_mut() {
+                accumulate_gpk_u256(&cur256, x, gpk);
+            }
+            cur256 = next256;
             steps += 1;
+            stats.record_d(d as u64);
+            stats.record_bits(cur256.bit_len() as u64);
 
-            if cur256.is_one() { return Some(steps); }
-            if use_stopping_time && cur256.lt_u128(n128) { return Some(steps); }
+            if cur256.is_one() {
+                stats.reached_one += 1;
+                return Ok(Some(steps));
+            }
+            if use_stopping_time && cur256.lt_u128(bound128) {
+                stats.dropped_below += 1;
+                return Ok(Some(steps));
+            }
         }
-        return None;
+        return Ok(None);
     }
 
     // Phase 2: パックドスキャン フォールバック（use_phase1=false 時）
-    let collect_gpk = gpk_stats.is_some();
+    let collect_gpk = stats.gpk.is_some();
     if steps < max_steps {
-        let initial_pn = PairNumber::from_biguint(&BigUint::from(n));
-        let big_current = BigUint::from(current);
-        let mut pn = PairNumber::from_biguint(&big_current);
+        let bound_pn = PairNumber::from_two_limbs(bound, 0);
+        let mut pn = PairNumber::from_u128(current);
 
         while steps < max_steps {
-            let result = if x == 3 {
-                packed::packed_step_3n1_opt(&pn, collect_gpk)
-            } else if x == 5 {
-                packed::packed_step_5n1_opt(&pn, collect_gpk)
-            } else {
-                packed::packed_step_generic_opt(&pn, x, collect_gpk)
-            };
-
-            if let Some(ref mut stats) = gpk_stats {
-                stats.total_g += result.g_count as u64;
-                stats.total_p += result.p_count as u64;
-                stats.total_k += result.k_count as u64;
-                stats.total_pairs += pn.pair_count() as u64;
-                stats.total_steps += 1;
-                let idx = (result.max_carry_chain as usize).min(127);
-                stats.carry_chain_hist[idx] += 1;
-            }
+            if cancel_requested(cancel, steps) { return Err(Cancelled); }
+            let result = packed::step_for_x(&pn, x, collect_gpk);
+            stats.record_packed(&result, pn.pair_count());
 
             let next = PairNumber::from_packed(
                 result.new_m4, result.new_m6, result.new_pair_count);
             steps += 1;
 
+            stats.record_d(result.d);
+            stats.record_bits(next.bit_len() as u64);
+
             if next.is_one() {
-                return Some(steps);
+                stats.reached_one += 1;
+                return Ok(Some(steps));
             }
-            if use_stopping_time && next < initial_pn {
-                return Some(steps);
+            if use_stopping_time && next < bound_pn {
+                stats.dropped_below += 1;
+                return Ok(Some(steps));
             }
             if next.pair_count() > MAX_PAIR_COUNT {
-                return None;
+                return Ok(None);
             }
 
             pn = next;
         }
     }
 
-    None
+    Ok(None)
+}
+
+/// 候補シード列から停止時間が最大のものを並列に探す。
+/// 戻り値は (シード, 停止時間)。max_steps 以内に停止しないシードは除外する。
+/// 同じ停止時間のシードが複数あれば最小のシードを返す。候補が空なら None。
+pub fn max_stopping_time_seed(seeds: &[u64], x: u64, max_steps: u64) -> Option<(u64, u64)> {
+    seeds
+        .par_iter()
+        .filter_map(|&n| stopping_time_u64_fast(n, x, max_steps, None, true, true).map(|st| (n, st)))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
 }
 
 /// キャンセル可能な軌道追跡。cancel が true になると途中結果を返す。
@@ -661,6 +1496,7 @@ pub fn trace_trajectory_cancellable(
     let mut total_steps = 0u64;
     let mut max_value = start.clone();
     let mut reached_one = pair.is_one();
+    let mut stop_reason = if reached_one { StopReason::ReachedOne } else { StopReason::MaxSteps };
 
     // 初期値の m4/m6 を記録
     pair_steps.push(PairStep {
@@ -673,16 +1509,11 @@ pub fn trace_trajectory_cancellable(
 
     while !reached_one && total_steps < max_steps {
         if cancel.load(AtomicOrdering::Relaxed) {
+            stop_reason = StopReason::Cancelled;
             break;
         }
 
-        let result = if x == 3 {
-            scan::collatz_step_3n1(&pair)
-        } else if x == 5 {
-            scan::collatz_step_5n1(&pair)
-        } else {
-            scan::collatz_step(&pair, x)
-        };
+        let result = scan::collatz_step_for_x(&pair, x);
 
         total_steps += 1;
         gpk_stats.accumulate(&result.gpk);
@@ -712,10 +1543,12 @@ pub fn trace_trajectory_cancellable(
 
         if result.next.is_one() {
             reached_one = true;
+            stop_reason = StopReason::ReachedOne;
         }
 
         // ビット長制限: 発散防止
         if result.next.pair_count() > MAX_PAIR_COUNT {
+            stop_reason = StopReason::ExceededPairCount;
             break;
         }
 
@@ -731,5 +1564,484 @@ pub fn trace_trajectory_cancellable(
         total_steps,
         max_value,
         reached_one,
+        stop_reason,
+        gpk_by_bitlen_bucket: Vec::new(),
+    }
+}
+
+/// orbits_to_dot が書き出す辺の上限。超えた分は書かずにコメントで打ち切りを示す。
+pub const DOT_MAX_EDGES: usize = 100_000;
+
+/// シード集合の軌道を Graphviz DOT で書き出す（合流する木構造の可視化用）。
+/// 奇数→奇数の各ステップを辺 `n -> T(n)` とし、ノードのラベルは値（10進）。
+/// 既に辿った値に合流したらそこで打ち切るので、共有する末尾の辺は1回だけ出る。
+/// 各軌道は 1 に到達するか max_steps ステップで止める。偶数のシードは飛ばす。
+/// 辺の総数が DOT_MAX_EDGES に達したら残りは書かない。
+pub fn orbits_to_dot(seeds: &[u64], x: u64, max_steps: u64, mut w: impl std::io::Write) -> std::io::Result<()> {
+    let stepper = packed::Stepper::new(x);
+    let mut visited: std::collections::HashSet<BigUint> = std::collections::HashSet::new();
+    let mut edges = 0usize;
+
+    writeln!(w, "digraph collatz_{}n1 {{", x)?;
+    'seeds: for &seed in seeds.iter().filter(|&&n| n % 2 == 1) {
+        let mut pn = PairNumber::from_two_limbs(seed, 0);
+        let mut value = BigUint::from(seed);
+        for _ in 0..max_steps {
+            if value.is_one() || !visited.insert(value.clone()) {
+                break;
+            }
+            if edges >= DOT_MAX_EDGES {
+                writeln!(w, "  // truncated at {} edges", DOT_MAX_EDGES)?;
+                break 'seeds;
+            }
+            let result = stepper.packed_step_opt(&pn, false);
+            pn = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+            let next = pn.to_biguint();
+            writeln!(w, "  \"{}\" -> \"{}\";", value, next)?;
+            edges += 1;
+            value = next;
+        }
+    }
+    writeln!(w, "}}")?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_segmented_matches_sequential() {
+        let big = (BigUint::one() << 300u32) - BigUint::one();
+        for (start, x, segment) in [
+            (BigUint::from(27u64), 3u64, 1u64),
+            (BigUint::from(27u64), 3, 7),
+            (BigUint::from(837_799u64), 3, 16),
+            (BigUint::from(u64::MAX), 3, 50),
+            (big, 3, 64),
+            (BigUint::from(7u64), 5, 10),
+        ] {
+            let seq = trace_trajectory(&start, x, 2_000);
+            let seg = trace_segmented(&start, x, 2_000, segment);
+            assert_eq!(seg.total_steps, seq.total_steps, "{}n+1 from {}", x, start);
+            assert_eq!(seg.reached_one, seq.reached_one);
+            let seq_d: Vec<u64> = seq.steps.iter().map(|&(_, d)| d).collect();
+            assert_eq!(seg.d_values, seq_d);
+
+            // 区間境界の値は逐次追跡の該当ステップの値と一致する
+            assert_eq!(seg.boundary_values[0], start);
+            for (i, v) in seg.boundary_values.iter().enumerate().skip(1) {
+                let step = ((i as u64) * segment).min(seq.total_steps) as usize;
+                assert_eq!(*v, seq.steps[step - 1].0, "boundary {}", i);
+            }
+            assert_eq!(seg.boundary_values.last().unwrap(), &seq.steps.last().unwrap().0);
+        }
+
+        // 合成は結合的: 全体を1区間にしても同じ最終値
+        let s = BigUint::from(97u64);
+        let seg = trace_segmented(&s, 3, 1000, 5);
+        let whole = SegmentTransform::from_d_values(3, &seg.d_values);
+        assert_eq!(whole.apply(&s), BigUint::one());
+        assert_eq!(trace_segmented(&BigUint::one(), 3, 100, 4).boundary_values, vec![BigUint::one()]);
+    }
+
+    #[test]
+    fn test_glide_and_total() {
+        // 27: 全 111 ステップのうち奇数ステップ（3n+1）が 41 回
+        let (glide, total) = glide_and_total(&BigUint::from(27u64), 3, 1000);
+        assert_eq!(total, Some(41));
+        assert_eq!(glide, stopping_time(&BigUint::from(27u64), 3, 1000));
+        assert_eq!(glide_and_total(&BigUint::one(), 3, 10), (Some(0), Some(0)));
+        // max_steps 打ち切り: glide は取れても total は取れない
+        let (g, t) = glide_and_total(&BigUint::from(27u64), 3, 40);
+        assert!(g.is_some() && t.is_none());
+
+        // BigUint 開始値と u128 溢れからの続行
+        for start in [(BigUint::one() << 100u32) + BigUint::from(27u64), BigUint::from(u64::MAX)] {
+            let (g, t) = glide_and_total(&start, 3, 100_000);
+            assert_eq!(g, stopping_time(&start, 3, 100_000));
+            assert_eq!(t, stopping_time_with_gpk(&start, 3, 100_000, None, false));
+        }
+    }
+
+    /// u128 / U256 フェーズの GPK 集計（窓抽出ベース）がパックドスキャンのみの集計と一致する
+    #[test]
+    fn test_phase1_gpk_matches_packed() {
+        let mut seeds: Vec<u64> = (1u64..=999).step_by(2).collect();
+        seeds.extend([u64::MAX, u64::MAX - 2, (1u64 << 63) + 1, 0xDEAD_BEEF_CAFE_F00D | 1]);
+        for x in [3u64, 5, 9, 129] {
+            for &n in &seeds {
+                let mut fast = GpkStats::new();
+                let mut packed_only = GpkStats::new();
+                let a = stopping_time_u64_fast(n, x, 60, Some(&mut fast), true, true);
+                let b = stopping_time_u64_fast(n, x, 60, Some(&mut packed_only), false, true);
+                assert_eq!(a, b, "{}n+1, n={}", x, n);
+                assert_eq!(
+                    (fast.total_g, fast.total_p, fast.total_k, fast.total_pairs, fast.total_steps),
+                    (packed_only.total_g, packed_only.total_p, packed_only.total_k, packed_only.total_pairs, packed_only.total_steps),
+                    "{}n+1, n={}", x, n,
+                );
+                assert_eq!(fast.carry_chain_hist, packed_only.carry_chain_hist);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trace_trajectory_peak() {
+        let seeds = [
+            BigUint::from(1u64),
+            BigUint::from(27u64),
+            BigUint::from(703u64),
+            (BigUint::one() << 130u32) + BigUint::from(7u64),
+        ];
+        for start in &seeds {
+            for x in [3u64, 5] {
+                let full = trace_trajectory(start, x, 500);
+                let peak = trace_trajectory_peak(start, x, 500);
+                assert_eq!(peak.max_value, full.max_value);
+                assert_eq!(peak.total_steps, full.total_steps);
+                assert_eq!(peak.stop_reason, full.stop_reason);
+                assert_eq!(peak.gpk_stats.total_g, full.gpk_stats.total_g);
+
+                // 最初に最大値を取ったステップ
+                let first_max = std::iter::once(start)
+                    .chain(full.steps.iter().map(|(v, _)| v))
+                    .position(|v| *v == full.max_value)
+                    .unwrap() as u64;
+                assert_eq!(peak.max_value_step, first_max, "start={} x={}", start, x);
+                let expected = &full.pair_steps[first_max as usize];
+                assert_eq!(peak.peak.m4_words, expected.m4_words);
+                assert_eq!(peak.peak.m6_words, expected.m6_words);
+                assert_eq!(peak.peak.d, expected.d);
+
+                let last = full.pair_steps.last().unwrap();
+                assert_eq!(peak.final_state.m4_words, last.m4_words);
+                assert_eq!(peak.final_state.pair_count, last.pair_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stopping_time_or_growth() {
+        // 3n+1 の 27 は途中で 9232 (14 ビット) まで伸びる
+        let n = BigUint::from(27u64);
+        let st = stopping_time(&n, 3, 1000).unwrap();
+        assert_eq!(stopping_time_or_growth(&n, 3, 1000, 64, None), StoppingOutcome::Stopped(st));
+        assert!(matches!(stopping_time_or_growth(&n, 3, 1000, 4, None), StoppingOutcome::LikelyDivergent(_)));
+        // 5n+1 の 7 は発散すると予想されている
+        assert!(matches!(stopping_time_or_growth(&BigUint::from(7u64), 5, 10_000, 32, None), StoppingOutcome::LikelyDivergent(_)));
+    }
+
+    #[test]
+    fn test_stop_reason() {
+        let r = trace_trajectory(&BigUint::from(27u64), 3, 1000);
+        assert_eq!(r.stop_reason, StopReason::ReachedOne);
+        assert!(r.reached_one);
+        assert_eq!(trace_trajectory(&BigUint::one(), 3, 10).stop_reason, StopReason::ReachedOne);
+
+        let r = trace_trajectory(&BigUint::from(27u64), 3, 10);
+        assert_eq!(r.stop_reason, StopReason::MaxSteps);
+        assert_eq!(r.total_steps, 10);
+        assert_eq!(trace_trajectory_decimated(&BigUint::from(27u64), 3, 10, 3).stop_reason, StopReason::MaxSteps);
+
+        // x=257 は 1 ステップで最大 8 ビット伸びるので、ペア数上限の直前から始めて超過させる
+        let big = (BigUint::one() << (2 * MAX_PAIR_COUNT as u32 - 2)) + BigUint::one();
+        let r = trace_trajectory(&big, 257, 100);
+        assert_eq!(r.stop_reason, StopReason::ExceededPairCount);
+        assert!(!r.reached_one);
+
+        let cancel = AtomicBool::new(true);
+        let r = trace_trajectory_cancellable(&BigUint::from(27u64), 3, 1000, &cancel, |_, _, _| {});
+        assert_eq!(r.stop_reason, StopReason::Cancelled);
+        assert_eq!(r.total_steps, 0);
+        let cancel = AtomicBool::new(false);
+        let r = trace_trajectory_cancellable(&BigUint::from(27u64), 3, 1000, &cancel, |_, _, _| {});
+        assert_eq!(r.stop_reason, StopReason::ReachedOne);
+    }
+
+    #[test]
+    fn test_gpk_bucket_boundary() {
+        // 2^k - 1 は floor(log2) = k-1、2^k + 1（2^k の次の奇数）は floor(log2) = k。
+        // k が奇数のときペア数からの見積もり 2*pair_count - 1 は 2^k - 1 を1つ上に数えてしまう
+        for k in [17usize, 64] {
+            let below = (BigUint::one() << k) - 1u32;
+            let above = (BigUint::one() << k) + 1u32;
+            let r = trace_trajectory_bucketed(&below, 3, 1, k);
+            assert_eq!(r.gpk_by_bitlen_bucket.len(), 1);
+            assert_eq!(r.gpk_by_bitlen_bucket[0].0, 0, "k={}", k);
+            let r = trace_trajectory_bucketed(&above, 3, 1, k);
+            assert_eq!(r.gpk_by_bitlen_bucket.len(), 1);
+            assert_eq!(r.gpk_by_bitlen_bucket[0].0, 1, "k={}", k);
+        }
+    }
+
+    #[test]
+    fn test_gpk_buckets_sum_to_total() {
+        let start = (BigUint::one() << 300u32) + BigUint::from(27u64);
+        for bucket_bits in [0usize, 16, 64] {
+            let r = trace_trajectory_bucketed(&start, 3, 100_000, bucket_bits);
+            assert!(r.reached_one);
+            assert!(r.gpk_by_bitlen_bucket.len() > 1);
+            assert!(r.gpk_by_bitlen_bucket.windows(2).all(|w| w[0].0 < w[1].0));
+            let mut sum = GpkStats::new();
+            for (_, st) in &r.gpk_by_bitlen_bucket {
+                sum.merge(st);
+            }
+            assert_eq!(sum.total_g, r.gpk_stats.total_g);
+            assert_eq!(sum.total_p, r.gpk_stats.total_p);
+            assert_eq!(sum.total_k, r.gpk_stats.total_k);
+            assert_eq!(sum.total_pairs, r.gpk_stats.total_pairs);
+            assert_eq!(sum.total_steps, r.gpk_stats.total_steps);
+            assert_eq!(sum.carry_chain_hist, r.gpk_stats.carry_chain_hist);
+        }
+        assert!(trace_trajectory(&start, 3, 10).gpk_by_bitlen_bucket.is_empty());
+    }
+
+    #[test]
+    fn test_stopping_time_below_own_start() {
+        for n_val in (3u64..=999).step_by(2) {
+            let n = BigUint::from(n_val);
+            assert_eq!(
+                stopping_time_below(&n, 3, 10_000, &n, None),
+                stopping_time(&n, 3, 10_000),
+                "bound=n mismatch for n={}", n_val
+            );
+        }
+        // 下限が小さいほど停止時間は長くなる（単調）
+        let n = BigUint::from(27u64);
+        let own = stopping_time(&n, 3, 10_000).unwrap();
+        let below_3 = stopping_time_below(&n, 3, 10_000, &BigUint::from(3u64), None).unwrap();
+        assert!(below_3 >= own);
+        assert_eq!(stopping_time_below(&BigUint::from(5u64), 3, 10_000, &BigUint::from(7u64), None), Some(0));
+    }
+
+    #[test]
+    fn test_max_stopping_time_seed_brute_force() {
+        let seeds: Vec<u64> = vec![3, 7, 27, 31, 41, 47, 63, 97, 703, 871, 1161];
+        let mut best: Option<(u64, u64)> = None;
+        for &n in &seeds {
+            let st = stopping_time(&BigUint::from(n), 3, 10_000).unwrap();
+            if best.is_none_or(|(_, b)| st > b) {
+                best = Some((n, st));
+            }
+        }
+        assert_eq!(max_stopping_time_seed(&seeds, 3, 10_000), best);
+        assert_eq!(max_stopping_time_seed(&[], 3, 10_000), None);
+    }
+
+    #[test]
+    fn test_decimated_every1_matches_full() {
+        for n_val in [1u64, 7, 27, 97, 871] {
+            let n = BigUint::from(n_val);
+            let full = trace_trajectory(&n, 3, 10_000);
+            let dec = trace_trajectory_decimated(&n, 3, 10_000, 1);
+            assert_eq!(dec.steps, full.steps, "steps mismatch for n={}", n_val);
+            assert_eq!(dec.pair_steps.len(), full.pair_steps.len());
+            assert_eq!(dec.gpk_per_step.len(), full.gpk_per_step.len());
+            assert_eq!(dec.total_steps, full.total_steps);
+            assert_eq!(dec.max_value, full.max_value);
+            assert_eq!(dec.reached_one, full.reached_one);
+        }
+    }
+
+    #[test]
+    fn test_decimated_sparse_keeps_stats_exact() {
+        let n = BigUint::from(27u64);
+        let full = trace_trajectory(&n, 3, 10_000);
+        let dec = trace_trajectory_decimated(&n, 3, 10_000, 10);
+        assert_eq!(dec.total_steps, full.total_steps);
+        assert_eq!(dec.max_value, full.max_value);
+        assert_eq!(dec.gpk_stats.total_g, full.gpk_stats.total_g);
+        assert_eq!(dec.gpk_stats.total_steps, full.gpk_stats.total_steps);
+        assert_eq!(dec.steps.len() as u64, full.total_steps / 10);
+        // 記録されたのは 10, 20, ... ステップ目
+        for (i, s) in dec.steps.iter().enumerate() {
+            assert_eq!(s, &full.steps[(i + 1) * 10 - 1]);
+        }
+        assert_eq!(dec.pair_steps.len(), dec.steps.len() + 1);
+    }
+
+    /// run_until: stop = is_one は use_stopping_time=false の停止時間、stop = (< n) は停止時間法と一致する
+    #[test]
+    fn test_run_until_matches_stopping_time() {
+        for (n_val, x) in [(27u64, 3u64), (97, 3), (703, 3), (1u64 << 40 | 7, 3), (3, 5), (255, 3)] {
+            let n = BigUint::from(n_val);
+            let pn = PairNumber::from_biguint(&n);
+
+            let (last, steps) = run_until(pn.clone(), x, |p| p.is_one(), 10_000);
+            let expected = stopping_time_with_gpk(&n, x, 10_000, None, false);
+            assert_eq!(Some(steps), expected, "n={}, x={}", n_val, x);
+            assert!(last.is_one());
+
+            let (last, steps) = run_until(pn.clone(), x, |p| p < &pn, 10_000);
+            assert_eq!(Some(steps), stopping_time(&n, x, 10_000), "n={}, x={}", n_val, x);
+            assert!(last < pn);
+        }
+
+        // 0 ステップ（start が stop を満たす）
+        let one = PairNumber::from_biguint(&BigUint::one());
+        assert_eq!(run_until(one.clone(), 3, |p| p.is_one(), 100), (one, 0));
+
+        // max_steps で打ち切り: 7 は 5n+1 で発散する。最終状態は軌道の max_steps 番目
+        let seven = BigUint::from(7u64);
+        let (last, steps) = run_until(PairNumber::from_biguint(&seven), 5, |p| p.is_one(), 50);
+        assert_eq!(steps, 50);
+        let traj = trace_trajectory(&seven, 5, 50);
+        assert_eq!(last.to_biguint(), traj.steps.last().unwrap().0);
+    }
+
+    #[test]
+    fn test_gpk_transition_counts() {
+        let r = trace_trajectory(&BigUint::from(27u64), 3, 1000);
+        assert!(r.reached_one);
+        assert_eq!(r.gpk_per_step.len() as u64, r.total_steps);
+        let counts = r.gpk_transition_counts();
+        let sum: u64 = counts.iter().flatten().sum();
+        assert_eq!(sum, r.total_steps - 1);
+
+        // 行和 = 最後のステップ以外のクラス出現数
+        let classes: Vec<usize> = r.gpk_per_step.iter().map(|g| g.dominant_class() as usize).collect();
+        for (c, row) in counts.iter().enumerate() {
+            let from = classes[..classes.len() - 1].iter().filter(|&&x| x == c).count() as u64;
+            assert_eq!(row.iter().sum::<u64>(), from);
+        }
+
+        // 1 ステップ以下なら遷移なし
+        let one = trace_trajectory(&BigUint::from(5u64), 3, 1000);
+        assert_eq!(one.total_steps, 1);
+        assert_eq!(one.gpk_transition_counts(), [[0; 3]; 3]);
+    }
+
+    #[test]
+    fn test_orbits_to_dot() {
+        // 3 → 5 → 1、7 → 11 → 17 → 13 → 5 で合流し、5 → 1 は1回だけ出る
+        let mut out = Vec::new();
+        orbits_to_dot(&[3, 7, 8], 3, 1000, &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        let edges: Vec<&str> = dot.lines().filter(|l| l.contains("->")).map(str::trim).collect();
+        assert_eq!(
+            edges,
+            [
+                "\"3\" -> \"5\";",
+                "\"5\" -> \"1\";",
+                "\"7\" -> \"11\";",
+                "\"11\" -> \"17\";",
+                "\"17\" -> \"13\";",
+                "\"13\" -> \"5\";",
+            ]
+        );
+        assert!(dot.starts_with("digraph collatz_3n1 {"));
+        assert!(dot.trim_end().ends_with('}'));
+
+        // max_steps で各軌道を打ち切る
+        let mut out = Vec::new();
+        orbits_to_dot(&[27], 3, 2, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().matches("->").count(), 2);
+    }
+
+    /// 交換を累積して追った m4/m6 列から各状態の値を復元できる
+    #[test]
+    fn test_exchange_adjusted_streams() {
+        let r = trace_trajectory(&BigUint::from(27u64), 3, 1000);
+        assert!(r.reached_one);
+        let (m4, m6) = (r.m4_stream(), r.m6_stream());
+        assert_eq!((m4.len(), m6.len()), (r.pair_steps.len(), r.pair_steps.len()));
+
+        let mut parity = false;
+        let values = std::iter::once(r.start.clone()).chain(r.steps.iter().map(|(n, _)| n.clone()));
+        for (i, (ps, value)) in r.pair_steps.iter().zip(values).enumerate() {
+            parity ^= ps.exchanged;
+            let (a, b) = if parity { (&m6[i], &m4[i]) } else { (&m4[i], &m6[i]) };
+            let bits: String = a.chars().zip(b.chars()).flat_map(|(hi, lo)| [hi, lo]).collect();
+            assert_eq!(BigUint::parse_bytes(bits.as_bytes(), 2).unwrap(), value, "step {}", i);
+        }
+        // 交換が起きるので、生の m4 列とは途中から食い違う
+        let raw_m4: Vec<String> = r.pair_steps.iter().map(|ps| words_to_bits_msb(&ps.m4_words, ps.pair_count)).collect();
+        assert_eq!(m4[0], raw_m4[0]);
+        assert_ne!(m4, raw_m4);
+
+        // 27 → 41 → 31 → 47 → 71 → 107 → 161（d = 1, 2, 1, 1, 1, 1）を手で分けた列。
+        // 例: 41 = 10 10 01 は交換 1 回なので m4_stream に m6 側の 001 が来る
+        let r = trace_trajectory(&BigUint::from(27u64), 3, 6);
+        assert_eq!(r.m4_stream(), ["011", "001", "111", "111", "1011", "0111", "0001"]);
+        assert_eq!(r.m6_stream(), ["101", "110", "011", "011", "0001", "1001", "1100"]);
+    }
+
+    /// replay: 27 の軌道の d 列から値の列を復元でき、d を書き換えると最初の食い違いで止まる
+    #[test]
+    fn test_replay() {
+        let start = BigUint::from(27u64);
+        let traj = trace_trajectory(&start, 3, 1000);
+        let ds: Vec<u64> = traj.steps.iter().map(|&(_, d)| d).collect();
+        let values: Vec<BigUint> = traj.steps.iter().map(|(n, _)| n.clone()).collect();
+        assert_eq!(replay(&start, 3, &ds).unwrap(), values);
+        assert_eq!(ds, d_sequence(&start, 3, 1000).0);
+        assert!(replay(&start, 3, &[]).unwrap().is_empty());
+
+        let mut bad = ds.clone();
+        bad[5] += 1;
+        let err = replay(&start, 3, &bad).unwrap_err();
+        assert_eq!(err, ReplayError { step: 5, expected_d: ds[5] + 1, actual_d: ds[5] });
+
+        // 偶数の開始値は xn+1 が奇数になる
+        assert_eq!(replay(&BigUint::from(4u64), 3, &[1]).unwrap_err().actual_d, 0);
+    }
+
+    #[test]
+    fn test_d_autocorrelation() {
+        let mut r = trace_trajectory(&BigUint::from(27u64), 3, 1000);
+        let ac = r.d_autocorrelation(10);
+        assert_eq!(ac.len(), 11);
+        assert!(ac.iter().all(|v| v.is_finite() && v.abs() <= 1.0 + 1e-9));
+        assert!((ac[0] - 1.0).abs() < 1e-12);
+
+        // d が一定なら完全相関、ラグが長さ以上なら 0
+        r.steps = vec![(BigUint::one(), 2); 50];
+        let ac = r.d_autocorrelation(60);
+        assert!(ac[..50].iter().all(|&v| v == 1.0));
+        assert!(ac[50..].iter().all(|&v| v == 0.0));
+
+        // 1, 2 の交互列はラグ 1 で -(n-1)/n
+        r.steps = (0..100).map(|i| (BigUint::one(), 1 + i % 2)).collect();
+        let ac = r.d_autocorrelation(2);
+        assert!((ac[1] + 0.99).abs() < 1e-12);
+        assert!((ac[2] - 0.98).abs() < 1e-12);
+
+        r.steps.clear();
+        assert_eq!(r.d_autocorrelation(3), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_orbit_summary_merge() {
+        // 7 → 11 → 17 → 13 → 5 → 1（d = 1, 1, 2, 3, 4）、3 → 5 → 1（d = 1, 4）
+        let a = trace_trajectory(&BigUint::from(7u64), 3, 1000);
+        let b = trace_trajectory(&BigUint::from(3u64), 3, 1000);
+        let sa = OrbitSummary::from(&a);
+        let sb = OrbitSummary::from(&b);
+        assert_eq!((sa.total_steps, sa.sum_d, sa.max_value_bits, sa.reached_one), (5, 11, 5, 1));
+        assert_eq!((sb.total_steps, sb.sum_d, sb.max_value_bits, sb.reached_one), (2, 5, 3, 1));
+
+        let mut merged = sa.clone();
+        merged.merge(&sb);
+        assert_eq!(merged.orbits, 2);
+        assert_eq!(merged.total_steps, 7);
+        assert_eq!(merged.sum_d, 16);
+        assert_eq!(merged.max_value_bits, 5);
+        assert_eq!(merged.reached_one, 2);
+        assert_eq!(merged.gpk_stats.total_steps, a.gpk_stats.total_steps + b.gpk_stats.total_steps);
+        assert_eq!(merged.gpk_stats.total_pairs, a.gpk_stats.total_pairs + b.gpk_stats.total_pairs);
+
+        let capped = OrbitSummary::from(&trace_trajectory(&BigUint::from(27u64), 3, 10));
+        let report = aggregate_summaries(&[sa, sb, capped]);
+        assert_eq!(report.total.orbits, 3);
+        assert_eq!(report.total.total_steps, 17);
+        assert!((report.mean_steps - 17.0 / 3.0).abs() < 1e-12);
+        assert!((report.reached_one_fraction - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(report.steps_distribution, vec![(2, 1), (5, 1), (10, 1)]);
+        assert_eq!(report.total.max_value_bits, report.max_bits_distribution.last().unwrap().0);
+
+        let empty = aggregate_summaries(&[]);
+        assert_eq!((empty.total.orbits, empty.mean_steps, empty.mean_d), (0, 0.0, 0.0));
+        assert!(empty.steps_distribution.is_empty());
     }
 }
@@ -1,11 +1,13 @@
 use num_bigint::BigUint;
-use num_traits::One;
+use num_traits::{One, ToPrimitive};
 use rayon::prelude::*;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
+use crate::packed;
+use crate::pair_number::PairNumber;
 use crate::scan::GpkStats;
-use crate::trajectory;
+use crate::trajectory::{self, merge_d_hist, Cancelled, SeedStats, StopReason, StoppingOutcome};
 
 /// 範囲検証の結果
 #[derive(Debug, Clone)]
@@ -22,403 +24,3159 @@ pub struct VerifyResult {
     pub failures: Vec<BigUint>,
     /// GPK 統計情報
     pub gpk_stats: GpkStats,
+    /// 既知サイクルに捕捉された数（収束扱い、failures には含めない）
+    pub cycle_hits: u64,
+    /// 1 に到達して停止した数（停止判定の前に 1 そのものに落ちたもの。シード 1 を含む）。
+    /// verify_range_glide_and_total では集計しない（0）
+    pub reached_one_count: u64,
+    /// 1 以外の値でシード（または下限）を下回って停止した数。verify_range_glide_and_total では集計しない（0）
+    pub dropped_below_count: u64,
+    /// 全シード・全ステップの d 値のヒストグラム (index=d, value=出現回数)
+    pub d_hist: Vec<u64>,
+    /// 軌道上に現れた値（シード自身を含む）の最大ビット長。中間値のメモリの上限の目安で、
+    /// MAX_PAIR_COUNT や成長上限を決める材料にする。verify_range_glide_and_total では集計しない（0）
+    pub max_intermediate_bits: u64,
+    /// 成長上限を超えた「発散の疑い」のある数（failures には含めない）
+    pub likely_divergent: Vec<BigUint>,
+    /// 全停止時間（1 への到達）の最大値とその数。verify_range_glide_and_total 以外では None
+    pub max_total_stopping_time: Option<(u64, BigUint)>,
+    /// 実際に使われた実行経路
+    pub strategy: Strategy,
 }
 
+/// 範囲検証の実行経路
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// u64 に収まる範囲をチャンク並列で処理した
+    ParallelU64,
+    /// BigUint の範囲をチャンク並列（BigUintRangeChunker）で処理した
+    ParallelBigUint,
+    /// 1 スレッドで順に処理した
+    SingleThread,
+}
+
+impl VerifyResult {
+    /// 空範囲（奇数が1つもない）の結果
+    fn empty() -> Self {
+        VerifyResult {
+            total_checked: 0,
+            all_converged: true,
+            max_stopping_time: 0,
+            max_stopping_time_number: BigUint::ZERO,
+            failures: Vec::new(),
+            gpk_stats: GpkStats::new(),
+            cycle_hits: 0,
+            reached_one_count: 0,
+            dropped_below_count: 0,
+            d_hist: Vec::new(),
+            max_intermediate_bits: 0,
+            likely_divergent: Vec::new(),
+            max_total_stopping_time: None,
+            strategy: Strategy::SingleThread,
+        }
+    }
+
+    /// 別の範囲の結果を合算する（verify_range_batches の部分結果をまとめる用）。
+    /// 個数・統計は加算、failures / likely_divergent は連結してソート、最大停止時間は
+    /// 同値なら小さい数を優先する。空の結果（total_checked = 0）は単位元として扱う。
+    /// strategy は self のものを残す。
+    pub fn merge(&mut self, other: &VerifyResult) {
+        if other.total_checked == 0 {
+            return;
+        }
+        let better = self.total_checked == 0
+            || other.max_stopping_time > self.max_stopping_time
+            || (other.max_stopping_time == self.max_stopping_time
+                && other.max_stopping_time_number < self.max_stopping_time_number);
+        if better {
+            self.max_stopping_time = other.max_stopping_time;
+            self.max_stopping_time_number = other.max_stopping_time_number.clone();
+        }
+        self.total_checked += other.total_checked;
+        self.all_converged &= other.all_converged;
+        self.failures.extend(other.failures.iter().cloned());
+        self.failures.sort();
+        self.gpk_stats.merge(&other.gpk_stats);
+        self.cycle_hits += other.cycle_hits;
+        self.reached_one_count += other.reached_one_count;
+        self.dropped_below_count += other.dropped_below_count;
+        merge_d_hist(&mut self.d_hist, &other.d_hist);
+        self.max_intermediate_bits = self.max_intermediate_bits.max(other.max_intermediate_bits);
+        self.likely_divergent.extend(other.likely_divergent.iter().cloned());
+        self.likely_divergent.sort();
+        if let Some((t, n)) = &other.max_total_stopping_time {
+            let better = self.max_total_stopping_time.as_ref().is_none_or(|(mt, mn)| t > mt || (t == mt && n < mn));
+            if better {
+                self.max_total_stopping_time = Some((*t, n.clone()));
+            }
+        }
+    }
+
+    /// d が奇数（m4/m6 の交換が起きた）ステップ数。d_hist から数える
+    pub fn odd_d_steps(&self) -> u64 {
+        self.d_hist.iter().skip(1).step_by(2).sum()
+    }
+
+    /// d が偶数のステップ数。odd_d_steps との和は d_hist の総和
+    /// （GPK を収集した経路では gpk_stats.total_steps）に等しい
+    pub fn even_d_steps(&self) -> u64 {
+        self.d_hist.iter().step_by(2).sum()
+    }
+
+    /// 全ステップを通じて最長のキャリー伝播距離（gpk_stats.max_carry_chain）。GPK を集計しない経路では 0
+    pub fn longest_chain_len(&self) -> u32 {
+        self.gpk_stats.max_carry_chain
+    }
+
+    /// longest_chain_len のステップを含む軌道のシード（同じ長さなら小さいシード）。
+    /// GPK を集計しない経路では None
+    pub fn longest_chain_seed(&self) -> Option<&BigUint> {
+        self.gpk_stats.max_carry_chain_seed.as_ref()
+    }
+
+    /// 結果の再現性確認用ダイジェスト（FNV-1a 64 ビット）。
+    /// total_checked, max_stopping_time, max_stopping_time_number, failures（ソートして）,
+    /// GpkStats の各フィールドを固定のバイト列（u64 はリトルエンディアン、BigUint は長さ付き）
+    /// にして畳み込む。同じ範囲を別マシン・別スレッド数で検証した結果を 1 つの数で突き合わせる。
+    /// 実行経路（strategy）や軌道の最大ビット長など、検証結果の同一性に関係しない値は含めない。
+    pub fn result_digest(&self) -> u64 {
+        let mut h = Fnv1a::new();
+        h.write_u64(self.total_checked);
+        h.write_u64(self.max_stopping_time);
+        h.write_biguint(&self.max_stopping_time_number);
+        let mut failures: Vec<&BigUint> = self.failures.iter().collect();
+        failures.sort();
+        h.write_u64(failures.len() as u64);
+        for n in failures {
+            h.write_biguint(n);
+        }
+        let g = &self.gpk_stats;
+        for v in [g.total_g, g.total_p, g.total_k, g.total_pairs, g.total_steps] {
+            h.write_u64(v);
+        }
+        for &v in &g.carry_chain_hist {
+            h.write_u64(v);
+        }
+        h.0
+    }
+}
+
+/// 検証経路に共通のチャンク内の集計。ワーカーはシードごとに record（または run_seed の後に
+/// stopped / failures / likely_divergent / cycle_hits）で加え、チャンクの終わりに共有の集計へ
+/// merge する。全チャンクを合わせたものを into_result で VerifyResult にする。
+#[derive(Debug, Clone, Default)]
+struct ChunkAccumulator {
+    /// 処理したシードの数
+    checked: u64,
+    /// (最大停止時間, その数)。同値なら小さい数
+    best: Option<(u64, BigUint)>,
+    /// (最大全停止時間, その数)。同値なら小さい数。1 まで追跡する経路（total_stopped）だけが使う
+    best_total: Option<(u64, BigUint)>,
+    failures: Vec<BigUint>,
+    likely_divergent: Vec<BigUint>,
+    cycle_hits: u64,
+    /// GPK 統計・d ヒストグラム・最大ビット長・停止の種類
+    stats: SeedStats,
+}
+
+impl ChunkAccumulator {
+    fn new(collect_gpk: bool) -> Self {
+        ChunkAccumulator { stats: SeedStats::new(collect_gpk), ..ChunkAccumulator::default() }
+    }
+
+    /// 停止したシードが1つもないときの max_stopping_time_number を n にする
+    /// （停止時間 0 の記録として入れるので、n は範囲の最初の奇数にすること）
+    fn set_fallback_number(&mut self, n: BigUint) {
+        self.stopped(0, || n);
+    }
+
+    /// シード 1 つ分の計算 f を stats の上で走らせ、f の間に更新された最長キャリー伝播の記録を
+    /// seed に帰属させて処理数を 1 増やす。結果の分類は呼び出し側が行う
+    fn run_seed<T>(&mut self, seed: impl FnOnce() -> BigUint, f: impl FnOnce(&mut SeedStats) -> T) -> T {
+        let chain_before = self.stats.max_carry_chain();
+        let outcome = f(&mut self.stats);
+        self.stats.attribute_chain_record(chain_before, seed);
+        self.checked += 1;
+        outcome
+    }
+
+    /// 停止時間 st で止まったシードを記録する（最大停止時間の更新。同値なら小さい数）
+    fn stopped(&mut self, st: u64, n: impl FnOnce() -> BigUint) {
+        update_record(&mut self.best, st, n);
+    }
+
+    /// 全停止時間 t で 1 に着いたシードを記録する（最大全停止時間の更新。同値なら小さい数）
+    fn total_stopped(&mut self, t: u64, n: impl FnOnce() -> BigUint) {
+        update_record(&mut self.best_total, t, n);
+    }
+
+    /// 停止時間を返す f でシード n を検証し、停止時間（None なら failures）を記録する
+    fn record(&mut self, n: impl Fn() -> BigUint, f: impl FnOnce(&mut SeedStats) -> Option<u64>) {
+        match self.run_seed(&n, f) {
+            Some(st) => self.stopped(st, n),
+            None => self.failures.push(n()),
+        }
+    }
+
+    /// record の中断対応版。f はシード専用の scratch の上で走らせ、最後まで計算できたときだけ
+    /// 集計に加える。中断されたシードは統計にも処理数にも入らない（scratch は毎回空に戻す）
+    fn record_cancellable(
+        &mut self,
+        scratch: &mut SeedStats,
+        n: impl Fn() -> BigUint,
+        f: impl FnOnce(&mut SeedStats) -> Result<Option<u64>, Cancelled>,
+    ) -> Result<(), Cancelled> {
+        let outcome = f(scratch);
+        if let Ok(st) = outcome {
+            self.record(n, |stats| {
+                stats.merge(scratch);
+                st
+            });
+        }
+        scratch.clear();
+        outcome.map(|_| ())
+    }
+
+    /// 別のチャンクの集計を加える
+    fn merge(&mut self, other: ChunkAccumulator) {
+        self.checked += other.checked;
+        if let Some((st, n)) = other.best {
+            self.stopped(st, || n);
+        }
+        if let Some((t, n)) = other.best_total {
+            self.total_stopped(t, || n);
+        }
+        self.failures.extend(other.failures);
+        self.likely_divergent.extend(other.likely_divergent);
+        self.cycle_hits += other.cycle_hits;
+        self.stats.merge(&other.stats);
+    }
+
+    /// 全体の集計を VerifyResult にする（failures / likely_divergent は昇順に並べる）
+    fn into_result(mut self, strategy: Strategy) -> VerifyResult {
+        self.failures.sort();
+        self.likely_divergent.sort();
+        let (max_stopping_time, max_stopping_time_number) = self.best.unwrap_or((0, BigUint::ZERO));
+        VerifyResult {
+            total_checked: self.checked,
+            all_converged: self.failures.is_empty() && self.likely_divergent.is_empty(),
+            max_stopping_time,
+            max_stopping_time_number,
+            failures: self.failures,
+            gpk_stats: self.stats.take_gpk(),
+            cycle_hits: self.cycle_hits,
+            reached_one_count: self.stats.reached_one,
+            dropped_below_count: self.stats.dropped_below,
+            d_hist: self.stats.d_hist,
+            max_intermediate_bits: self.stats.peak_bits,
+            likely_divergent: self.likely_divergent,
+            max_total_stopping_time: self.best_total,
+            strategy,
+        }
+    }
+}
+
+/// (値, 数) の最大記録を v, n で更新する（同値なら小さい数。n は必要なときだけ作る）
+fn update_record(record: &mut Option<(u64, BigUint)>, v: u64, n: impl FnOnce() -> BigUint) {
+    match record {
+        Some((best, best_n)) if v <= *best => {
+            if v == *best {
+                let n = n();
+                if n < *best_n {
+                    *best_n = n;
+                }
+            }
+        }
+        record => *record = Some((v, n())),
+    }
+}
+
+/// FNV-1a 64 ビット（result_digest 用。std の Hasher と違い、実装やプラットフォームに依らず固定）
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write_u64(&mut self, v: u64) {
+        for b in v.to_le_bytes() {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// 64 ビット桁数 + 各桁（下位から）
+    fn write_biguint(&mut self, v: &BigUint) {
+        let digits = v.to_u64_digits();
+        self.write_u64(digits.len() as u64);
+        for d in digits {
+            self.write_u64(d);
+        }
+    }
+}
+
+/// BigUint 範囲 [start, end] の奇数をチャンクに分割するイテレータ。
+/// (チャンク先頭の奇数, チャンク内の奇数の個数) を返す。
+/// チャンク先頭は事前計算した chunk_stride (= 2 * chunk_size) の逐次加算で求め、
+/// チャンクごとの BigUint 乗算を避ける。除算は最終チャンクの個数計算で1回のみ。
+pub struct BigUintRangeChunker {
+    next_base: BigUint,
+    end: BigUint,
+    chunk_size: u64,
+    chunk_stride: BigUint,
+    /// チャンク末尾までの距離 (= chunk_stride - 2)
+    last_offset: BigUint,
+    done: bool,
+}
+
+impl BigUintRangeChunker {
+    /// start は偶数なら次の奇数に切り上げる。chunk_size は 1 以上であること。
+    pub fn new(start: &BigUint, end: &BigUint, chunk_size: u64) -> Self {
+        assert!(chunk_size >= 1, "chunk_size must be >= 1");
+        let mut next_base = start.clone();
+        if !next_base.bit(0) {
+            next_base += 1u32;
+        }
+        let chunk_stride = BigUint::from(chunk_size) * 2u32;
+        let last_offset = &chunk_stride - 2u32;
+        let done = next_base > *end;
+        BigUintRangeChunker {
+            next_base,
+            end: end.clone(),
+            chunk_size,
+            chunk_stride,
+            last_offset,
+            done,
+        }
+    }
+}
+
+impl Iterator for BigUintRangeChunker {
+    type Item = (BigUint, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let base = self.next_base.clone();
+        let chunk_last = &base + &self.last_offset;
+        let count = if chunk_last <= self.end {
+            self.chunk_size
+        } else {
+            // 最終チャンク: 残りの奇数の個数
+            let rest: BigUint = (&self.end - &base) >> 1u32;
+            rest.to_u64_digits().first().copied().unwrap_or(0) + 1
+        };
+        self.next_base += &self.chunk_stride;
+        if self.next_base > self.end {
+            self.done = true;
+        }
+        Some((base, count))
+    }
+}
+
+/// [start, end] の奇数を batch 個ずつの部分範囲に分け、部分範囲ごとの VerifyResult を順に返す
+/// イテレータ。各部分範囲は next() が呼ばれたときに verify_range_parallel で検証するので、
+/// 呼び出し側は1件ずつ処理・保存して途中でやめられる。部分範囲の先頭は start（偶数なら次の奇数）
+/// から 2·batch 刻みなので、k 件処理した後は start + 2·batch·k から同じ batch で呼び直せば
+/// 続きの部分範囲が同じ区切りで得られる。各結果は独立していて、VerifyResult::merge で
+/// 合算すると全範囲の結果になる。batch は 1 以上であること。
+pub fn verify_range_batches(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    batch: u64,
+) -> impl Iterator<Item = VerifyResult> {
+    BigUintRangeChunker::new(start, end, batch).map(move |(base, count)| {
+        let last = &base + 2u64 * (count - 1);
+        verify_range_parallel(&base, &last, x, max_steps, |_, _| {})
+    })
+}
+
+/// [start, end] の奇数の個数（進捗の正確な総数）。2^64 個を超える範囲でも飽和させずに数える
+pub fn odd_count(start: &BigUint, end: &BigUint) -> BigUint {
+    let first = if start.bit(0) { start.clone() } else { start + 1u32 };
+    if first > *end {
+        return BigUint::ZERO;
+    }
+    ((end - &first) >> 1u32) + 1u32
+}
+
+/// 進捗の割合 done / total（0.0〜1.0）。total は odd_count の値。u64 に飽和させた総数で割ると
+/// 2^64 個を超える範囲で割合の桁がずれるので、total を BigUint から f64 にしてから割る。
+/// total が 0 なら 0
+pub fn progress_fraction(done: u64, total: &BigUint) -> f64 {
+    match total.to_f64() {
+        Some(total) if total > 0.0 => (done as f64 / total).min(1.0),
+        _ => 0.0,
+    }
+}
+
+/// 奇数 first から end までの奇数の個数（(u64, u64) の進捗コールバックの総数）。u64 に収まらなければ
+/// u64::MAX に飽和させる。下位リムだけを取ると 2^64 個を超える範囲で総数が小さく化け、
+/// 完了数が総数を超えてしまうため。正確な割合は odd_count と progress_fraction で求める。
+fn odd_count_saturating(first: &BigUint, end: &BigUint) -> u64 {
+    u64::try_from(odd_count(first, end)).unwrap_or(u64::MAX)
+}
+
+/// 並列版の進捗報告間隔の既定値（各チャンクがこの個数の奇数ごとに progress_callback を呼ぶ）
+pub const REPORT_EVERY_PARALLEL: u64 = 100;
+
+/// シングルスレッド版の進捗報告間隔の既定値（この個数の奇数ごとに progress_callback を呼ぶ）
+pub const REPORT_EVERY_SEQUENTIAL: u64 = 1000;
+
 /// [start, end] の全奇数を停止時間法で検証する（シングルスレッド版）。
-/// progress_callback: (完了数, 総数) を定期的に呼ぶ。
+/// progress_callback: (完了数, 総数) を REPORT_EVERY_SEQUENTIAL 個ごとと最後に呼ぶ。
 pub fn verify_range(
     start: &BigUint,
     end: &BigUint,
     x: u64,
     max_steps: u64,
     progress_callback: impl Fn(u64, u64),
+) -> VerifyResult {
+    verify_range_reporting(start, end, x, max_steps, REPORT_EVERY_SEQUENTIAL, progress_callback)
+}
+
+/// verify_range の本体。progress_callback は report_every 個ごとと最後に呼ぶ。
+fn verify_range_reporting(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    report_every: u64,
+    progress_callback: impl Fn(u64, u64),
+) -> VerifyResult {
+    let report_every = report_every.max(1);
+    let two = BigUint::from(2u64);
+    let one = BigUint::one();
+
+    // start を奇数に調整
+    let mut n = start.clone();
+    if &n % &two == BigUint::ZERO {
+        n += &one;
+    }
+    if n > *end {
+        return VerifyResult::empty();
+    }
+
+    let total_estimate = odd_count_saturating(&n, end);
+
+    let mut acc = ChunkAccumulator::new(true);
+    acc.set_fallback_number(n.clone());
+
+    while n <= *end {
+        acc.record(|| n.clone(), |stats| trajectory::stopping_time_with_gpk_d(&n, x, max_steps, true, stats));
+
+        if acc.checked.is_multiple_of(report_every) {
+            progress_callback(acc.checked, total_estimate);
+        }
+
+        n += &two;
+    }
+
+    progress_callback(acc.checked, total_estimate);
+    acc.into_result(Strategy::SingleThread)
+}
+
+/// [start, end] の全奇数を停止時間法で検証する（既知サイクル対応版）。
+/// 軌道が known_cycles のいずれかのサイクル（奇数→奇数の周期列）に入って
+/// n 未満に戻れなくなった数は、失敗ではなく cycle_hits として数える。5n+1 の {13, 33, 83}, {17, 43, 27} など、
+/// 発散ではなくサイクル捕捉による非停止を区別するために使う。
+pub fn verify_range_with_cycles(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    known_cycles: &[Vec<u64>],
+    progress_callback: impl Fn(u64, u64),
 ) -> VerifyResult {
     let two = BigUint::from(2u64);
     let one = BigUint::one();
 
-    // start を奇数に調整
-    let mut n = start.clone();
-    if &n % &two == BigUint::ZERO {
-        n += &one;
+    let mut n = start.clone();
+    if &n % &two == BigUint::ZERO {
+        n += &one;
+    }
+    if n > *end {
+        return VerifyResult::empty();
+    }
+
+    let total_estimate = odd_count_saturating(&n, end);
+
+    let mut acc = ChunkAccumulator::new(true);
+    acc.set_fallback_number(n.clone());
+
+    while n <= *end {
+        let outcome = acc.run_seed(|| n.clone(), |stats| {
+            trajectory::stopping_time_or_cycle_d(&n, x, max_steps, known_cycles, None, stats)
+        });
+        match outcome {
+            StoppingOutcome::Stopped(st) => acc.stopped(st, || n.clone()),
+            StoppingOutcome::EnteredCycle(_) => acc.cycle_hits += 1,
+            StoppingOutcome::Unresolved | StoppingOutcome::LikelyDivergent(_) => acc.failures.push(n.clone()),
+        }
+
+        if acc.checked.is_multiple_of(REPORT_EVERY_SEQUENTIAL) {
+            progress_callback(acc.checked, total_estimate);
+        }
+
+        n += &two;
+    }
+
+    progress_callback(acc.checked, total_estimate);
+    acc.into_result(Strategy::SingleThread)
+}
+
+/// 大域下限による帰納的検証（並列版）。
+/// lower_bound が Some(B) なら、B 未満の奇数は全て収束済みという前提で、
+/// 軌道が B 未満に到達した時点で収束とみなす。None なら各シード自身の値
+/// （通常の停止時間法）を下限とする。max_stopping_time は下限到達までのステップ数。
+/// チャンク分割と並列化は drive_chunks で行い、BigUint 範囲でも並列に処理する。
+/// u64 に収まるシードは u128 / U256 フェーズから始める高速版（stopping_time_u64_below_d）で処理する。
+pub fn verify_range_inductive(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    lower_bound: Option<u64>,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    let bound = lower_bound.map(BigUint::from);
+    let cancel = AtomicBool::new(false);
+    let run = ChunkRun { start, end, collect_gpk: true, report_every: REPORT_EVERY_PARALLEL, cancel: &cancel };
+
+    let acc = drive_chunks(&run, &progress_callback, |n, local, _| {
+        // u64 に収まるシードは u128 / U256 フェーズのある高速版で処理する
+        local.record(|| n.clone(), |stats| match (u64::try_from(n).ok(), &bound) {
+            (Some(n64), _) => trajectory::stopping_time_u64_below_d(n64, lower_bound.unwrap_or(n64), x, max_steps, true, true, stats),
+            (None, Some(b)) => trajectory::stopping_time_below_d(n, x, max_steps, b, stats),
+            (None, None) => trajectory::stopping_time_with_gpk_d(n, x, max_steps, true, stats),
+        });
+        Ok(())
+    }, |_, _| {});
+
+    acc.into_result(Strategy::ParallelBigUint)
+}
+
+/// 奇数→奇数写像の1ステップ。verify_range_with で任意の実験的写像を検証するための拡張点。
+/// 戻り値は (次の奇数, その間に割った 2 の指数 d)。
+pub trait OddStep {
+    fn step(&self, n: &PairNumber) -> (PairNumber, u64);
+}
+
+/// 組み込みの xn+1 写像（x-1 は 2 の冪）。パックド版ステップで計算する。
+#[derive(Debug, Clone, Copy)]
+pub struct XnPlusOne(pub u64);
+
+impl OddStep for XnPlusOne {
+    fn step(&self, n: &PairNumber) -> (PairNumber, u64) {
+        let result = packed::step_for_x(n, self.0, false);
+        let next = PairNumber::from_packed(result.new_m4, result.new_m6, result.new_pair_count);
+        (next, result.d)
+    }
+}
+
+/// 任意の OddStep で [start, end] の全奇数を停止時間法で検証する（並列版）。
+/// 各 n について、軌道が 1 に到達するか n 未満になるまでのステップ数を停止時間とする。
+/// max_steps 以内に停止しない数、またはビット長上限を超えた数は failures に入る。
+/// GPK はステッパーから得られないため gpk_stats は空のまま。
+pub fn verify_range_with<S: OddStep + Sync + ?Sized>(
+    stepper: &S,
+    start: &BigUint,
+    end: &BigUint,
+    max_steps: u64,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    let stopping_time = |n: &BigUint, stats: &mut SeedStats| -> Option<u64> {
+        let start_pn = PairNumber::from_biguint(n);
+        let mut pn = start_pn.clone();
+        stats.record_bits(n.bits());
+        for steps in 1..=max_steps {
+            let (next, d) = stepper.step(&pn);
+            stats.record_d(d);
+            stats.record_bits(next.bit_len() as u64);
+            if next.is_one() {
+                stats.reached_one += 1;
+                return Some(steps);
+            }
+            if next < start_pn {
+                stats.dropped_below += 1;
+                return Some(steps);
+            }
+            if next.pair_count() > trajectory::MAX_PAIR_COUNT {
+                return None;
+            }
+            pn = next;
+        }
+        None
+    };
+
+    let cancel = AtomicBool::new(false);
+    let run = ChunkRun { start, end, collect_gpk: false, report_every: REPORT_EVERY_PARALLEL, cancel: &cancel };
+    let acc = drive_chunks(&run, &progress_callback, |n, local, _| {
+        local.record(|| n.clone(), |stats| stopping_time(n, stats));
+        Ok(())
+    }, |_, _| {});
+
+    acc.into_result(Strategy::ParallelBigUint)
+}
+
+/// 成長上限付き検証（並列版）。
+/// growth_limit_bits が Some(g) なら、軌道のビット長が開始値より g ビットを超えて伸びた数を
+/// failures ではなく likely_divergent に分けて返す（5n+1 等の探索用の発散ヒューリスティクス）。
+/// None なら通常の停止時間法と同じで likely_divergent は空。
+pub fn verify_range_with_growth_limit(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    growth_limit_bits: Option<u64>,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    let cancel = AtomicBool::new(false);
+    let run = ChunkRun { start, end, collect_gpk: true, report_every: REPORT_EVERY_PARALLEL, cancel: &cancel };
+    let acc = drive_chunks(&run, &progress_callback, |n, local, _| {
+        let outcome = local.run_seed(|| n.clone(), |stats| {
+            trajectory::stopping_time_or_cycle_d(n, x, max_steps, &[], growth_limit_bits, stats)
+        });
+        match outcome {
+            StoppingOutcome::Stopped(st) => local.stopped(st, || n.clone()),
+            StoppingOutcome::LikelyDivergent(_) => local.likely_divergent.push(n.clone()),
+            StoppingOutcome::Unresolved | StoppingOutcome::EnteredCycle(_) => local.failures.push(n.clone()),
+        }
+        Ok(())
+    }, |_, _| {});
+
+    acc.into_result(Strategy::ParallelBigUint)
+}
+
+/// 停止時間と全停止時間を1パスで求める検証（並列版）。
+/// 各数について n 未満への到達（glide）の後も 1 まで追跡し、
+/// max_stopping_time / max_stopping_time_number に glide の記録を、
+/// max_total_stopping_time に全停止時間の記録を入れる（同値なら小さい数）。
+/// max_steps 以内に 1 に届かなかった数は failures に入る。GPK は収集しない。
+pub fn verify_range_glide_and_total(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    if odd_count(start, end) == BigUint::ZERO {
+        return VerifyResult::empty();
+    }
+
+    let cancel = AtomicBool::new(false);
+    let run = ChunkRun { start, end, collect_gpk: false, report_every: REPORT_EVERY_PARALLEL, cancel: &cancel };
+    let acc = drive_chunks(&run, &progress_callback, |n, local, _| {
+        let (glide, total) = local.run_seed(|| n.clone(), |_| trajectory::glide_and_total(n, x, max_steps));
+        if let Some(g) = glide {
+            local.stopped(g, || n.clone());
+        }
+        match total {
+            Some(t) => local.total_stopped(t, || n.clone()),
+            None => local.failures.push(n.clone()),
+        }
+        Ok(())
+    }, |_, _| {});
+
+    acc.into_result(Strategy::ParallelBigUint)
+}
+
+/// verify_range_small が専用パスで扱う範囲の上限（end < 2^40）。
+/// これ以上を含む範囲は verify_range_parallel に委ねる。
+pub const SMALL_RANGE_LIMIT: u64 = 1 << 40;
+
+/// verify_range_small で同時に進めるシード数（スカラーのレーン数）
+const SMALL_LANES: usize = 8;
+
+/// 小さいシード専用の範囲検証（end < SMALL_RANGE_LIMIT）。
+/// 各チャンク内で SMALL_LANES (= 8) 個のシードをスカラーの u64 レーンに並べて1ステップずつ
+/// 同時に進め、停止したレーンには次のシードを詰め直す。SIMD 命令は使わず、依存のない
+/// 乗算・末尾ゼロ計数を並べて命令レベル並列性（ILP）で重ねる。GPK は集計しない（gpk_stats は空）。
+/// 値が u64 演算の範囲を超えたレーンだけ u128 → パックドの一般経路で続きを計算する。
+/// max_stopping_time が同じなら小さい方の数を max_stopping_time_number にする。
+/// end >= SMALL_RANGE_LIMIT なら verify_range_parallel の結果をそのまま返す。
+pub fn verify_range_small(start: &BigUint, end: &BigUint, x: u64, max_steps: u64) -> VerifyResult {
+    if *end >= BigUint::from(SMALL_RANGE_LIMIT) {
+        return verify_range_parallel(start, end, x, max_steps, |_, _| {});
+    }
+    // u64 に収まらない start は end より大きいので空範囲（下位リムだけを取ると範囲が化ける）
+    let end = u64::try_from(end).unwrap_or(0);
+    let start = u64::try_from(start).unwrap_or(u64::MAX) | 1;
+    if start > end {
+        return VerifyResult::empty();
+    }
+
+    let total_odd = (end - start) / 2 + 1;
+    let chunk_size: u64 = 10000;
+    let num_chunks = total_odd.div_ceil(chunk_size);
+
+    let mut global = ChunkAccumulator::new(false);
+    global.set_fallback_number(BigUint::from(start));
+    let global = Mutex::new(global);
+
+    (0..num_chunks).into_par_iter().for_each(|chunk_idx| {
+        let chunk_start = start + chunk_idx * chunk_size * 2;
+        let chunk_end = std::cmp::min(chunk_start + (chunk_size - 1) * 2, end);
+        let mut local = ChunkAccumulator::new(false);
+        verify_chunk_small(chunk_start, chunk_end, x, max_steps, &mut local);
+        global.lock().unwrap().merge(local);
+    });
+
+    global.into_inner().unwrap().into_result(Strategy::ParallelU64)
+}
+
+/// verify_range_small の1チャンク（奇数 [chunk_start, chunk_end]）の結果を acc に加える。
+/// acc.stats.peak_bits は u64 レーン上の最大値をまとめて反映し、一般経路に渡したレーンは
+/// そちらで更新する。
+fn verify_chunk_small(chunk_start: u64, chunk_end: u64, x: u64, max_steps: u64, acc: &mut ChunkAccumulator) {
+    let overflow_limit = (u64::MAX - 1) / x;
+    let mut seed = [0u64; SMALL_LANES];
+    let mut cur = [0u64; SMALL_LANES];
+    let mut steps = [0u64; SMALL_LANES];
+    let mut active = [false; SMALL_LANES];
+    let mut next_seed = chunk_start;
+
+    let mut max_value = 0u64;
+    // レーンは順不同に終わるので、最大停止時間の同値は acc.stopped が小さい数に寄せる
+    let finish = |acc: &mut ChunkAccumulator, n: u64, st: Option<u64>| {
+        acc.checked += 1;
+        match st {
+            Some(st) => acc.stopped(st, || BigUint::from(n)),
+            None => acc.failures.push(BigUint::from(n)),
+        }
+    };
+
+    loop {
+        // 空いたレーンに次のシードを詰める（1 は停止時間 0 として直接処理）
+        for l in 0..SMALL_LANES {
+            while !active[l] && next_seed <= chunk_end {
+                let n = next_seed;
+                next_seed += 2;
+                max_value = max_value.max(n);
+                if n == 1 {
+                    acc.stats.reached_one += 1;
+                    finish(acc, 1, Some(0));
+                } else if max_steps == 0 {
+                    finish(acc, n, None);
+                } else {
+                    seed[l] = n;
+                    cur[l] = n;
+                    steps[l] = 0;
+                    active[l] = true;
+                }
+            }
+        }
+        if !active.iter().any(|&a| a) {
+            break;
+        }
+
+        for l in 0..SMALL_LANES {
+            if !active[l] {
+                continue;
+            }
+            if cur[l] > overflow_limit {
+                // u64 を超える: このレーンだけ一般経路で続きを計算する
+                let rest = continue_stopping_time(cur[l], seed[l], x, max_steps - steps[l], &mut acc.stats);
+                finish(acc, seed[l], rest.map(|r| steps[l] + r));
+                active[l] = false;
+                continue;
+            }
+            let xn1 = cur[l] * x + 1;
+            let d = xn1.trailing_zeros();
+            cur[l] = xn1 >> d;
+            max_value = max_value.max(cur[l]);
+            steps[l] += 1;
+            acc.stats.record_d(d as u64);
+
+            if cur[l] < seed[l] {
+                if cur[l] == 1 {
+                    acc.stats.reached_one += 1;
+                } else {
+                    acc.stats.dropped_below += 1;
+                }
+                finish(acc, seed[l], Some(steps[l]));
+                active[l] = false;
+            } else if steps[l] >= max_steps {
+                finish(acc, seed[l], None);
+                active[l] = false;
+            }
+        }
+    }
+
+    acc.stats.record_bits((64 - max_value.leading_zeros()) as u64);
+}
+
+/// current（> seed）から seed 未満に落ちるまでの残りステップ数（u128 → パックド）。
+/// max_steps 以内に落ちなければ None。d・最大ビット長・停止の種類は stats に加える。
+fn continue_stopping_time(current: u64, seed: u64, x: u64, max_steps: u64, stats: &mut SeedStats) -> Option<u64> {
+    let x128 = x as u128;
+    let overflow_limit = (u128::MAX - 1) / x128;
+    let mut cur = current as u128;
+    let mut steps = 0u64;
+    while steps < max_steps {
+        if cur > overflow_limit {
+            let rest = trajectory::stopping_time_below_d(&BigUint::from(cur), x, max_steps - steps, &BigUint::from(seed), stats)?;
+            return Some(steps + rest);
+        }
+        let xn1 = cur * x128 + 1;
+        let d = xn1.trailing_zeros();
+        cur = xn1 >> d;
+        stats.record_bits((128 - cur.leading_zeros()) as u64);
+        steps += 1;
+        stats.record_d(d as u64);
+        if cur < seed as u128 {
+            if cur == 1 {
+                stats.reached_one += 1;
+            } else {
+                stats.dropped_below += 1;
+            }
+            return Some(steps);
+        }
+    }
+    None
+}
+
+/// [start, end] の全奇数を停止時間法で検証する（並列版）。
+/// Rayon でチャンク分割して並列処理。
+/// progress_callback: (完了数, 総数) を定期的に呼ぶ（スレッドセーフ）。
+/// 呼ぶ間隔は REPORT_EVERY_PARALLEL 個ごと。変えるときは VerifyConfig::report_every を設定して
+/// try_verify_range_with_progress を使う。
+pub fn verify_range_parallel(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    verify_range_parallel_cancellable(start, end, x, max_steps, true, true, true, &AtomicBool::new(false), progress_callback)
+}
+
+/// verify_range_parallel と同じ検証を、シードのコスト見積もりで作業単位を分けて行う。
+/// 範囲を COST_BALANCE_BLOCK 個ずつのブロックに分け、各ブロックの中で各シードを u64 だけで
+/// COST_PROBE_STEPS ステップまで試し走りさせ（cost_balanced_units）、見積もりコストの合計が
+/// ほぼ等しい連続区間に切って検証する。ブロック同士も並列に流すので、ブロックの境目で
+/// 全スレッドが待ち合わせることはない。長い glide を持つまれなシードが 1 つの作業単位に
+/// 偏るのを避けるためのもので、結果は verify_range_parallel と同じ。
+/// 試し走りの分だけ総仕事量は増えるので、速くなるかは範囲とスレッド数による。
+/// u64 に収まらない範囲は verify_range_parallel と同じくシングルスレッド版で処理する。
+pub fn verify_range_parallel_balanced(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    if end.bits() <= 64 {
+        let s = u64::try_from(start).unwrap_or(u64::MAX);
+        let e = u64::try_from(end).unwrap_or(0);
+        if s > e {
+            return VerifyResult::empty();
+        }
+        let s = s.max(1);
+        let config = VerifyConfig::new(start.clone(), end.clone(), x, max_steps);
+        return verify_range_parallel_u64_balanced(s, e, &config, &progress_callback);
+    }
+    let mut adj_start = start.clone();
+    if !adj_start.bit(0) {
+        adj_start += 1u32;
+    }
+    verify_range_reporting(&adj_start, end, x, max_steps, REPORT_EVERY_PARALLEL, progress_callback)
+}
+
+/// コスト見積もりの試し走りの最大ステップ数（ほとんどのシードはこれより早く n 未満に落ちる）
+const COST_PROBE_STEPS: u64 = 32;
+/// 試し走りで n 未満に落ちなかったとき、n を上回っている 1 ビットあたりに見込む残りステップ数
+/// （3n+1 は 1 奇数ステップあたり平均 2 - log2(3) ≈ 0.415 ビット縮む）
+const COST_PER_EXCESS_BIT: u64 = 3;
+/// コスト均衡化で一度に見積もる奇数の個数（見積もり列のメモリを抑えるためのブロック）
+const COST_BALANCE_BLOCK: u64 = 1 << 20;
+/// コスト均衡化でスレッドあたりに切る作業単位の数
+const COST_UNITS_PER_THREAD: u64 = 8;
+
+/// 停止時間法で n を検証するコストの見積もり（ステップ数の単位）。u64 だけで最大
+/// COST_PROBE_STEPS ステップ走らせ、n 未満に落ちればそのステップ数、落ちなければ
+/// 試し走りの分に n を上回っているビット数 × COST_PER_EXCESS_BIT を足す。
+/// x·n+1 が u64 を超える（軌道が大きく伸びた）場合は max_steps とみなす。
+fn estimate_seed_cost(n: u64, x: u64, max_steps: u64) -> u64 {
+    let mut v = n;
+    for step in 1..=COST_PROBE_STEPS.min(max_steps) {
+        let Some(xn1) = v.checked_mul(x).and_then(|xv| xv.checked_add(1)) else {
+            return max_steps;
+        };
+        v = xn1 >> xn1.trailing_zeros();
+        if v < n || v == 1 {
+            return step;
+        }
+    }
+    let excess = (v.ilog2() - n.ilog2()) as u64;
+    (COST_PROBE_STEPS + excess * COST_PER_EXCESS_BIT).min(max_steps)
+}
+
+/// 奇数 first, first+2, ..., last を、見積もりコスト（estimate_seed_cost）の合計がほぼ
+/// 総コスト / (スレッド数 × COST_UNITS_PER_THREAD) になる連続区間 (先頭, 末尾) に切る。
+/// 単独で目標を超える重いシードはそれだけで 1 区間になる。区間は昇順に隙間なく並ぶ。
+fn cost_balanced_units(first: u64, last: u64, x: u64, max_steps: u64) -> Vec<(u64, u64)> {
+    let count = (last - first) / 2 + 1;
+    let costs: Vec<u64> = (0..count).into_par_iter().map(|i| estimate_seed_cost(first + 2 * i, x, max_steps)).collect();
+    let total: u64 = costs.iter().sum();
+    let target = (total / (rayon::current_num_threads() as u64 * COST_UNITS_PER_THREAD)).max(1);
+
+    let mut units = Vec::new();
+    let mut unit_first = first;
+    let mut acc = 0u64;
+    for (i, &cost) in costs.iter().enumerate() {
+        acc += cost;
+        let n = first + 2 * i as u64;
+        if acc >= target && n < last {
+            units.push((unit_first, n));
+            unit_first = n + 2;
+            acc = 0;
+        }
+    }
+    units.push((unit_first, last));
+    units
+}
+
+/// u64 範囲 [start, end] のコスト均衡化した並列検証（verify_range_parallel_balanced の本体）。
+/// ブロックごとの見積もりと検証はブロックを受け持ったタスクの中で行い、作業単位は
+/// rayon のワークスティーリングでほかのブロックの作業単位と混ざって流れる。
+/// start / end 以外の設定（進捗の報告間隔 report_every を含む）は config から読む
+fn verify_range_parallel_u64_balanced(
+    start: u64,
+    end: u64,
+    config: &VerifyConfig,
+    progress_callback: &(impl Fn(u64, u64) + Sync),
+) -> VerifyResult {
+    let (x, max_steps, collect_gpk) = (config.x, config.max_steps, config.collect_gpk);
+    let (use_phase1, use_stopping_time) = (config.use_phase1, config.use_stopping_time);
+    // start を奇数に調整
+    let start = if start % 2 == 0 { start + 1 } else { start };
+    if start > end {
+        return VerifyResult::empty();
+    }
+
+    let total_odd = (end - start) / 2 + 1;
+    let num_blocks = total_odd.div_ceil(COST_BALANCE_BLOCK);
+
+    let global_done = AtomicU64::new(0);
+    let progress = SharedProgress::new(&global_done, total_odd, config.report_every, progress_callback);
+    // 停止した数がなければ最大停止時間 0 の数は start（同値は小さい数を優先し、チャンク処理順に依存しない）
+    let mut global = ChunkAccumulator::new(collect_gpk);
+    global.set_fallback_number(BigUint::from(start));
+    let global = Mutex::new(global);
+
+    let run_unit = |unit_start: u64, unit_end: u64| {
+        let mut local = ChunkAccumulator::new(collect_gpk);
+        let mut unreported = 0u64;
+
+        let mut n = unit_start;
+        while n <= unit_end {
+            local.record(|| BigUint::from(n), |stats| {
+                trajectory::stopping_time_u64_fast_d(n, x, max_steps.for_u64(n), use_phase1, use_stopping_time, stats)
+            });
+            progress.tick(&mut unreported);
+            n += 2;
+        }
+
+        progress.flush(&mut unreported);
+        global.lock().unwrap().merge(local);
+    };
+
+    (0..num_blocks).into_par_iter().for_each(|block_idx| {
+        let block_start = start + block_idx * COST_BALANCE_BLOCK * 2;
+        let block_end = std::cmp::min(block_start + (COST_BALANCE_BLOCK - 1) * 2, end);
+        cost_balanced_units(block_start, block_end, x, max_steps.for_u64(block_end))
+            .into_par_iter()
+            .for_each(|(unit_start, unit_end)| run_unit(unit_start, unit_end));
+    });
+
+    global.into_inner().unwrap().into_result(Strategy::ParallelU64)
+}
+
+/// 明示したシードの集合だけを停止時間法で並列検証する（連続区間ではない疎な集合向け）。
+/// 区間を舐めずに、記録保持者や特定剰余類のシードだけを触る。
+/// 偶数（0 を含む）は奇数→奇数写像の対象外なので数えずに飛ばす。重複はそのまま重複して数える。
+/// 最大停止時間が同じシードが複数あるときは小さい方を max_stopping_time_number にする。
+pub fn verify_seeds(seeds: &[u64], x: u64, max_steps: u64, collect_gpk: bool) -> VerifyResult {
+    // チャンク分割: verify_range_parallel_u64_live と同じ 10000 個単位
+    let chunk_size = 10000usize;
+
+    let global = Mutex::new(ChunkAccumulator::new(collect_gpk));
+
+    seeds.par_chunks(chunk_size).for_each(|chunk| {
+        let mut local = ChunkAccumulator::new(collect_gpk);
+        for &n in chunk.iter().filter(|&&n| n % 2 == 1) {
+            local.record(|| BigUint::from(n), |stats| trajectory::stopping_time_u64_fast_d(n, x, max_steps, true, true, stats));
+        }
+        global.lock().unwrap().merge(local);
+    });
+
+    global.into_inner().unwrap().into_result(Strategy::ParallelU64)
+}
+
+/// 検証中の集計値。ワーカーがチャンク単位で ChunkAccumulator を merge し、他スレッドからいつでも読める。
+/// 進捗の数と総数は atomic、それ以外は短時間ロックの Mutex。
+struct LiveAggregates {
+    /// 検証の総数（u64 に飽和）。開始時に一度だけ設定される
+    total: AtomicU64,
+    /// 検証の正確な総数（odd_count）。total と同時に設定される
+    total_odd: Mutex<BigUint>,
+    done: AtomicU64,
+    acc: Mutex<ChunkAccumulator>,
+}
+
+impl LiveAggregates {
+    fn new() -> Self {
+        LiveAggregates {
+            total: AtomicU64::new(0),
+            total_odd: Mutex::new(BigUint::ZERO),
+            done: AtomicU64::new(0),
+            acc: Mutex::new(ChunkAccumulator::default()),
+        }
+    }
+
+    /// 検証の総数を設定する（開始時に一度だけ呼ぶ）
+    fn set_total(&self, total_odd: BigUint) {
+        self.total.store(u64::try_from(&total_odd).unwrap_or(u64::MAX), Ordering::Relaxed);
+        *self.total_odd.lock().unwrap() = total_odd;
+    }
+
+    /// ワーカーの局所集計を反映する
+    fn publish(&self, local: ChunkAccumulator) {
+        self.acc.lock().unwrap().merge(local);
+    }
+
+    /// 現在の集計値のコピー
+    fn snapshot(&self, finished: bool) -> VerifySnapshot {
+        let acc = self.acc.lock().unwrap();
+        let (max_stopping_time, max_stopping_time_number) = acc.best.clone().unwrap_or((0, BigUint::ZERO));
+        VerifySnapshot {
+            done: self.done.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            total_odd: self.total_odd.lock().unwrap().clone(),
+            max_stopping_time,
+            max_stopping_time_number,
+            failure_count: acc.failures.len() as u64,
+            gpk_stats: acc.stats.gpk.clone().unwrap_or_else(GpkStats::new),
+            max_intermediate_bits: acc.stats.peak_bits,
+            finished,
+        }
+    }
+
+    /// 集計値を VerifyResult にコピーする（ワーカー終了後に呼ぶ。終了後も snapshot は読める）
+    fn to_result(&self, strategy: Strategy) -> VerifyResult {
+        self.acc.lock().unwrap().clone().into_result(strategy)
+    }
+}
+
+/// verify_range_spawn の途中経過
+#[derive(Debug, Clone)]
+pub struct VerifySnapshot {
+    /// 検証済みの奇数の数
+    pub done: u64,
+    /// 検証する奇数の総数（u64 に飽和）。開始直後は 0 のことがある
+    pub total: u64,
+    /// 検証する奇数の正確な総数（odd_count）。開始直後は 0 のことがある
+    pub total_odd: BigUint,
+    /// 現時点の最大停止時間とその数
+    pub max_stopping_time: u64,
+    pub max_stopping_time_number: BigUint,
+    /// 現時点で収束しなかった数の個数
+    pub failure_count: u64,
+    /// 現時点までの GPK 統計（チャンク単位で反映される）
+    pub gpk_stats: GpkStats,
+    /// 現時点までの軌道上の値の最大ビット長（チャンク単位で反映される）
+    pub max_intermediate_bits: u64,
+    /// ワーカーが終了したか
+    pub finished: bool,
+}
+
+impl VerifySnapshot {
+    /// 進捗の割合 done / total_odd（0.0〜1.0、progress_fraction）。総数が未設定（0）なら 0
+    pub fn fraction_done(&self) -> f64 {
+        progress_fraction(self.done, &self.total_odd)
+    }
+}
+
+/// verify_range_spawn / VerifyHandle が共有する状態
+struct SpawnShared {
+    live: LiveAggregates,
+    cancel: AtomicBool,
+}
+
+/// バックグラウンドで走る範囲検証へのハンドル。
+/// snapshot で途中経過を読み、join で最終結果を受け取る。
+pub struct VerifyHandle {
+    shared: Arc<SpawnShared>,
+    thread: std::thread::JoinHandle<VerifyResult>,
+}
+
+impl VerifyHandle {
+    /// 途中経過を読む（ワーカーは止めない）
+    pub fn snapshot(&self) -> VerifySnapshot {
+        let finished = self.is_finished();
+        self.shared.live.snapshot(finished)
+    }
+
+    /// キャンセルを要求する。join は途中結果を返す
+    pub fn cancel(&self) {
+        self.shared.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// ワーカーが終了したか（panic で終わった場合も true。そのとき join は panic する）
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// 終了を待って最終結果を返す
+    pub fn join(self) -> VerifyResult {
+        self.thread.join().expect("verify worker panicked")
+    }
+}
+
+/// verify_range_parallel_cancellable を専用スレッドで開始し、すぐにハンドルを返す。
+/// 並列処理自体は rayon のグローバルプールで行われる。
+pub fn verify_range_spawn(
+    start: BigUint,
+    end: BigUint,
+    x: u64,
+    max_steps: u64,
+    collect_gpk: bool,
+    use_phase1: bool,
+    use_stopping_time: bool,
+) -> VerifyHandle {
+    let shared = Arc::new(SpawnShared {
+        live: LiveAggregates::new(),
+        cancel: AtomicBool::new(false),
+    });
+    let config = VerifyConfig { collect_gpk, use_phase1, use_stopping_time, ..VerifyConfig::new(start, end, x, max_steps) };
+    let worker = Arc::clone(&shared);
+    let thread = std::thread::spawn(move || {
+        let sink = LiveSink { cancel: &worker.cancel, progress: &|_, _| {}, live: &worker.live };
+        let strategy = verify_range_live(&config, &sink);
+        worker.live.to_result(strategy)
+    });
+    VerifyHandle { shared, thread }
+}
+
+/// 区間検証の処理速度（奇数/秒）の見積もり（try_verify_range / try_verify_range_with_progress 向け）。
+/// config.start から sample 個の奇数を config の設定（max_steps・collect_gpk・use_phase1・
+/// use_stopping_time）で1スレッドで実際に検証して測る。本番の設定に合わせること
+/// （GPK 収集や停止時間法の有無で速度は桁で変わる）。config で実際に走る経路が並列
+/// （ParallelU64 / ParallelBigUint）なら rayon のスレッド数倍して返し（奇数ごとに独立なので
+/// ほぼ線形に伸びる）、force_single_thread の SingleThread ならそのまま返す。sample=0 は 1 として扱う。
+pub fn estimate_throughput(config: &VerifyConfig, sample: u64) -> f64 {
+    let two = BigUint::from(2u64);
+    let mut n = config.start.clone();
+    if !n.bit(0) {
+        n += 1u32;
+    }
+    let sample = sample.max(1);
+    let mut stats = SeedStats::new(config.collect_gpk);
+    let never = AtomicBool::new(false);
+
+    let timer = std::time::Instant::now();
+    for _ in 0..sample {
+        let st = config_stopping_time(config, &n, &mut stats, &never);
+        std::hint::black_box(st.ok());
+        n += &two;
+    }
+    let secs = timer.elapsed().as_secs_f64().max(1e-9);
+    let threads = match planned_strategy(config) {
+        Strategy::SingleThread => 1,
+        Strategy::ParallelU64 | Strategy::ParallelBigUint => rayon::current_num_threads(),
+    };
+    sample as f64 / secs * threads as f64
+}
+
+/// キャンセル可能な並列検証。cancel が true になると途中結果を返す。
+/// collect_gpk が false なら GPK 統計の収集をスキップして高速化。
+pub fn verify_range_parallel_cancellable(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    collect_gpk: bool,
+    use_phase1: bool,
+    use_stopping_time: bool,
+    cancel: &AtomicBool,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> VerifyResult {
+    let config = VerifyConfig {
+        collect_gpk,
+        use_phase1,
+        use_stopping_time,
+        ..VerifyConfig::new(start.clone(), end.clone(), x, max_steps)
+    };
+    let live = LiveAggregates::new();
+    let strategy = verify_range_live(&config, &LiveSink { cancel, progress: &progress_callback, live: &live });
+    live.to_result(strategy)
+}
+
+/// verify_range_parallel_rich の進捗コールバックに渡す情報
+#[derive(Debug, Clone)]
+pub struct ProgressInfo {
+    /// 検証済みの奇数の数
+    pub done: u64,
+    /// 検証する奇数の総数（見積もり）
+    pub total: u64,
+    /// 開始からの平均処理速度（奇数/秒）
+    pub rate: f64,
+    /// 現時点の最大停止時間とその数（u64 経路ではチャンク単位で反映される）
+    pub current_max_st: u64,
+    pub current_max_st_seed: BigUint,
+}
+
+/// 進捗に処理速度と途中の最大停止時間を付けて報告する並列検証。
+/// 集計・経路は verify_range_parallel_cancellable（GPK 収集あり、phase1 あり、停止時間法）と同じ。
+/// コールバックは1つずつ順に呼ばれ、done と current_max_st は呼び出しごとに単調非減少。
+/// 報告はワーカーが report_every 個ごとに試みるが、ほかのスレッドが報告中ならその回は飛ばす
+/// （ワーカーを待たせない）。終了時には最終集計の値でもう一度呼ぶ。
+pub fn verify_range_parallel_rich(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    report_every: u64,
+    progress_callback: impl Fn(&ProgressInfo) + Sync,
+) -> VerifyResult {
+    let live = LiveAggregates::new();
+    let cancel = AtomicBool::new(false);
+    let timer = std::time::Instant::now();
+    // 前回報告した最大停止時間。報告はこのロックの下で値を読んでから行うので、報告の順序と値の
+    // 順序が揃う。集計のロックが取れない（ワーカーが反映中）ときは前回の値をそのまま使う
+    let last_best = Mutex::new((0u64, BigUint::ZERO));
+    let report = || {
+        let Ok(mut best) = last_best.try_lock() else {
+            return;
+        };
+        if let Ok(acc) = live.acc.try_lock() {
+            if let Some(acc_best) = &acc.best {
+                best.clone_from(acc_best);
+            }
+        }
+        let done = live.done.load(Ordering::Relaxed);
+        progress_callback(&ProgressInfo {
+            done,
+            total: live.total.load(Ordering::Relaxed),
+            rate: done as f64 / timer.elapsed().as_secs_f64().max(1e-9),
+            current_max_st: best.0,
+            current_max_st_seed: best.1.clone(),
+        });
+    };
+    let config = VerifyConfig { report_every, ..VerifyConfig::new(start.clone(), end.clone(), x, max_steps) };
+    let strategy = verify_range_live(&config, &LiveSink { cancel: &cancel, progress: &|_, _| report(), live: &live });
+    report();
+    live.to_result(strategy)
+}
+
+/// キャンセル可能な検証の出力先
+struct LiveSink<'a, P> {
+    /// 立つと検証を途中でやめる
+    cancel: &'a AtomicBool,
+    /// (完了数, 総数) の進捗コールバック
+    progress: &'a P,
+    /// 逐次反映する集計
+    live: &'a LiveAggregates,
+}
+
+/// キャンセル可能な検証の本体。結果は sink.live に逐次反映される。戻り値は使った実行経路
+/// （u64 範囲は ParallelU64、それ以外は SingleThread。force_single_thread は見ない）。
+fn verify_range_live(config: &VerifyConfig, sink: &LiveSink<impl Fn(u64, u64) + Sync>) -> Strategy {
+    let LiveSink { cancel, progress: progress_callback, live } = *sink;
+    let (end, x, max_steps, collect_gpk) = (&config.end, config.x, config.max_steps, config.collect_gpk);
+    let report_every = config.report_every.max(1);
+    let two = BigUint::from(2u64);
+    let one = BigUint::one();
+
+    let mut adj_start = config.start.clone();
+    if &adj_start % &two == BigUint::ZERO {
+        adj_start += &one;
+    }
+
+    let start_u64 = adj_start.to_u64_digits();
+    let end_u64 = end.to_u64_digits();
+
+    if start_u64.len() <= 1 && end_u64.len() <= 1 {
+        let s = start_u64.first().copied().unwrap_or(1);
+        let e = end_u64.first().copied().unwrap_or(0);
+        verify_range_parallel_u64_live(s, e, config, sink);
+        return Strategy::ParallelU64;
+    }
+
+    if adj_start > *end {
+        return Strategy::SingleThread;
+    }
+
+    // BigUint: シングルスレッド（キャンセル対応）
+    live.set_total(odd_count(&adj_start, end));
+    let total_estimate = live.total.load(Ordering::Relaxed);
+    live.acc.lock().unwrap().set_fallback_number(adj_start.clone());
+
+    let mut n = adj_start;
+    let mut total_checked = 0u64;
+    let mut local = ChunkAccumulator::new(collect_gpk);
+    let mut scratch = SeedStats::new(collect_gpk);
+
+    while n <= *end {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let outcome = local.record_cancellable(&mut scratch, || n.clone(), |stats| {
+            trajectory::stopping_time_with_gpk_cancellable_d(&n, x, max_steps.for_bits(n.bits()), config.use_stopping_time, stats, Some(cancel))
+        });
+        if outcome.is_err() {
+            break;
+        }
+        total_checked += 1;
+        live.done.store(total_checked, Ordering::Relaxed);
+        if total_checked.is_multiple_of(report_every) {
+            live.publish(std::mem::replace(&mut local, ChunkAccumulator::new(collect_gpk)));
+            progress_callback(total_checked, total_estimate);
+        }
+        n += &two;
+    }
+
+    live.publish(local);
+    progress_callback(total_checked, total_estimate);
+    Strategy::SingleThread
+}
+
+/// u64 範囲 [start, end] のキャンセル可能な並列検証（結果は sink.live にチャンク単位で反映）。
+/// start / end 以外の設定は config から読む
+fn verify_range_parallel_u64_live(start: u64, end: u64, config: &VerifyConfig, sink: &LiveSink<impl Fn(u64, u64) + Sync>) {
+    let LiveSink { cancel, progress: progress_callback, live } = *sink;
+    let (x, max_steps, collect_gpk) = (config.x, config.max_steps, config.collect_gpk);
+    let (use_phase1, use_stopping_time) = (config.use_phase1, config.use_stopping_time);
+    let start = if start % 2 == 0 { start + 1 } else { start };
+    if start > end {
+        return;
+    }
+
+    let total_odd = (end - start) / 2 + 1;
+    let chunk_size: u64 = 10000;
+    let num_chunks = (total_odd + chunk_size - 1) / chunk_size;
+    let progress = SharedProgress::new(&live.done, total_odd, config.report_every, progress_callback);
+
+    live.set_total(BigUint::from(total_odd));
+    live.acc.lock().unwrap().set_fallback_number(BigUint::from(start));
+
+    (0..num_chunks).into_par_iter().for_each(|chunk_idx| {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let chunk_start = start + chunk_idx * chunk_size * 2;
+        let chunk_end = std::cmp::min(chunk_start + (chunk_size - 1) * 2, end);
+
+        let mut local = ChunkAccumulator::new(collect_gpk);
+        let mut scratch = SeedStats::new(collect_gpk);
+        let mut unreported = 0u64;
+
+        let mut n = chunk_start;
+        while n <= chunk_end {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let outcome = local.record_cancellable(&mut scratch, || BigUint::from(n), |stats| {
+                trajectory::stopping_time_u64_cancellable_d(n, n, x, max_steps.for_u64(n), use_phase1, use_stopping_time, stats, Some(cancel))
+            });
+            if outcome.is_err() {
+                break;
+            }
+            progress.tick(&mut unreported);
+            n += 2;
+        }
+
+        progress.flush(&mut unreported);
+        live.publish(local);
+    });
+}
+
+/// Auto のシード 1 ビットあたりのステップ上限。3n+1 で 1 に着くまでの奇数ステップ数は
+/// 平均でビット長の約 2.4 倍、u64 の既知の記録（9780657631 など）でも約 12.5 倍
+pub const AUTO_STEPS_PER_BIT: u64 = 16;
+
+/// Auto のステップ上限の下限（27 のような短いのに長い軌道を持つ小さいシード向け）
+pub const AUTO_MIN_STEPS: u64 = 64;
+
+/// シードごとのステップ上限（VerifyConfig::max_steps）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxSteps {
+    /// 全シードで同じ上限
+    Fixed(u64),
+    /// シードのビット長に比例させる: max(AUTO_STEPS_PER_BIT × ビット長, AUTO_MIN_STEPS)。
+    /// 大きいシードが固定上限に引っかかって偽の failures になるのを防ぎ、
+    /// 小さいシードでは発散する軌道を早く打ち切る
+    Auto,
+}
+
+impl MaxSteps {
+    /// ビット長 bits のシードに使うステップ上限
+    pub fn for_bits(self, bits: u64) -> u64 {
+        match self {
+            MaxSteps::Fixed(steps) => steps,
+            MaxSteps::Auto => AUTO_STEPS_PER_BIT.saturating_mul(bits).max(AUTO_MIN_STEPS),
+        }
+    }
+
+    /// u64 のシード n に使うステップ上限
+    #[inline]
+    fn for_u64(self, n: u64) -> u64 {
+        self.for_bits((64 - n.leading_zeros()) as u64)
+    }
+}
+
+impl From<u64> for MaxSteps {
+    fn from(steps: u64) -> Self {
+        MaxSteps::Fixed(steps)
+    }
+}
+
+/// "auto"（大文字小文字は問わない）なら Auto、それ以外は u64 として Fixed（CLI・GUI の入力用）
+impl std::str::FromStr for MaxSteps {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("auto") {
+            Ok(MaxSteps::Auto)
+        } else {
+            s.parse().map(MaxSteps::Fixed)
+        }
+    }
+}
+
+/// Fixed は数値、Auto は "auto"（FromStr で読み戻せる）
+impl std::fmt::Display for MaxSteps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaxSteps::Fixed(steps) => write!(f, "{}", steps),
+            MaxSteps::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// try_verify_range の設定
+#[derive(Debug, Clone)]
+pub struct VerifyConfig {
+    pub start: BigUint,
+    pub end: BigUint,
+    /// 写像 (xn+1)/2^d の x（x-1 が 2 の冪、x >= 3）
+    pub x: u64,
+    /// シードごとのステップ上限（u64 を渡せば Fixed）
+    pub max_steps: MaxSteps,
+    pub collect_gpk: bool,
+    /// u128 / U256 フェーズを使う（u64 経路のみ）
+    pub use_phase1: bool,
+    /// n 未満への到達で停止する（false なら 1 まで追跡）
+    pub use_stopping_time: bool,
+    /// 並列化せず 1 スレッドで小さい数から順に検証する（u64 範囲でも u128 / U256 フェーズは使う）。
+    /// 集計結果は並列経路と同じで、不一致の再現・デバッグ用。result.strategy は SingleThread
+    pub force_single_thread: bool,
+    /// 何個の奇数ごとに進捗コールバックを呼ぶか（並列経路はチャンクごと、1 スレッド経路は通算で数える。
+    /// 0 は 1 扱い）。速い小さなシードでは大きく、遅い大数では小さくする
+    pub report_every: u64,
+}
+
+impl VerifyConfig {
+    /// 既定値: GPK 収集あり、phase1 あり、停止時間法、並列、進捗は REPORT_EVERY_PARALLEL 個ごと
+    pub fn new(start: BigUint, end: BigUint, x: u64, max_steps: impl Into<MaxSteps>) -> Self {
+        VerifyConfig {
+            start,
+            end,
+            x,
+            max_steps: max_steps.into(),
+            collect_gpk: true,
+            use_phase1: true,
+            use_stopping_time: true,
+            force_single_thread: false,
+            report_every: REPORT_EVERY_PARALLEL,
+        }
+    }
+}
+
+/// try_verify_range が検証を始める前に弾く設定の誤り
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// x が 3 以上でないか、x-1 が 2 の冪でない
+    InvalidMultiplier(u64),
+    /// max_steps = 0 では全ての数が failures になる
+    ZeroMaxSteps,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidMultiplier(x) => write!(f, "x = {} is not of the form 2^s + 1 (s >= 1)", x),
+            VerifyError::ZeroMaxSteps => write!(f, "max_steps must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// 設定を検査してから範囲検証する。黙って経路を切り替えず、使った経路を result.strategy で返す:
+/// end が u64 に収まれば ParallelU64、超えれば ParallelBigUint（BigUintRangeChunker でチャンク並列）、
+/// force_single_thread なら範囲によらず SingleThread。
+/// 空範囲は Ok（total_checked = 0）。
+pub fn try_verify_range(config: &VerifyConfig) -> Result<VerifyResult, VerifyError> {
+    try_verify_range_with_progress(config, &AtomicBool::new(false), |_, _| {})
+}
+
+/// try_verify_range に中断と進捗報告を付けたもの。progress_callback: (完了数, 総数) を
+/// config.report_every 個ごとと、各チャンク（1 スレッド経路では範囲）の最後に呼ぶ。
+/// cancel が true になると、それまでに検証したシードだけの途中結果を返す。
+pub fn try_verify_range_with_progress(
+    config: &VerifyConfig,
+    cancel: &AtomicBool,
+    progress_callback: impl Fn(u64, u64) + Sync,
+) -> Result<VerifyResult, VerifyError> {
+    let x = config.x;
+    if x < 3 || !(x - 1).is_power_of_two() {
+        return Err(VerifyError::InvalidMultiplier(x));
+    }
+    if config.max_steps == MaxSteps::Fixed(0) {
+        return Err(VerifyError::ZeroMaxSteps);
+    }
+    match planned_strategy(config) {
+        Strategy::SingleThread => Ok(verify_range_sequential(config, cancel, &progress_callback)),
+        Strategy::ParallelU64 => {
+            let live = LiveAggregates::new();
+            let strategy = verify_range_live(config, &LiveSink { cancel, progress: &progress_callback, live: &live });
+            debug_assert_eq!(strategy, Strategy::ParallelU64);
+            Ok(live.to_result(strategy))
+        }
+        Strategy::ParallelBigUint => Ok(verify_range_parallel_biguint(config, cancel, &progress_callback)),
+    }
+}
+
+/// try_verify_range が config で使う実行経路（estimate_throughput もこれに合わせて見積もる）
+fn planned_strategy(config: &VerifyConfig) -> Strategy {
+    if config.force_single_thread {
+        Strategy::SingleThread
+    } else if config.end.bits() <= 64 {
+        Strategy::ParallelU64
+    } else {
+        Strategy::ParallelBigUint
+    }
+}
+
+/// try_verify_range の 1 スレッド経路。奇数を小さい順に1つずつ検証するので、
+/// 最大停止時間の同値は自然に小さい数になり、failures も昇順に並ぶ。
+/// u64 に収まるシードは並列の u64 経路と同じ stopping_time_u64_fast_d（フェーズ付き）、
+/// それ以上は stopping_time_with_gpk_d で処理する。停止した数がないときの
+/// max_stopping_time_number も並列経路に合わせる（u64 範囲なら最初の奇数、それ以外は 0）。
+fn verify_range_sequential(
+    config: &VerifyConfig,
+    cancel: &AtomicBool,
+    progress_callback: &impl Fn(u64, u64),
+) -> VerifyResult {
+    let report_every = config.report_every.max(1);
+    let two = BigUint::from(2u64);
+    let mut n = config.start.clone();
+    if !n.bit(0) {
+        n += 1u32;
+    }
+    if n > config.end {
+        return VerifyResult::empty();
+    }
+    let total_estimate = odd_count_saturating(&n, &config.end);
+    let mut acc = ChunkAccumulator::new(config.collect_gpk);
+    if config.end.bits() <= 64 {
+        acc.set_fallback_number(n.clone());
+    }
+
+    let mut scratch = SeedStats::new(config.collect_gpk);
+    while n <= config.end && !cancel.load(Ordering::Relaxed) {
+        let outcome = acc.record_cancellable(&mut scratch, || n.clone(), |stats| config_stopping_time(config, &n, stats, cancel));
+        if outcome.is_err() {
+            break;
+        }
+        if acc.checked.is_multiple_of(report_every) {
+            progress_callback(acc.checked, total_estimate);
+        }
+        n += &two;
+    }
+
+    progress_callback(acc.checked, total_estimate);
+    acc.into_result(Strategy::SingleThread)
+}
+
+/// config の設定で n の停止時間を計算する（中断対応）。u64 に収まるシードは
+/// stopping_time_u64_cancellable_d（フェーズ付き）、それ以上は stopping_time_with_gpk_cancellable_d
+fn config_stopping_time(config: &VerifyConfig, n: &BigUint, stats: &mut SeedStats, cancel: &AtomicBool) -> Result<Option<u64>, Cancelled> {
+    match u64::try_from(n) {
+        Ok(v) => trajectory::stopping_time_u64_cancellable_d(
+            v, v, config.x, config.max_steps.for_u64(v), config.use_phase1, config.use_stopping_time, stats, Some(cancel),
+        ),
+        Err(_) => trajectory::stopping_time_with_gpk_cancellable_d(
+            n, config.x, config.max_steps.for_bits(n.bits()), config.use_stopping_time, stats, Some(cancel),
+        ),
+    }
+}
+
+/// BigUint 範囲の停止時間法によるチャンク並列検証（try_verify_range 用）。
+/// 同じ最大停止時間なら小さい方の数を max_stopping_time_number にする。
+fn verify_range_parallel_biguint(
+    config: &VerifyConfig,
+    cancel: &AtomicBool,
+    progress_callback: &(impl Fn(u64, u64) + Sync),
+) -> VerifyResult {
+    verify_range_chunked(config, cancel, progress_callback, |_, _| {})
+}
+
+/// 並列ワーカーの進捗報告。ワーカーは手元の未報告数を数え、every 個たまるごとと作業単位の
+/// 最後に、共有の完了数 done へ加えてから callback(完了数, total) を呼ぶ
+struct SharedProgress<'a, P> {
+    done: &'a AtomicU64,
+    total: u64,
+    every: u64,
+    callback: &'a P,
+}
+
+impl<'a, P: Fn(u64, u64) + Sync> SharedProgress<'a, P> {
+    /// every が 0 なら 1 扱い
+    fn new(done: &'a AtomicU64, total: u64, every: u64, callback: &'a P) -> Self {
+        SharedProgress { done, total, every: every.max(1), callback }
+    }
+
+    /// 1 個処理したことを数え、every 個たまったら報告する
+    fn tick(&self, unreported: &mut u64) {
+        *unreported += 1;
+        if *unreported >= self.every {
+            self.flush(unreported);
+        }
+    }
+
+    /// 未報告分を共有の完了数に加えて報告する（未報告が 0 なら何もしない）
+    fn flush(&self, unreported: &mut u64) {
+        if *unreported > 0 {
+            let done = self.done.fetch_add(*unreported, Ordering::Relaxed) + *unreported;
+            (self.callback)(done, self.total);
+            *unreported = 0;
+        }
+    }
+}
+
+/// drive_chunks で流す範囲と設定
+struct ChunkRun<'a> {
+    start: &'a BigUint,
+    end: &'a BigUint,
+    collect_gpk: bool,
+    /// 各チャンクが何個ごとに進捗を報告するか（0 は 1 扱い）
+    report_every: u64,
+    /// 立つと新しいチャンクを作らず、処理中のチャンクも次のシードの前でやめる
+    cancel: &'a AtomicBool,
+}
+
+/// BigUint 範囲をチャンク並列で検証する経路の共通ドライバ。[start, end] の奇数を
+/// BigUintRangeChunker で 10000 個ずつのチャンクにし、チャンクは作りながら rayon に流す
+/// （先に全チャンクを集めると、2^50 個のような範囲ではチャンク列だけでメモリが尽きる）。
+/// シードごとに seed(n, チャンクの集計, scratch) を呼び、Err（中断）ならそのチャンクをやめる。
+/// scratch は record_cancellable 用のシード専用の統計。チャンクを処理し終えるたびに
+/// on_chunk(チャンクの先頭, チャンクの集計) を呼んでから全体に合算する。
+/// progress_callback の総数は odd_count を u64 に飽和させたもの。
+fn drive_chunks(
+    run: &ChunkRun,
+    progress_callback: &(impl Fn(u64, u64) + Sync),
+    seed: impl Fn(&BigUint, &mut ChunkAccumulator, &mut SeedStats) -> Result<(), Cancelled> + Sync,
+    on_chunk: impl Fn(&BigUint, &ChunkAccumulator) + Sync,
+) -> ChunkAccumulator {
+    let chunk_size: u64 = 10000;
+    let two = BigUint::from(2u64);
+    let global_done = AtomicU64::new(0);
+    let progress = SharedProgress::new(&global_done, odd_count_saturating(run.start, run.end), run.report_every, progress_callback);
+    let global = Mutex::new(ChunkAccumulator::new(run.collect_gpk));
+
+    BigUintRangeChunker::new(run.start, run.end, chunk_size)
+        .take_while(|_| !run.cancel.load(Ordering::Relaxed))
+        .par_bridge()
+        .for_each(|(base, count)| {
+            let mut local = ChunkAccumulator::new(run.collect_gpk);
+            let mut scratch = SeedStats::new(run.collect_gpk);
+            let mut unreported = 0u64;
+
+            let mut n = base.clone();
+            for _ in 0..count {
+                if run.cancel.load(Ordering::Relaxed) || seed(&n, &mut local, &mut scratch).is_err() {
+                    break;
+                }
+                progress.tick(&mut unreported);
+                n += &two;
+            }
+
+            progress.flush(&mut unreported);
+            on_chunk(&base, &local);
+            global.lock().unwrap().merge(local);
+        });
+
+    global.into_inner().unwrap()
+}
+
+/// config の設定で drive_chunks を回す（verify_range_parallel_biguint と verify_range_ndjson の共通部分）。
+/// 実行経路は範囲全体が u64 に収まれば ParallelU64（停止した数がないときの
+/// max_stopping_time_number も u64 経路に合わせて最初の奇数）、それ以外は ParallelBigUint。
+fn verify_range_chunked(
+    config: &VerifyConfig,
+    cancel: &AtomicBool,
+    progress_callback: &(impl Fn(u64, u64) + Sync),
+    on_chunk: impl Fn(&BigUint, &ChunkAccumulator) + Sync,
+) -> VerifyResult {
+    let run = ChunkRun {
+        start: &config.start,
+        end: &config.end,
+        collect_gpk: config.collect_gpk,
+        report_every: config.report_every,
+        cancel,
+    };
+    let mut acc = drive_chunks(
+        &run,
+        progress_callback,
+        |n, local, scratch| local.record_cancellable(scratch, || n.clone(), |stats| config_stopping_time(config, n, stats, cancel)),
+        on_chunk,
+    );
+    if config.end.bits() > 64 {
+        return acc.into_result(Strategy::ParallelBigUint);
+    }
+    if let Some((first, _)) = BigUintRangeChunker::new(&config.start, &config.end, 1).next() {
+        acc.set_fallback_number(first);
+    }
+    acc.into_result(Strategy::ParallelU64)
+}
+
+/// 単一シードの詳細レポート（analyze_seed）
+#[derive(Debug, Clone)]
+pub struct SeedAnalysis {
+    /// 開始値
+    pub start: BigUint,
+    /// 停止時間（開始値未満への到達までのステップ数）。max_steps 以内に到達しなければ None
+    pub stopping_time: Option<u64>,
+    /// 全停止時間（1 への到達までのステップ数）。max_steps 以内に到達しなければ None
+    pub total_stopping_time: Option<u64>,
+    /// 軌道上の最大値（開始値を含む）
+    pub max_value: BigUint,
+    /// 最大値を取ったステップ番号（開始値が最大なら 0）
+    pub max_value_step: u64,
+    /// 追跡が終了した理由（ステップ上限か、ペア数上限による打ち切りか）
+    pub stop_reason: StopReason,
+    /// 追跡した全ステップの GPK 集約統計
+    pub gpk_stats: GpkStats,
+}
+
+/// 1 つの数について分かることをまとめて返す。
+/// 軌道は trace_trajectory_peak で 1 まで（または max_steps / ペア数上限まで）追い、
+/// 停止時間は glide_and_total で求める。stopping_time の None が
+/// ステップ上限によるものか発散の打ち切りによるものかは stop_reason で区別できる。
+pub fn analyze_seed(n: &BigUint, x: u64, max_steps: u64) -> SeedAnalysis {
+    let peak = trajectory::trace_trajectory_peak(n, x, max_steps);
+    let (stopping_time, _) = trajectory::glide_and_total(n, x, max_steps);
+    let total_stopping_time = (peak.stop_reason == StopReason::ReachedOne).then_some(peak.total_steps);
+    SeedAnalysis {
+        start: n.clone(),
+        stopping_time,
+        total_stopping_time,
+        max_value: peak.max_value,
+        max_value_step: peak.max_value_step,
+        stop_reason: peak.stop_reason,
+        gpk_stats: peak.gpk_stats,
+    }
+}
+
+/// 単一シードの収束証明書。
+/// start から各ステップの (奇数値, d) を並べ、最後の値が 1 または start 未満であることを示す。
+/// 各遷移は value[i] * 2^d[i] = x * value[i-1] + 1 で独立に検算できる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// 開始値（奇数）
+    pub start: BigUint,
+    /// 写像の係数 x
+    pub x: u64,
+    /// 各ステップの (次の奇数, d)
+    pub steps: Vec<(BigUint, u64)>,
+}
+
+/// n の収束証明書を作る。max_steps 以内に 1 または n 未満に到達しなければ None。
+/// n は奇数であること。
+pub fn convergence_certificate(n: &BigUint, x: u64, max_steps: u64) -> Option<Certificate> {
+    if !n.bit(0) {
+        return None;
+    }
+    let stepper = XnPlusOne(x);
+    let start_pn = PairNumber::from_biguint(n);
+    let mut pn = start_pn.clone();
+    let mut steps: Vec<(BigUint, u64)> = Vec::new();
+
+    while (steps.len() as u64) < max_steps {
+        let (next, d) = stepper.step(&pn);
+        steps.push((next.to_biguint(), d));
+        if next.is_one() || next < start_pn {
+            return Some(Certificate { start: n.clone(), x, steps });
+        }
+        if next.pair_count() > trajectory::MAX_PAIR_COUNT {
+            return None;
+        }
+        pn = next;
+    }
+
+    None
+}
+
+/// 証明書を PairNumber のステップ関数を使わず BigUint 演算だけで検算する。
+/// 全遷移が正しく（各値が奇数で d が xn+1 の 2 の指数に一致）、最後の値が 1 または start 未満なら true。
+pub fn verify_certificate(cert: &Certificate, x: u64) -> bool {
+    if cert.x != x || !cert.start.bit(0) {
+        return false;
+    }
+    let mut prev = &cert.start;
+    for (value, d) in &cert.steps {
+        if !value.bit(0) {
+            return false;
+        }
+        if (value << *d) != prev * x + 1u32 {
+            return false;
+        }
+        prev = value;
+    }
+    match cert.steps.last() {
+        Some((last, _)) => last.is_one() || *last < cert.start,
+        None => false,
+    }
+}
+
+/// verify_range_ndjson が書き出しをまとめて flush するレコード数
+pub const NDJSON_FLUSH_EVERY: usize = 64;
+
+/// 区間検証の結果をチャンクごとに NDJSON（1行1 JSON オブジェクト）で w に書きながら検証する。
+/// 検証は try_verify_range の並列経路と同じ（VerifyConfig::new の既定設定）で、チャンクは
+/// BigUintRangeChunker で 10000 個ずつに分け、並列に処理し終えた順に1行ずつ書く
+/// （行の順序はチャンク順とは限らない）。NDJSON_FLUSH_EVERY 行ごとと最後に flush する。
+/// 各行のキー: chunk_start, chunk_end, checked, max_stopping_time, max_stopping_time_number,
+/// failures, total_g, total_p, total_k, total_pairs, total_steps。
+/// BigUint の値（chunk_start / chunk_end / max_stopping_time_number / failures の要素）は
+/// 精度を落とさないよう10進文字列、それ以外は数値で書く。
+/// 全行を合算したものが戻り値の VerifyResult になる。書き込みに失敗したら最初のエラーを返す。
+pub fn verify_range_ndjson(
+    start: &BigUint,
+    end: &BigUint,
+    x: u64,
+    max_steps: u64,
+    w: impl std::io::Write + Send,
+) -> std::io::Result<VerifyResult> {
+    use std::fmt::Write as _;
+
+    // (書き込み先, flush していない行数, 最初の書き込みエラー)
+    let sink = Mutex::new((w, 0usize, None::<std::io::Error>));
+    let config = VerifyConfig::new(start.clone(), end.clone(), x, max_steps);
+
+    let write_chunk = |base: &BigUint, local: &ChunkAccumulator| {
+        let count = local.checked;
+        let (local_max_st, local_max_st_n) = local.best.as_ref().map_or((0, base), |(st, n)| (*st, n));
+        let empty = GpkStats::new();
+        let local_gpk = local.stats.gpk.as_ref().unwrap_or(&empty);
+        let mut line = String::new();
+        let _ = write!(line, "{{\"chunk_start\":\"{}\",\"chunk_end\":\"{}\",\"checked\":{},\"max_stopping_time\":{},\"max_stopping_time_number\":\"{}\",\"failures\":[",
+            base, base + 2u64 * (count - 1), count, local_max_st, local_max_st_n);
+        for (i, f) in local.failures.iter().enumerate() {
+            let _ = write!(line, "{}\"{}\"", if i == 0 { "" } else { "," }, f);
+        }
+        let _ = writeln!(line, "],\"total_g\":{},\"total_p\":{},\"total_k\":{},\"total_pairs\":{},\"total_steps\":{}}}",
+            local_gpk.total_g, local_gpk.total_p, local_gpk.total_k, local_gpk.total_pairs, local_gpk.total_steps);
+        let mut sink = sink.lock().unwrap();
+        let (w, pending, error) = &mut *sink;
+        if error.is_none() {
+            *pending += 1;
+            let written = w.write_all(line.as_bytes()).and_then(|()| {
+                if *pending >= NDJSON_FLUSH_EVERY {
+                    *pending = 0;
+                    w.flush()
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = written {
+                *error = Some(e);
+            }
+        }
+    };
+    let result = verify_range_chunked(&config, &AtomicBool::new(false), &|_, _| {}, write_chunk);
+
+    let (mut w, _, error) = sink.into_inner().unwrap();
+    if let Some(e) = error {
+        return Err(e);
+    }
+    w.flush()?;
+
+    Ok(result)
+}
+
+/// 証明書をテキストで書き出す。1行目 `x start`、以降1行1ステップで `value d`（10進）。
+pub fn write_certificate(cert: &Certificate, path: &std::path::Path) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(w, "{} {}", cert.x, cert.start)?;
+    for (value, d) in &cert.steps {
+        writeln!(w, "{} {}", value, d)?;
+    }
+    w.flush()
+}
+
+/// VerifyResult を Parquet（Arrow 列指向）で書き出す（`arrow` feature）。
+/// 1回の検証を1行として書くので、多数の実行結果のファイルを
+/// pandas / polars でそのまま連結して集計できる。
+/// 列: total_checked, all_converged, max_stopping_time, max_stopping_time_number (文字列),
+/// failures (文字列リスト), cycle_hits, total_g, total_p, total_k, total_pairs,
+/// total_gpk_steps, carry_chain_hist (u64 リスト), d_hist (u64 リスト), likely_divergent (文字列リスト),
+/// max_intermediate_bits
+#[cfg(feature = "arrow")]
+pub fn write_verify_arrow(result: &VerifyResult, path: &std::path::Path) -> parquet::errors::Result<()> {
+    use arrow::array::{ArrayRef, BooleanArray, ListBuilder, StringArray, StringBuilder, UInt64Array, UInt64Builder};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let gs = &result.gpk_stats;
+    let u64_col = |v: u64| -> ArrayRef { Arc::new(UInt64Array::from(vec![v])) };
+
+    let mut failures = ListBuilder::new(StringBuilder::new());
+    for f in &result.failures {
+        failures.values().append_value(f.to_string());
+    }
+    failures.append(true);
+
+    let mut hist = ListBuilder::new(UInt64Builder::new());
+    hist.values().append_slice(&gs.carry_chain_hist);
+    hist.append(true);
+
+    let mut divergent = ListBuilder::new(StringBuilder::new());
+    for n in &result.likely_divergent {
+        divergent.values().append_value(n.to_string());
+    }
+    divergent.append(true);
+
+    let mut d_hist = ListBuilder::new(UInt64Builder::new());
+    d_hist.values().append_slice(&result.d_hist);
+    d_hist.append(true);
+
+    let batch = RecordBatch::try_from_iter(vec![
+        ("total_checked", u64_col(result.total_checked)),
+        ("all_converged", Arc::new(BooleanArray::from(vec![result.all_converged])) as ArrayRef),
+        ("max_stopping_time", u64_col(result.max_stopping_time)),
+        ("max_stopping_time_number", Arc::new(StringArray::from(vec![result.max_stopping_time_number.to_string()])) as ArrayRef),
+        ("failures", Arc::new(failures.finish()) as ArrayRef),
+        ("cycle_hits", u64_col(result.cycle_hits)),
+        ("total_g", u64_col(gs.total_g)),
+        ("total_p", u64_col(gs.total_p)),
+        ("total_k", u64_col(gs.total_k)),
+        ("total_pairs", u64_col(gs.total_pairs)),
+        ("total_gpk_steps", u64_col(gs.total_steps)),
+        ("carry_chain_hist", Arc::new(hist.finish()) as ArrayRef),
+        ("d_hist", Arc::new(d_hist.finish()) as ArrayRef),
+        ("likely_divergent", Arc::new(divergent.finish()) as ArrayRef),
+        ("max_intermediate_bits", u64_col(result.max_intermediate_bits)),
+    ])?;
+
+    let file = std::fs::File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 常に 1 を返す写像: n > 1 は全て停止時間 1
+    struct ToOne;
+    impl OddStep for ToOne {
+        fn step(&self, _n: &PairNumber) -> (PairNumber, u64) {
+            (PairNumber::from_biguint(&BigUint::one()), 0)
+        }
+    }
+
+    /// 恒等写像: どの数も停止しない
+    struct Identity;
+    impl OddStep for Identity {
+        fn step(&self, n: &PairNumber) -> (PairNumber, u64) {
+            (n.clone(), 0)
+        }
+    }
+
+    /// d ヒストグラムの加重和が全ステップの割り算回数の総和に一致する
+    #[test]
+    fn test_d_hist_matches_total_halvings() {
+        // n=1 の扱いは検証器ごとに異なる（0 ステップ / 1 ステップ）ので 3 から
+        let (start, end) = (3u64, 9999u64);
+        let mut total_halvings = 0u64;
+        let mut total_steps = 0u64;
+        for n in (start..=end).step_by(2) {
+            let mut cur = n;
+            while cur >= n {
+                let xn1 = 3 * cur + 1;
+                let d = xn1.trailing_zeros() as u64;
+                total_halvings += d;
+                total_steps += 1;
+                cur = xn1 >> d;
+            }
+        }
+
+        let check = |r: &VerifyResult, name: &str| {
+            let weighted: u64 = r.d_hist.iter().enumerate().map(|(d, &c)| d as u64 * c).sum();
+            assert_eq!(weighted, total_halvings, "{}", name);
+            assert_eq!(r.d_hist.iter().sum::<u64>(), total_steps, "{}", name);
+            assert_eq!(r.d_hist.first().copied().unwrap_or(0), 0, "{}: d=0 never occurs for odd n", name);
+        };
+        let (s, e) = (BigUint::from(start), BigUint::from(end));
+        check(&verify_range(&s, &e, 3, 10_000, |_, _| {}), "verify_range");
+        check(&verify_range_parallel(&s, &e, 3, 10_000, |_, _| {}), "verify_range_parallel");
+        check(&verify_range_inductive(&s, &e, 3, 10_000, None, |_, _| {}), "verify_range_inductive");
+        check(&verify_range_with(&XnPlusOne(3), &s, &e, 10_000, |_, _| {}), "verify_range_with");
     }
 
-    // 奇数の総数を概算
-    let range = if end >= &n {
-        end - &n
-    } else {
-        BigUint::ZERO
-    };
-    let total_estimate: u64 = (&range / &two).to_u64_digits().first().copied().unwrap_or(0) + 1;
+    #[test]
+    fn test_growth_limit_5n1() {
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(199u64);
+        let plain = verify_range(&start, &end, 5, 500, |_, _| {});
+        let none = verify_range_with_growth_limit(&start, &end, 5, 500, None, |_, _| {});
+        assert_eq!(none.failures, plain.failures);
+        assert!(none.likely_divergent.is_empty());
 
-    let mut total_checked = 0u64;
-    let mut max_stopping_time = 0u64;
-    let mut max_stopping_time_number = n.clone();
-    let mut failures: Vec<BigUint> = Vec::new();
-    let mut gpk_stats = GpkStats::new();
+        let r = verify_range_with_growth_limit(&start, &end, 5, 500, Some(40), |_, _| {});
+        assert_eq!(r.total_checked, plain.total_checked);
+        // 5n+1 の 7 は発散すると予想されている
+        assert!(r.likely_divergent.contains(&BigUint::from(7u64)));
+        assert!(!r.all_converged);
+        // 振り分けは plain の失敗の分割（収束する数は変わらない）
+        let mut both: Vec<BigUint> = r.failures.iter().chain(&r.likely_divergent).cloned().collect();
+        both.sort();
+        assert_eq!(both, plain.failures);
+        // サイクル {13, 33, 83} の 13 は成長しないので failures に残る
+        assert!(r.failures.contains(&BigUint::from(13u64)));
+        assert_eq!(r.max_stopping_time, plain.max_stopping_time);
+    }
 
-    while n <= *end {
-        match trajectory::stopping_time_with_gpk(&n, x, max_steps, Some(&mut gpk_stats), true) {
-            Some(st) => {
-                if st > max_stopping_time {
-                    max_stopping_time = st;
-                    max_stopping_time_number = n.clone();
-                }
+    #[test]
+    fn test_empty_range() {
+        let cancel = AtomicBool::new(false);
+        let big = BigUint::one() << 100u32;
+        for (s, e) in [(BigUint::from(100u64), BigUint::from(10u64)), (BigUint::from(10u64), BigUint::from(10u64)), (&big + 10u32, big.clone())] {
+            let results = [
+                verify_range(&s, &e, 3, 1000, |_, _| {}),
+                verify_range_with_cycles(&s, &e, 3, 1000, &[], |_, _| {}),
+                verify_range_parallel(&s, &e, 3, 1000, |_, _| {}),
+                verify_range_parallel_cancellable(&s, &e, 3, 1000, true, true, true, &cancel, |_, _| {}),
+                verify_range_inductive(&s, &e, 3, 1000, None, |_, _| {}),
+            ];
+            for r in &results {
+                assert_eq!(r.total_checked, 0, "[{}, {}]", s, e);
+                assert!(r.all_converged);
+                assert_eq!(r.max_stopping_time_number, BigUint::ZERO, "[{}, {}]", s, e);
             }
-            None => {
-                failures.push(n.clone());
+        }
+    }
+
+    #[test]
+    fn test_verify_range_spawn_snapshots() {
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(399_999u64);
+        let handle = verify_range_spawn(start.clone(), end.clone(), 3, 10_000, true, true, true);
+
+        let mut snapshots = Vec::new();
+        loop {
+            let snap = handle.snapshot();
+            let finished = snap.finished;
+            snapshots.push(snap);
+            if finished {
+                break;
             }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        for w in snapshots.windows(2) {
+            assert!(w[0].done <= w[1].done);
+            assert!(w[0].max_stopping_time <= w[1].max_stopping_time);
+            assert!(w[0].gpk_stats.total_steps <= w[1].gpk_stats.total_steps);
         }
+        let last = snapshots.last().unwrap().clone();
+        assert!(handle.is_finished());
 
-        total_checked += 1;
+        let r = handle.join();
+        let expected = verify_range_parallel_cancellable(&start, &end, 3, 10_000, true, true, true, &AtomicBool::new(false), |_, _| {});
+        assert_eq!(r.total_checked, 200_000);
+        assert_eq!(last.done, r.total_checked);
+        assert_eq!(last.total, r.total_checked);
+        assert_eq!(last.failure_count, 0);
+        assert_eq!((last.max_stopping_time, &last.max_stopping_time_number), (r.max_stopping_time, &r.max_stopping_time_number));
+        assert_eq!(last.gpk_stats.total_steps, r.gpk_stats.total_steps);
+        assert_eq!(r.max_stopping_time, expected.max_stopping_time);
+        assert_eq!(r.max_stopping_time_number, expected.max_stopping_time_number);
+        assert_eq!(r.gpk_stats.total_steps, expected.gpk_stats.total_steps);
+        assert_eq!(r.d_hist, expected.d_hist);
+        assert!(r.all_converged);
+    }
 
-        if total_checked % 1000 == 0 {
-            progress_callback(total_checked, total_estimate);
+    #[test]
+    fn test_verify_range_spawn_panic_finishes() {
+        // x = 0 は u128 の溢れ判定で 0 除算になり、ワーカーが panic する。
+        // それでも is_finished / snapshot().finished が立ち、ポーリングが止まらない
+        let handle = verify_range_spawn(BigUint::from(1u64), BigUint::from(99u64), 0, 100, false, true, true);
+        let began = std::time::Instant::now();
+        while !handle.snapshot().finished {
+            assert!(began.elapsed() < std::time::Duration::from_secs(10));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(handle.is_finished());
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handle.join())).is_err());
+    }
+
+    #[test]
+    fn test_verify_range_spawn_cancel_biguint() {
+        // BigUint 経路（シングルスレッド）: キャンセルすると途中結果が返る
+        let start = BigUint::one() << 80u32;
+        let end = &start + BigUint::from(100_000_000u64);
+        let handle = verify_range_spawn(start.clone(), end, 3, 10_000, true, true, true);
+        while handle.snapshot().done < 1000 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
         }
+        handle.cancel();
+        let snap = handle.snapshot();
+        assert_eq!(snap.total, 50_000_000);
+        assert!(snap.gpk_stats.total_steps > 0);
+        let r = handle.join();
+        assert!(r.total_checked >= snap.done && r.total_checked < 50_000_000);
+        assert!(r.max_stopping_time >= snap.max_stopping_time);
+        assert!(r.max_stopping_time_number > start);
+    }
 
-        n += &two;
+    #[test]
+    fn test_cancel_interrupts_long_orbit() {
+        // 5n+1 の 13 は 13→33→83→13 のサイクルで止まらない。max_steps が実質無限でも中断が効く
+        let seed = BigUint::from(13u64);
+        let cancel = AtomicBool::new(false);
+        let began = std::time::Instant::now();
+        let r = std::thread::scope(|s| {
+            let worker = s.spawn(|| verify_range_parallel_cancellable(&seed, &seed, 5, u64::MAX, true, true, true, &cancel, |_, _| {}));
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            cancel.store(true, Ordering::Relaxed);
+            worker.join().unwrap()
+        });
+        assert!(began.elapsed() < std::time::Duration::from_secs(5));
+        // 中断されたシードは失敗にも処理済みにも統計にも入らない
+        assert_eq!(r.total_checked, 0);
+        assert!(r.failures.is_empty());
+        assert_eq!(r.gpk_stats.total_steps, 0);
+        assert!(r.d_hist.iter().all(|&c| c == 0));
+        assert_eq!((r.reached_one_count, r.dropped_below_count), (0, 0));
     }
 
-    progress_callback(total_checked, total_estimate);
+    #[test]
+    fn test_try_verify_range_cancel_huge_biguint_range() {
+        // 2^60 個の奇数を含む BigUint 範囲でも、チャンクを作りながら流すので中断すればすぐ返る
+        let start = (BigUint::one() << 80u32) + 1u32;
+        let end = &start + (BigUint::one() << 61u32);
+        let config = VerifyConfig::new(start.clone(), end, 3, 10_000);
+
+        let r = try_verify_range_with_progress(&config, &AtomicBool::new(true), |_, _| {}).unwrap();
+        assert_eq!(r.total_checked, 0);
+        assert_eq!(r.strategy, Strategy::ParallelBigUint);
 
-    VerifyResult {
-        total_checked,
-        all_converged: failures.is_empty(),
-        max_stopping_time,
-        max_stopping_time_number,
-        failures,
-        gpk_stats,
+        let cancel = AtomicBool::new(false);
+        let seen = AtomicU64::new(0);
+        let began = std::time::Instant::now();
+        let r = std::thread::scope(|s| {
+            let worker = s.spawn(|| {
+                try_verify_range_with_progress(&config, &cancel, |done, total| {
+                    assert_eq!(total, (1u64 << 60) + 1);
+                    seen.store(done, Ordering::Relaxed);
+                })
+                .unwrap()
+            });
+            while seen.load(Ordering::Relaxed) == 0 {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            cancel.store(true, Ordering::Relaxed);
+            worker.join().unwrap()
+        });
+        assert!(began.elapsed() < std::time::Duration::from_secs(10));
+        assert!(r.total_checked >= seen.load(Ordering::Relaxed));
+        assert!(r.max_stopping_time_number >= start);
     }
-}
 
-/// [start, end] の全奇数を停止時間法で検証する（並列版）。
-/// Rayon でチャンク分割して並列処理。
-/// progress_callback: (完了数, 総数) を定期的に呼ぶ（スレッドセーフ）。
-pub fn verify_range_parallel(
-    start: &BigUint,
-    end: &BigUint,
-    x: u64,
-    max_steps: u64,
-    progress_callback: impl Fn(u64, u64) + Sync,
-) -> VerifyResult {
-    let two = BigUint::from(2u64);
-    let one = BigUint::one();
+    #[test]
+    fn test_verify_range_small_matches_parallel() {
+        let start = BigUint::from(3u64);
+        let end = BigUint::from(1_000_000u64);
+        let small = verify_range_small(&start, &end, 3, 10_000);
+        let general = verify_range_parallel(&start, &end, 3, 10_000, |_, _| {});
+        assert_eq!(small.total_checked, general.total_checked);
+        assert!(small.all_converged && general.all_converged);
+        assert_eq!(small.max_stopping_time, general.max_stopping_time);
+        assert_eq!(trajectory::stopping_time(&small.max_stopping_time_number, 3, 10_000), Some(small.max_stopping_time));
+        assert_eq!(small.d_hist, general.d_hist);
 
-    // start を奇数に調整
-    let mut adj_start = start.clone();
-    if &adj_start % &two == BigUint::ZERO {
-        adj_start += &one;
+        // start > end は start が u64 に収まらなくても空範囲
+        let (s, e) = ((BigUint::one() << 64u32) + 3u32, BigUint::from(1_001u64));
+        let small = verify_range_small(&s, &e, 3, 10_000);
+        let general = verify_range_parallel(&s, &e, 3, 10_000, |_, _| {});
+        assert_eq!(small.total_checked, 0);
+        assert_eq!(small.total_checked, general.total_checked);
+
+        // 5n+1: 収束しない数（サイクル・発散）も一般経路と一致する
+        let (s, e) = (BigUint::from(1u64), BigUint::from(2_001u64));
+        let small = verify_range_small(&s, &e, 5, 300);
+        let general = verify_range_parallel(&s, &e, 5, 300, |_, _| {});
+        let mut general_failures = general.failures.clone();
+        general_failures.sort();
+        assert_eq!(small.total_checked, general.total_checked);
+        assert_eq!(small.failures, general_failures);
+        assert_eq!(small.max_stopping_time, general.max_stopping_time);
+        assert_eq!(small.d_hist, general.d_hist);
     }
 
-    // u64 に収まる範囲なら高速パスを使う
-    let start_u64 = adj_start.to_u64_digits();
-    let end_u64 = end.to_u64_digits();
+    #[test]
+    fn test_verify_range_small_overflow_lane_and_defer() {
+        // 専用パスで扱う最大のシード付近
+        let start = BigUint::from(SMALL_RANGE_LIMIT - 20_001);
+        let end = BigUint::from(SMALL_RANGE_LIMIT - 1);
+        let small = verify_range_small(&start, &end, 3, 100_000);
+        let general = verify_range_parallel(&start, &end, 3, 100_000, |_, _| {});
+        assert_eq!(small.total_checked, 10_001);
+        assert_eq!(small.max_stopping_time, general.max_stopping_time);
+        assert_eq!(small.d_hist, general.d_hist);
 
-    if start_u64.len() <= 1 && end_u64.len() <= 1 {
-        let s = start_u64.first().copied().unwrap_or(1);
-        let e = end_u64.first().copied().unwrap_or(0);
-        return verify_range_parallel_u64(s, e, x, max_steps, true, true, &progress_callback);
+        // x=129 は 1 ステップで u64 を超えやすい: u128 / パックド経路への引き継ぎ
+        let (s, e) = (BigUint::from(1u64), BigUint::from(401u64));
+        let small = verify_range_small(&s, &e, 129, 200);
+        let general = verify_range_parallel(&s, &e, 129, 200, |_, _| {});
+        let mut general_failures = general.failures.clone();
+        general_failures.sort();
+        assert_eq!(small.failures, general_failures);
+        assert_eq!(small.max_stopping_time, general.max_stopping_time);
+        assert_eq!(small.d_hist, general.d_hist);
+
+        // 上限以上を含む範囲は一般経路に委ねる（GPK も集計される）
+        let (s, e) = (BigUint::from(SMALL_RANGE_LIMIT - 99), BigUint::from(SMALL_RANGE_LIMIT + 99));
+        let deferred = verify_range_small(&s, &e, 3, 10_000);
+        assert_eq!(deferred.total_checked, 100);
+        assert!(deferred.gpk_stats.total_steps > 0);
     }
 
-    // BigUint の場合はシングルスレッド版にフォールバック
-    verify_range(&adj_start, end, x, max_steps, progress_callback)
-}
+    #[test]
+    fn test_estimate_throughput() {
+        let big = BigUint::one() << 100u32;
+        for (start, collect_gpk) in [(BigUint::from(3u64), false), (BigUint::from(1_000_000u64), true), (big, false)] {
+            let config = VerifyConfig { collect_gpk, ..VerifyConfig::new(start.clone(), &start + 1_000_000u32, 3, 10_000) };
+            let t = estimate_throughput(&config, 500);
+            assert!(t.is_finite() && t > 0.0, "start={} t={}", start, t);
+        }
+        let config = VerifyConfig::new(BigUint::from(27u64), BigUint::from(99u64), 3, 10_000);
+        assert!(estimate_throughput(&config, 0) > 0.0);
 
-/// u64 範囲の並列検証（高速パス）
-fn verify_range_parallel_u64(
-    start: u64,
-    end: u64,
-    x: u64,
-    max_steps: u64,
-    use_phase1: bool,
-    use_stopping_time: bool,
-    progress_callback: &(impl Fn(u64, u64) + Sync),
-) -> VerifyResult {
-    // start を奇数に調整
-    let start = if start % 2 == 0 { start + 1 } else { start };
-    if start > end {
-        return VerifyResult {
-            total_checked: 0,
-            all_converged: true,
-            max_stopping_time: 0,
-            max_stopping_time_number: BigUint::ZERO,
-            failures: Vec::new(),
-            gpk_stats: GpkStats::new(),
-        };
+        // 1 まで追跡する設定（use_stopping_time = false）は1個あたりのステップ数が桁で多いので遅く見積もる
+        let stopping = VerifyConfig { collect_gpk: false, ..VerifyConfig::new(BigUint::from(1_000_001u64), BigUint::from(2_000_001u64), 3, 10_000) };
+        let total = VerifyConfig { use_stopping_time: false, ..stopping.clone() };
+        assert!(estimate_throughput(&stopping, 5_000) > 2.0 * estimate_throughput(&total, 5_000));
     }
 
-    let total_odd = (end - start) / 2 + 1;
+    #[test]
+    fn test_try_verify_range_strategy() {
+        // u64 範囲
+        let r = try_verify_range(&VerifyConfig::new(BigUint::from(1u64), BigUint::from(9_999u64), 3, 10_000)).unwrap();
+        assert_eq!(r.strategy, Strategy::ParallelU64);
+        assert_eq!(r.total_checked, 5_000);
 
-    // チャンク分割: 各チャンク10000個の奇数
-    let chunk_size: u64 = 10000;
-    let num_chunks = (total_odd + chunk_size - 1) / chunk_size;
+        // 2^64 を超える範囲はチャンク並列の BigUint 経路、結果は単一スレッド版と一致する
+        let start = (BigUint::one() << 64u32) + 1u32;
+        let end = &start + BigUint::from(40_000u64);
+        let r = try_verify_range(&VerifyConfig::new(start.clone(), end.clone(), 3, 10_000)).unwrap();
+        let single = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        assert_eq!(r.strategy, Strategy::ParallelBigUint);
+        assert_eq!(single.strategy, Strategy::SingleThread);
+        assert_eq!(r.total_checked, single.total_checked);
+        assert_eq!(r.max_stopping_time, single.max_stopping_time);
+        assert_eq!(r.max_stopping_time_number, single.max_stopping_time_number);
+        assert_eq!(r.gpk_stats.total_steps, single.gpk_stats.total_steps);
+        assert_eq!(r.d_hist, single.d_hist);
+        // 既存の verify_range_parallel はこの範囲では1スレッドにフォールバックし、それが見える
+        assert_eq!(verify_range_parallel(&start, &(&start + 100u32), 3, 10_000, |_, _| {}).strategy, Strategy::SingleThread);
 
-    let global_done = AtomicU64::new(0);
-    let global_max_st = AtomicU64::new(0);
-    let global_max_st_n = Mutex::new(start);
-    let global_failures: Mutex<Vec<BigUint>> = Mutex::new(Vec::new());
-    let global_gpk_stats: Mutex<GpkStats> = Mutex::new(GpkStats::new());
+        // 空範囲は Ok
+        let big = BigUint::one() << 100u32;
+        let r = try_verify_range(&VerifyConfig::new(&big + 10u32, big, 3, 10_000)).unwrap();
+        assert_eq!(r.total_checked, 0);
 
-    (0..num_chunks).into_par_iter().for_each(|chunk_idx| {
-        let chunk_start = start + chunk_idx * chunk_size * 2;
-        let chunk_end = std::cmp::min(chunk_start + (chunk_size - 1) * 2, end);
+        // 設定の誤り
+        for x in [0u64, 1, 2, 4, 7, 11] {
+            let config = VerifyConfig::new(BigUint::from(1u64), BigUint::from(99u64), x, 100);
+            assert_eq!(try_verify_range(&config).unwrap_err(), VerifyError::InvalidMultiplier(x));
+        }
+        let config = VerifyConfig::new(BigUint::from(1u64), BigUint::from(99u64), 3, 0);
+        assert_eq!(try_verify_range(&config).unwrap_err(), VerifyError::ZeroMaxSteps);
+    }
 
-        let mut local_max_st = 0u64;
-        let mut local_max_st_n = chunk_start;
-        let mut local_failures: Vec<BigUint> = Vec::new();
-        let mut unreported = 0u64;
-        let mut local_gpk = GpkStats::new();
+    #[test]
+    fn test_glide_and_total_records() {
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(999u64);
+        let r = verify_range_glide_and_total(&start, &end, 3, 10_000, |_, _| {});
 
-        let mut n = chunk_start;
-        while n <= chunk_end {
-            match trajectory::stopping_time_u64_fast(n, x, max_steps, Some(&mut local_gpk), use_phase1, use_stopping_time) {
-                Some(st) => {
-                    if st > local_max_st {
-                        local_max_st = st;
-                        local_max_st_n = n;
-                    }
+        // 素朴な u64 計算（奇数→奇数ステップ数）で記録を求める
+        let mut best_glide = (0u64, 0u64);
+        let mut best_total = (0u64, 0u64);
+        for n in (1u64..=999).step_by(2) {
+            let (mut cur, mut steps, mut glide) = (n, 0u64, if n == 1 { Some(0) } else { None });
+            while cur != 1 {
+                let xn1 = 3 * cur + 1;
+                cur = xn1 >> xn1.trailing_zeros();
+                steps += 1;
+                if glide.is_none() && cur < n {
+                    glide = Some(steps);
                 }
-                None => {
-                    local_failures.push(BigUint::from(n));
-                }
-            }
-            unreported += 1;
-            n += 2;
-
-            // チャンク内でも定期的に進捗報告
-            if unreported >= 100 {
-                let done = global_done.fetch_add(unreported, Ordering::Relaxed) + unreported;
-                progress_callback(done, total_odd);
-                unreported = 0;
             }
+            let g = glide.unwrap();
+            if g > best_glide.0 { best_glide = (g, n); }
+            if steps > best_total.0 { best_total = (steps, n); }
         }
 
-        // 残りをグローバルに反映
-        if unreported > 0 {
-            let done = global_done.fetch_add(unreported, Ordering::Relaxed) + unreported;
-            progress_callback(done, total_odd);
-        }
+        assert_eq!(r.total_checked, 500);
+        assert!(r.all_converged);
+        assert_eq!((r.max_stopping_time, r.max_stopping_time_number.clone()), (best_glide.0, BigUint::from(best_glide.1)));
+        assert_eq!(r.max_total_stopping_time, Some((best_total.0, BigUint::from(best_total.1))));
+        // 1000 未満の全停止時間の記録は 871（全 178 ステップ、うち奇数ステップ 65）
+        assert_eq!(r.max_total_stopping_time, Some((65, BigUint::from(871u64))));
+
+        // glide の記録は通常の検証と一致する
+        let plain = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        assert_eq!(r.max_stopping_time, plain.max_stopping_time);
+        assert_eq!(r.max_stopping_time_number, plain.max_stopping_time_number);
+        assert_eq!(plain.max_total_stopping_time, None);
+    }
+
+    #[test]
+    fn test_convergence_certificate() {
+        let n = BigUint::from(27u64);
+        let cert = convergence_certificate(&n, 3, 10_000).unwrap();
+        assert!(verify_certificate(&cert, 3));
+        assert!(!verify_certificate(&cert, 5));
+        assert!(*cert.steps.last().map(|(v, _)| v).unwrap() < n);
 
-        // 最大停止時間を更新
-        let prev_max = global_max_st.load(Ordering::Relaxed);
-        if local_max_st > prev_max {
-            global_max_st.fetch_max(local_max_st, Ordering::Relaxed);
-            let mut guard = global_max_st_n.lock().unwrap();
-            if local_max_st >= global_max_st.load(Ordering::Relaxed) {
-                *guard = local_max_st_n;
+        let big = (BigUint::one() << 200u32) + BigUint::from(27u64);
+        let cert_big = convergence_certificate(&big, 3, 10_000).unwrap();
+        assert!(verify_certificate(&cert_big, 3));
+
+        // 値の改竄
+        let mut tampered = cert.clone();
+        tampered.steps[3].0 += 2u32;
+        assert!(!verify_certificate(&tampered, 3));
+        // d の改竄
+        let mut tampered = cert.clone();
+        tampered.steps[0].1 += 1;
+        assert!(!verify_certificate(&tampered, 3));
+        // 途中で打ち切り（start 未満に届かない）
+        let mut truncated = cert.clone();
+        truncated.steps.truncate(2);
+        assert!(!verify_certificate(&truncated, 3));
+
+        // 5n+1 のサイクル {13, 33, 83} は停止しない
+        assert!(convergence_certificate(&BigUint::from(13u64), 5, 1000).is_none());
+        // 偶数は対象外
+        assert!(convergence_certificate(&BigUint::from(28u64), 3, 1000).is_none());
+    }
+
+    #[test]
+    fn test_verify_range_with_custom_stepper() {
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(30_001u64);
+
+        let r = verify_range_with(&ToOne, &start, &end, 10, |_, _| {});
+        assert_eq!(r.total_checked, 15_001);
+        assert!(r.all_converged);
+        assert_eq!(r.max_stopping_time, 1);
+        assert_eq!(r.max_stopping_time_number, BigUint::from(1u64));
+
+        let stepper: &(dyn OddStep + Sync) = &Identity;
+        let r = verify_range_with(stepper, &BigUint::from(101u64), &BigUint::from(121u64), 5, |_, _| {});
+        assert_eq!(r.total_checked, 11);
+        let expected: Vec<BigUint> = (101u64..=121).step_by(2).map(BigUint::from).collect();
+        assert_eq!(r.failures, expected);
+    }
+
+    #[test]
+    fn test_verify_range_with_builtin_matches() {
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(4_999u64);
+        let builtin = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        let with = verify_range_with(&XnPlusOne(3), &start, &end, 10_000, |_, _| {});
+        assert_eq!(with.total_checked, builtin.total_checked);
+        assert_eq!(with.max_stopping_time, builtin.max_stopping_time);
+        assert_eq!(with.max_stopping_time_number, builtin.max_stopping_time_number);
+        assert!(with.all_converged);
+    }
+
+    /// チャンク先頭と個数が範囲内の奇数をちょうど一度ずつ被覆する
+    #[test]
+    fn test_chunker_covers_range() {
+        for (start, end, chunk) in [(1u64, 1u64, 1u64), (1, 99, 10), (2, 100, 7), (3, 3, 5), (5, 4, 3), (1, 1001, 1)] {
+            let chunks: Vec<(BigUint, u64)> =
+                BigUintRangeChunker::new(&BigUint::from(start), &BigUint::from(end), chunk).collect();
+            let mut covered = Vec::new();
+            for (base, count) in &chunks {
+                assert!(*count >= 1 && *count <= chunk);
+                let b = base.to_u64_digits().first().copied().unwrap_or(0);
+                for j in 0..*count {
+                    covered.push(b + 2 * j);
+                }
             }
+            let s = if start % 2 == 0 { start + 1 } else { start };
+            let expected: Vec<u64> = (s..=end).step_by(2).collect();
+            assert_eq!(covered, expected, "coverage mismatch for [{}, {}] chunk={}", start, end, chunk);
         }
+    }
 
-        if !local_failures.is_empty() {
-            global_failures.lock().unwrap().extend(local_failures);
-        }
+    #[test]
+    fn test_known_cycles_5n1() {
+        let cycles = vec![vec![1u64], vec![13, 33, 83], vec![17, 43, 27]];
+        let start = BigUint::from(1u64);
+        let end = BigUint::from(99u64);
 
-        global_gpk_stats.lock().unwrap().merge(&local_gpk);
-    });
+        let plain = verify_range(&start, &end, 5, 1000, |_, _| {});
+        let with_cycles = verify_range_with_cycles(&start, &end, 5, 1000, &cycles, |_, _| {});
+
+        assert_eq!(with_cycles.total_checked, plain.total_checked);
+        // 13 と 17 はサイクル自体の最小元なので停止時間法では停止しない
+        assert!(plain.failures.contains(&BigUint::from(13u64)));
+        assert!(plain.failures.contains(&BigUint::from(17u64)));
+        assert!(!with_cycles.failures.contains(&BigUint::from(13u64)));
+        assert!(!with_cycles.failures.contains(&BigUint::from(17u64)));
+        assert!(with_cycles.cycle_hits >= 2);
+        assert_eq!(
+            with_cycles.failures.len() as u64 + with_cycles.cycle_hits,
+            plain.failures.len() as u64
+        );
+    }
 
-    let total_checked = global_done.load(Ordering::Relaxed);
-    let max_stopping_time = global_max_st.load(Ordering::Relaxed);
-    let max_stopping_time_number = BigUint::from(*global_max_st_n.lock().unwrap());
-    let failures = global_failures.into_inner().unwrap();
-    let gpk_stats = global_gpk_stats.into_inner().unwrap();
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_write_verify_arrow_roundtrip() {
+        use arrow::array::{Array, BooleanArray, ListArray, StringArray, UInt64Array};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 
-    VerifyResult {
-        total_checked,
-        all_converged: failures.is_empty(),
-        max_stopping_time,
-        max_stopping_time_number,
-        failures,
-        gpk_stats,
+        let result = verify_range(&BigUint::from(1u64), &BigUint::from(999u64), 3, 10_000, |_, _| {});
+        let path = std::env::temp_dir().join(format!("collatz_verify_{}.parquet", std::process::id()));
+        write_verify_arrow(&result, &path).unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(batch.num_rows(), 1);
+
+        let col_u64 = |name: &str| -> u64 {
+            batch.column_by_name(name).unwrap().as_any().downcast_ref::<UInt64Array>().unwrap().value(0)
+        };
+        assert_eq!(col_u64("total_checked"), result.total_checked);
+        assert_eq!(col_u64("max_stopping_time"), result.max_stopping_time);
+        assert_eq!(col_u64("total_g"), result.gpk_stats.total_g);
+        assert_eq!(col_u64("total_gpk_steps"), result.gpk_stats.total_steps);
+        assert_eq!(col_u64("max_intermediate_bits"), result.max_intermediate_bits);
+        let converged = batch.column_by_name("all_converged").unwrap().as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(converged.value(0), result.all_converged);
+        let number = batch.column_by_name("max_stopping_time_number").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(number.value(0), result.max_stopping_time_number.to_string());
+        let hist = batch.column_by_name("carry_chain_hist").unwrap().as_any().downcast_ref::<ListArray>().unwrap();
+        let hist_values = hist.value(0);
+        let hist_values = hist_values.as_any().downcast_ref::<UInt64Array>().unwrap();
+        assert_eq!(hist_values.values().to_vec(), result.gpk_stats.carry_chain_hist.to_vec());
     }
-}
 
-/// キャンセル可能な並列検証。cancel が true になると途中結果を返す。
-/// collect_gpk が false なら GPK 統計の収集をスキップして高速化。
-pub fn verify_range_parallel_cancellable(
-    start: &BigUint,
-    end: &BigUint,
-    x: u64,
-    max_steps: u64,
-    collect_gpk: bool,
-    use_phase1: bool,
-    use_stopping_time: bool,
-    cancel: &AtomicBool,
-    progress_callback: impl Fn(u64, u64) + Sync,
-) -> VerifyResult {
-    let two = BigUint::from(2u64);
-    let one = BigUint::one();
+    #[test]
+    fn test_inductive_matches_stopping_time() {
+        let start = BigUint::from(1001u64);
+        let end = BigUint::from(30001u64);
+        let plain = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        let own = verify_range_inductive(&start, &end, 3, 10_000, None, |_, _| {});
+        assert_eq!(own.total_checked, plain.total_checked);
+        assert_eq!(own.all_converged, plain.all_converged);
+        assert_eq!(own.max_stopping_time, plain.max_stopping_time);
+        assert_eq!(own.max_stopping_time_number, plain.max_stopping_time_number);
+        assert_eq!(own.gpk_stats.total_steps, plain.gpk_stats.total_steps);
 
-    let mut adj_start = start.clone();
-    if &adj_start % &two == BigUint::ZERO {
-        adj_start += &one;
+        // 下限 = 範囲の開始: 全シードが収束し、各シードの停止時間は自身基準以上
+        let bounded = verify_range_inductive(&start, &end, 3, 10_000, Some(1001), |_, _| {});
+        assert_eq!(bounded.total_checked, plain.total_checked);
+        assert!(bounded.all_converged);
+        assert!(bounded.max_stopping_time >= plain.max_stopping_time);
+        assert!(bounded.gpk_stats.total_steps >= plain.gpk_stats.total_steps);
+        // 範囲先頭のシードは両者で同じ停止時間
+        assert_eq!(
+            trajectory::stopping_time_below(&start, 3, 10_000, &start, None),
+            trajectory::stopping_time(&start, 3, 10_000)
+        );
     }
 
-    let start_u64 = adj_start.to_u64_digits();
-    let end_u64 = end.to_u64_digits();
+    /// 下限を範囲の途中に置くと、下限未満のシードは即座に、残りは下限を割った時点で打ち切られ、
+    /// 収束判定は下限なしの検証と一致する
+    #[test]
+    fn test_inductive_shortcut_fires() {
+        let start = BigUint::from(1001u64);
+        let end = BigUint::from(30001u64);
+        let plain = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        let shortcut = verify_range_inductive(&start, &end, 3, 10_000, Some(15_001), |_, _| {});
+        assert_eq!(shortcut.total_checked, 14_501);
+        // 下限を割らずに 1 に着くのは 3n+1 = 2^16 の 21845 だけ
+        assert_eq!(shortcut.reached_one_count, 1);
+        assert_eq!(shortcut.dropped_below_count, 14_500);
+        assert_eq!(shortcut.total_checked, plain.total_checked);
+        assert_eq!(shortcut.all_converged, plain.all_converged);
+        assert_eq!(shortcut.failures, plain.failures);
+        assert!(shortcut.gpk_stats.total_steps < plain.gpk_stats.total_steps);
+    }
 
-    if start_u64.len() <= 1 && end_u64.len() <= 1 {
-        let s = start_u64.first().copied().unwrap_or(1);
-        let e = end_u64.first().copied().unwrap_or(0);
-        return verify_range_parallel_u64_cancellable(s, e, x, max_steps, collect_gpk, use_phase1, use_stopping_time, cancel, &progress_callback);
+    #[test]
+    fn test_chunker_large_base() {
+        let start = (BigUint::one() << 100u32) + 1u32;
+        let end = &start + 20u32;
+        let chunks: Vec<(BigUint, u64)> = BigUintRangeChunker::new(&start, &end, 4).collect();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], (start.clone(), 4));
+        assert_eq!(chunks[1], (&start + 8u32, 4));
+        assert_eq!(chunks[2], (&start + 16u32, 3));
     }
 
-    // BigUint: シングルスレッド（キャンセル対応）
-    let total_estimate: u64 = {
-        let range = if end >= &adj_start { end - &adj_start } else { BigUint::ZERO };
-        (&range / &two).to_u64_digits().first().copied().unwrap_or(0) + 1
-    };
+    /// VerifyConfig::report_every ごとに progress_callback が呼ばれる回数
+    #[test]
+    fn test_report_every_callback_count() {
+        let count_calls_with = |start: &BigUint, end: &BigUint, report_every: u64, force_single_thread: bool| {
+            let calls = AtomicU64::new(0);
+            let max_done = AtomicU64::new(0);
+            let config = VerifyConfig {
+                collect_gpk: false,
+                report_every,
+                force_single_thread,
+                ..VerifyConfig::new(start.clone(), end.clone(), 3, 10_000)
+            };
+            let r = try_verify_range_with_progress(&config, &AtomicBool::new(false), |done, _| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                max_done.fetch_max(done, Ordering::Relaxed);
+            })
+            .unwrap();
+            assert_eq!(max_done.load(Ordering::Relaxed), r.total_checked);
+            calls.load(Ordering::Relaxed)
+        };
+        let count_calls = |start: &BigUint, end: &BigUint, report_every: u64| count_calls_with(start, end, report_every, false);
 
-    let mut n = adj_start;
-    let mut total_checked = 0u64;
-    let mut max_stopping_time = 0u64;
-    let mut max_stopping_time_number = n.clone();
-    let mut failures: Vec<BigUint> = Vec::new();
-    let mut gpk_stats = GpkStats::new();
+        // u64 経路: 20000 奇数 = 10000 個のチャンク2つ、チャンクごとに数えて端数でも1回
+        let (s, e) = (BigUint::from(1u64), BigUint::from(39_999u64));
+        assert_eq!(count_calls(&s, &e, REPORT_EVERY_PARALLEL), 200);
+        assert_eq!(count_calls(&s, &e, 1000), 20);
+        assert_eq!(count_calls(&s, &e, 3000), 2 * 4);
+        assert_eq!(count_calls(&s, &e, 20_000), 2);
 
-    while n <= *end {
-        if cancel.load(Ordering::Relaxed) {
-            break;
-        }
-        let gpk_arg = if collect_gpk { Some(&mut gpk_stats) } else { None };
-        match trajectory::stopping_time_with_gpk(&n, x, max_steps, gpk_arg, use_stopping_time) {
-            Some(st) => {
-                if st > max_stopping_time {
-                    max_stopping_time = st;
-                    max_stopping_time_number = n.clone();
+        // BigUint 経路: 2500 奇数 = 1 チャンク、u64 経路と同じくチャンクごとに数えて端数でも1回
+        let s = (BigUint::one() << 64u32) + 1u32;
+        let e = &s + 4998u32;
+        assert_eq!(count_calls(&s, &e, 1000), 3);
+        assert_eq!(count_calls(&s, &e, 500), 5);
+        assert_eq!(count_calls(&s, &e, 0), count_calls(&s, &e, 1));
+
+        // 1 スレッド経路: 通算で数え、最後にもう1回
+        assert_eq!(count_calls_with(&s, &e, 500, true), 6);
+
+        // 既定値は REPORT_EVERY_PARALLEL
+        let calls = AtomicU64::new(0);
+        let (s64, e64) = (BigUint::from(1u64), BigUint::from(39_999u64));
+        verify_range_parallel(&s64, &e64, 3, 10_000, |_, _| { calls.fetch_add(1, Ordering::Relaxed); });
+        assert_eq!(calls.load(Ordering::Relaxed), 200);
+
+        // シングルスレッド版は REPORT_EVERY_SEQUENTIAL 固定
+        let calls = AtomicU64::new(0);
+        verify_range(&s, &e, 3, 10_000, |_, _| { calls.fetch_add(1, Ordering::Relaxed); });
+        assert_eq!(calls.load(Ordering::Relaxed), 2500 / REPORT_EVERY_SEQUENTIAL + 1);
+    }
+
+    /// max_intermediate_bits: 5n+1 で軌道が伸びる範囲で、全経路が BigUint の素朴な追跡と一致する
+    #[test]
+    fn test_max_intermediate_bits_5n1() {
+        let (start, end, max_steps) = (3u64, 199u64, 600u64);
+        let x = BigUint::from(5u64);
+        let mut expected = 0u64;
+        for seed in (start..=end).step_by(2) {
+            let seed = BigUint::from(seed);
+            let mut n = seed.clone();
+            expected = expected.max(n.bits());
+            for _ in 0..max_steps {
+                let xn1 = &n * &x + 1u32;
+                n = &xn1 >> xn1.trailing_zeros().unwrap();
+                expected = expected.max(n.bits());
+                if n.is_one() || n < seed {
+                    break;
                 }
             }
-            None => {
-                failures.push(n.clone());
-            }
         }
-        total_checked += 1;
-        if total_checked % 1000 == 0 {
-            progress_callback(total_checked, total_estimate);
+        // 7 などは 5n+1 で発散するので、u128 / U256 を超えてパックド経路まで伸びる
+        assert!(expected > 256, "expected = {}", expected);
+
+        let (s, e) = (BigUint::from(start), BigUint::from(end));
+        let cancel = AtomicBool::new(false);
+        let results = [
+            ("verify_range", verify_range(&s, &e, 5, max_steps, |_, _| {})),
+            ("verify_range_parallel", verify_range_parallel(&s, &e, 5, max_steps, |_, _| {})),
+            ("no phase1", verify_range_parallel_cancellable(&s, &e, 5, max_steps, false, false, true, &cancel, |_, _| {})),
+            ("verify_range_small", verify_range_small(&s, &e, 5, max_steps)),
+            ("verify_range_inductive", verify_range_inductive(&s, &e, 5, max_steps, None, |_, _| {})),
+            ("verify_range_with", verify_range_with(&XnPlusOne(5), &s, &e, max_steps, |_, _| {})),
+            ("try_verify_range", try_verify_range(&VerifyConfig::new(s.clone(), e.clone(), 5, max_steps)).unwrap()),
+            ("verify_range_spawn", verify_range_spawn(s.clone(), e.clone(), 5, max_steps, false, true, true).join()),
+        ];
+        for (name, r) in &results {
+            assert!(!r.all_converged, "{}", name);
+            assert_eq!(r.max_intermediate_bits, expected, "{}", name);
         }
-        n += &two;
+
+        // 3n+1: 軌道上の奇数の最大値で決まる
+        let r = verify_range_parallel(&BigUint::from(1u64), &BigUint::from(31u64), 3, 10_000, |_, _| {});
+        assert_eq!(r.max_intermediate_bits, BigUint::from(3077u64).bits()); // 27 の軌道の最大奇数 3077 (3077*3+1 = 9232)
+        assert_eq!(VerifyResult::empty().max_intermediate_bits, 0);
     }
 
-    progress_callback(total_checked, total_estimate);
+    /// verify_seeds を各シードの stopping_time_u64_fast の集計と照合
+    #[test]
+    fn test_verify_seeds_matches_individual() {
+        let mut next = crate::reference::test_rng(0x9E37_79B9_7F4A_7C15u64);
+        let mut seeds: Vec<u64> = vec![27, 703, 871, 77_671, 837_799, 63_728_127, 1, 2, 0, 27];
+        for _ in 0..4000 {
+            seeds.push(next() >> 20);
+        }
+        for x in [3u64, 5] {
+            let r = verify_seeds(&seeds, x, 500, true);
+
+            let mut total = 0u64;
+            let mut max_st = 0u64;
+            let mut max_n: Option<u64> = None;
+            let mut failures: Vec<BigUint> = Vec::new();
+            let mut gpk = GpkStats::new();
+            for &n in seeds.iter().filter(|&&n| n % 2 == 1) {
+                total += 1;
+                match trajectory::stopping_time_u64_fast(n, x, 500, Some(&mut gpk), true, true) {
+                    Some(st) => {
+                        if max_n.is_none() || st > max_st || (st == max_st && Some(n) < max_n) {
+                            max_st = st;
+                            max_n = Some(n);
+                        }
+                    }
+                    None => failures.push(BigUint::from(n)),
+                }
+            }
+            failures.sort();
+
+            assert_eq!(r.total_checked, total);
+            assert_eq!(r.max_stopping_time, max_st);
+            assert_eq!(r.max_stopping_time_number, BigUint::from(max_n.unwrap()));
+            assert_eq!(r.failures, failures);
+            assert_eq!(r.all_converged, failures.is_empty());
+            assert_eq!(r.gpk_stats.total_steps, gpk.total_steps);
+            assert_eq!(r.gpk_stats.total_g, gpk.total_g);
+            assert_eq!(r.gpk_stats.total_pairs, gpk.total_pairs);
+            assert_eq!(r.gpk_stats.carry_chain_hist, gpk.carry_chain_hist);
+        }
+        // チャンクをまたぐ
+        let seeds: Vec<u64> = (1..60_000u64).rev().collect();
+        let r = verify_seeds(&seeds, 3, 10_000, false);
+        assert_eq!(r.total_checked, 30_000);
+        assert!(r.all_converged);
+        let range = verify_range_parallel(&BigUint::one(), &BigUint::from(59_999u64), 3, 10_000, |_, _| {});
+        assert_eq!(r.total_checked, range.total_checked);
+        assert_eq!(r.max_stopping_time, range.max_stopping_time);
+        let n = r.max_stopping_time_number.to_u64_digits()[0];
+        assert_eq!(trajectory::stopping_time_u64_fast(n, 3, 10_000, None, true, true), Some(r.max_stopping_time));
 
-    VerifyResult {
-        total_checked,
-        all_converged: failures.is_empty(),
-        max_stopping_time,
-        max_stopping_time_number,
-        failures,
-        gpk_stats,
+        // 空・偶数のみ
+        let r = verify_seeds(&[0, 2, 4], 3, 100, false);
+        assert_eq!(r.total_checked, 0);
+        assert!(r.all_converged);
     }
-}
 
-/// u64 範囲のキャンセル可能な並列検証
-fn verify_range_parallel_u64_cancellable(
-    start: u64,
-    end: u64,
-    x: u64,
-    max_steps: u64,
-    collect_gpk: bool,
-    use_phase1: bool,
-    use_stopping_time: bool,
-    cancel: &AtomicBool,
-    progress_callback: &(impl Fn(u64, u64) + Sync),
-) -> VerifyResult {
-    let start = if start % 2 == 0 { start + 1 } else { start };
-    if start > end {
-        return VerifyResult {
-            total_checked: 0,
-            all_converged: true,
-            max_stopping_time: 0,
-            max_stopping_time_number: BigUint::ZERO,
-            failures: Vec::new(),
-            gpk_stats: GpkStats::new(),
+    /// 同じ範囲を別々に検証したダイジェストが一致し、範囲が違えば変わる
+    #[test]
+    fn test_result_digest_reproducible() {
+        let run = |start: u64, end: u64, max_steps: u64| {
+            verify_range_parallel_cancellable(&BigUint::from(start), &BigUint::from(end), 5, max_steps, true, true, true, &AtomicBool::new(false), |_, _| {})
         };
+        // 5n+1 は発散する軌道があるので failures も並び順も入る
+        let a = run(1, 19_999, 100);
+        let b = run(1, 19_999, 100);
+        assert!(!a.failures.is_empty());
+        assert_eq!(a.failures, b.failures);
+        assert_eq!(a.max_stopping_time_number, b.max_stopping_time_number);
+        assert_eq!(a.result_digest(), b.result_digest());
+
+        let mut shuffled = a.clone();
+        shuffled.failures.reverse();
+        assert_eq!(shuffled.result_digest(), a.result_digest());
+
+        assert_ne!(run(1, 19_997, 100).result_digest(), a.result_digest());
+        assert_ne!(run(1, 19_999, 101).result_digest(), a.result_digest());
+
+        // BigUint 経路も同様
+        let s = (BigUint::one() << 64u32) + 1u32;
+        let e = &s + 20_000u32;
+        let big = || verify_range_parallel_cancellable(&s, &e, 3, 10_000, true, true, true, &AtomicBool::new(false), |_, _| {});
+        assert_eq!(big().result_digest(), big().result_digest());
     }
 
-    let total_odd = (end - start) / 2 + 1;
-    let chunk_size: u64 = 10000;
-    let num_chunks = (total_odd + chunk_size - 1) / chunk_size;
+    /// 下限付きの u64 高速経路が、シードごとの stopping_time_below（パックド経路）と一致する
+    #[test]
+    fn test_inductive_u64_fast_matches_packed() {
+        for (start, end, x, bound, max_steps) in [(5_001u64, 15_001u64, 3u64, 5_001u64, 10_000u64), (5_001, 15_001, 3, 1_000_000, 10_000), (101, 1_001, 5, 101, 200)] {
+            let r = verify_range_inductive(&BigUint::from(start), &BigUint::from(end), x, max_steps, Some(bound), |_, _| {});
+            let b = BigUint::from(bound);
+            let mut gpk = GpkStats::new();
+            let mut best: Option<(u64, u64)> = None;
+            let mut failures = Vec::new();
+            for n in (start..=end).step_by(2) {
+                match trajectory::stopping_time_below(&BigUint::from(n), x, max_steps, &b, Some(&mut gpk)) {
+                    Some(st) => {
+                        if best.is_none_or(|(m, _)| st > m) {
+                            best = Some((st, n));
+                        }
+                    }
+                    None => failures.push(BigUint::from(n)),
+                }
+            }
+            let (max_st, max_n) = best.unwrap_or((0, 0));
+            assert_eq!(r.total_checked, (end - start) / 2 + 1);
+            assert_eq!(r.failures, failures, "x={} bound={}", x, bound);
+            assert_eq!(r.max_stopping_time, max_st, "x={} bound={}", x, bound);
+            if best.is_some() {
+                assert_eq!(r.max_stopping_time_number, BigUint::from(max_n));
+            }
+            assert_eq!(r.gpk_stats.total_steps, gpk.total_steps);
+            assert_eq!(r.gpk_stats.total_g, gpk.total_g);
+            assert_eq!(r.gpk_stats.carry_chain_hist, gpk.carry_chain_hist);
+        }
+    }
 
-    let global_done = AtomicU64::new(0);
-    let global_max_st = AtomicU64::new(0);
-    let global_max_st_n = Mutex::new(start);
-    let global_failures: Mutex<Vec<BigUint>> = Mutex::new(Vec::new());
-    let global_gpk_stats: Mutex<GpkStats> = Mutex::new(GpkStats::new());
+    /// 27 (3n+1): 奇数→奇数で停止時間 37、全停止時間 41、最大の奇数値 3077
+    #[test]
+    fn test_analyze_seed_27() {
+        let n = BigUint::from(27u64);
+        let a = analyze_seed(&n, 3, 1000);
+        assert_eq!(a.total_stopping_time, Some(41));
+        assert_eq!(a.stopping_time, trajectory::stopping_time(&n, 3, 1000));
+        assert_eq!(a.stopping_time, Some(37));
+        assert_eq!(a.max_value, BigUint::from(3077u64));
+        let trace = trajectory::trace_trajectory(&n, 3, 1000);
+        assert_eq!(trace.steps[a.max_value_step as usize - 1].0, a.max_value);
+        assert_eq!(a.stop_reason, StopReason::ReachedOne);
+        assert_eq!(a.gpk_stats.total_steps, 41);
 
-    (0..num_chunks).into_par_iter().for_each(|chunk_idx| {
-        if cancel.load(Ordering::Relaxed) {
-            return;
+        // ステップ上限で打ち切り: 停止時間は分かるが全停止時間は分からない
+        let cut = analyze_seed(&n, 3, 39);
+        assert_eq!(cut.stop_reason, StopReason::MaxSteps);
+        assert_eq!(cut.total_stopping_time, None);
+        assert_eq!(cut.stopping_time, a.stopping_time);
+
+        let one = analyze_seed(&BigUint::one(), 3, 10);
+        assert_eq!((one.stopping_time, one.total_stopping_time, one.max_value_step), (Some(0), Some(0), 0));
+    }
+
+    /// reached_one_count / dropped_below_count: 素朴な追跡で数えた 1 への到達数と一致し、
+    /// 和は total_checked - failures.len() になる
+    #[test]
+    fn test_reached_one_and_dropped_below_counts() {
+        let (start, end, max_steps) = (1u64, 2001u64, 10_000u64);
+        let mut expected_one = 0u64;
+        for seed in (start..=end).step_by(2) {
+            let mut n = seed;
+            loop {
+                if n == 1 {
+                    expected_one += 1;
+                    break;
+                }
+                let xn1 = 3 * n + 1;
+                n = xn1 >> xn1.trailing_zeros();
+                if n < seed && n != 1 {
+                    break;
+                }
+            }
         }
+        // 1, 3, 5, 21, 85, 341, 1365 などが 1 に直接落ちる
+        assert!(expected_one >= 7, "expected_one = {}", expected_one);
 
-        let chunk_start = start + chunk_idx * chunk_size * 2;
-        let chunk_end = std::cmp::min(chunk_start + (chunk_size - 1) * 2, end);
+        let (s, e) = (BigUint::from(start), BigUint::from(end));
+        let cancel = AtomicBool::new(false);
+        let seeds: Vec<u64> = (start..=end).collect();
+        let results = [
+            ("verify_range", verify_range(&s, &e, 3, max_steps, |_, _| {})),
+            ("verify_range_parallel", verify_range_parallel(&s, &e, 3, max_steps, |_, _| {})),
+            ("verify_range_small", verify_range_small(&s, &e, 3, max_steps)),
+            ("verify_range_inductive", verify_range_inductive(&s, &e, 3, max_steps, None, |_, _| {})),
+            ("verify_range_with", verify_range_with(&XnPlusOne(3), &s, &e, max_steps, |_, _| {})),
+            ("verify_range_with_cycles", verify_range_with_cycles(&s, &e, 3, max_steps, &[], |_, _| {})),
+            ("verify_range_with_growth_limit", verify_range_with_growth_limit(&s, &e, 3, max_steps, None, |_, _| {})),
+            ("verify_seeds", verify_seeds(&seeds, 3, max_steps, false)),
+            ("try_verify_range", try_verify_range(&VerifyConfig::new(s.clone(), e.clone(), 3, max_steps)).unwrap()),
+            ("verify_range_spawn", verify_range_spawn(s.clone(), e.clone(), 3, max_steps, false, true, true).join()),
+        ];
+        // どの経路も同じ ChunkAccumulator で集計するので、停止時間・d ヒストグラムの集計も一致する
+        let base = &results[0].1;
+        for (name, r) in &results {
+            assert_eq!(r.reached_one_count, expected_one, "{}", name);
+            assert_eq!(r.reached_one_count + r.dropped_below_count, r.total_checked - r.failures.len() as u64, "{}", name);
+            assert_eq!(r.total_checked, base.total_checked, "{}", name);
+            assert_eq!((r.max_stopping_time, &r.max_stopping_time_number), (base.max_stopping_time, &base.max_stopping_time_number), "{}", name);
+            assert_eq!(r.dropped_below_count, base.dropped_below_count, "{}", name);
+            // verify_range_with だけはシード 1 も 1→1 の1ステップを進めて数える
+            if *name != "verify_range_with" {
+                assert_eq!(r.d_hist, base.d_hist, "{}", name);
+            }
+            assert_eq!(r.max_intermediate_bits, base.max_intermediate_bits, "{}", name);
+        }
 
-        let mut local_max_st = 0u64;
-        let mut local_max_st_n = chunk_start;
-        let mut local_failures: Vec<BigUint> = Vec::new();
-        let mut unreported = 0u64;
-        let mut local_gpk = GpkStats::new();
+        // 停止時間法を使わなければ全シードが 1 まで追跡される
+        let r = verify_range_parallel_cancellable(&s, &e, 3, max_steps, false, true, false, &cancel, |_, _| {});
+        assert_eq!((r.reached_one_count, r.dropped_below_count), (r.total_checked, 0));
 
-        let mut n = chunk_start;
-        while n <= chunk_end {
-            if cancel.load(Ordering::Relaxed) {
-                break;
+        // 5n+1 は発散する数が failures に入り、和はその分だけ減る
+        let r = verify_range_parallel(&BigUint::from(3u64), &BigUint::from(199u64), 5, 600, |_, _| {});
+        assert!(!r.failures.is_empty());
+        assert_eq!(r.reached_one_count + r.dropped_below_count, r.total_checked - r.failures.len() as u64);
+        assert_eq!(VerifyResult::empty().reached_one_count + VerifyResult::empty().dropped_below_count, 0);
+
+        // サイクルに捕捉された数はどちらにも数えない
+        let cycles = [vec![13, 33, 83], vec![17, 43, 27]];
+        let r = verify_range_with_cycles(&BigUint::from(3u64), &BigUint::from(199u64), 5, 600, &cycles, |_, _| {});
+        assert!(r.cycle_hits > 0);
+        assert_eq!(r.reached_one_count + r.dropped_below_count + r.cycle_hits + r.failures.len() as u64, r.total_checked);
+    }
+
+    /// force_single_thread: u64 / BigUint 範囲とも、並列経路と集計結果が一致する
+    #[test]
+    fn test_force_single_thread_matches_parallel() {
+        let big = (BigUint::one() << 64u32) + 1u32;
+        let cases = [
+            (BigUint::from(1u64), BigUint::from(30_001u64), 3u64, 10_000u64),
+            (BigUint::from(3u64), BigUint::from(2_001u64), 5, 300),
+            (big.clone(), &big + 4_000u32, 3, 10_000),
+        ];
+        for (start, end, x, max_steps) in cases {
+            let parallel = try_verify_range(&VerifyConfig::new(start.clone(), end.clone(), x, max_steps)).unwrap();
+            let config = VerifyConfig { force_single_thread: true, ..VerifyConfig::new(start.clone(), end, x, max_steps) };
+            let single = try_verify_range(&config).unwrap();
+            assert_eq!(single.strategy, Strategy::SingleThread);
+            assert_ne!(parallel.strategy, Strategy::SingleThread);
+            assert_eq!(single.result_digest(), parallel.result_digest(), "start={} x={}", start, x);
+            assert_eq!(single.failures, parallel.failures);
+            assert_eq!(single.d_hist, parallel.d_hist);
+            assert_eq!(single.max_intermediate_bits, parallel.max_intermediate_bits);
+            assert_eq!(
+                (single.reached_one_count, single.dropped_below_count),
+                (parallel.reached_one_count, parallel.dropped_below_count)
+            );
+        }
+    }
+
+    /// verify_range_parallel_rich: done と current_max_st は単調非減少で、最後の報告は最終結果と一致する
+    #[test]
+    fn test_verify_range_parallel_rich_monotonic() {
+        let big = (BigUint::one() << 64u32) + 1u32;
+        for (start, end) in [(BigUint::from(1u64), BigUint::from(99_999u64)), (big.clone(), &big + 3_000u32)] {
+            let infos: Mutex<Vec<ProgressInfo>> = Mutex::new(Vec::new());
+            let r = verify_range_parallel_rich(&start, &end, 3, 10_000, 500, |info| infos.lock().unwrap().push(info.clone()));
+            let infos = infos.into_inner().unwrap();
+            assert!(infos.len() > 2, "start={}", start);
+            for w in infos.windows(2) {
+                assert!(w[0].current_max_st <= w[1].current_max_st, "start={}", start);
+                assert!(w[0].done <= w[1].done, "start={}", start);
             }
-            let gpk_arg = if collect_gpk { Some(&mut local_gpk) } else { None };
-            match trajectory::stopping_time_u64_fast(n, x, max_steps, gpk_arg, use_phase1, use_stopping_time) {
-                Some(st) => {
-                    if st > local_max_st {
-                        local_max_st = st;
-                        local_max_st_n = n;
-                    }
+            let last = infos.last().unwrap();
+            assert_eq!((last.done, last.total), (r.total_checked, r.total_checked));
+            assert_eq!((last.current_max_st, &last.current_max_st_seed), (r.max_stopping_time, &r.max_stopping_time_number));
+            assert!(last.rate > 0.0);
+            assert!(infos.iter().any(|i| i.current_max_st > 0));
+        }
+    }
+
+    /// odd_d_steps + even_d_steps は全ステップ数（GPK を集めた経路では gpk_stats.total_steps）に等しい
+    #[test]
+    fn test_odd_even_d_steps() {
+        let (s, e) = (BigUint::from(1u64), BigUint::from(20_001u64));
+        let results = [
+            verify_range(&s, &e, 3, 10_000, |_, _| {}),
+            verify_range_parallel(&s, &e, 3, 10_000, |_, _| {}),
+            verify_range_inductive(&s, &e, 3, 10_000, None, |_, _| {}),
+            try_verify_range(&VerifyConfig::new(s.clone(), e.clone(), 3, 10_000)).unwrap(),
+        ];
+        for r in &results {
+            assert!(r.gpk_stats.total_steps > 0);
+            assert_eq!(r.odd_d_steps() + r.even_d_steps(), r.gpk_stats.total_steps, "{:?}", r.strategy);
+            assert_eq!(r.odd_d_steps() + r.even_d_steps(), r.d_hist.iter().sum::<u64>());
+            assert_eq!(r.odd_d_steps(), results[0].odd_d_steps());
+            // 3n+1 では d=1 のステップだけで全体の約半分になる
+            assert!(r.odd_d_steps() > r.even_d_steps(), "{:?}", r.strategy);
+        }
+        // GPK を集めない経路でも d_hist から数える
+        let small = verify_range_small(&s, &e, 3, 10_000);
+        assert_eq!((small.odd_d_steps(), small.even_d_steps()), (results[0].odd_d_steps(), results[0].even_d_steps()));
+        assert_eq!((VerifyResult::empty().odd_d_steps(), VerifyResult::empty().even_d_steps()), (0, 0));
+    }
+
+    /// NDJSON の1行（verify_range_ndjson が書くフラットなオブジェクト）を (キー, 値) に分ける。
+    /// 値は数値・文字列ならそのまま、文字列配列なら要素を "," で連結した文字列
+    fn parse_ndjson_line(line: &str) -> std::collections::HashMap<String, String> {
+        let body = line.strip_prefix('{').and_then(|l| l.strip_suffix('}')).expect("object");
+        let mut fields = std::collections::HashMap::new();
+        let mut rest = body;
+        while !rest.is_empty() {
+            let rest_key = rest.strip_prefix('"').expect("key");
+            let (key, after) = rest_key.split_once("\":").expect("key end");
+            let (value, next) = if let Some(arr) = after.strip_prefix('[') {
+                let (items, next) = arr.split_once(']').expect("array end");
+                (items.replace('"', ""), next)
+            } else if let Some(text) = after.strip_prefix('"') {
+                let (text, next) = text.split_once('"').expect("string end");
+                (text.to_string(), next)
+            } else {
+                let (number, next) = after.split_once(',').unwrap_or((after, ""));
+                (number.to_string(), next)
+            };
+            fields.insert(key.to_string(), value);
+            rest = next.strip_prefix(',').unwrap_or(next);
+        }
+        fields
+    }
+
+    #[test]
+    fn test_verify_range_ndjson() {
+        for (x, end, max_steps) in [(3u64, 100_001u64, 10_000u64), (5, 20_001, 100)] {
+            let start = BigUint::one();
+            let end = BigUint::from(end);
+            let mut out = Vec::new();
+            let r = verify_range_ndjson(&start, &end, x, max_steps, &mut out).unwrap();
+
+            let text = String::from_utf8(out).unwrap();
+            let mut checked = 0u64;
+            let mut best: Option<(u64, BigUint)> = None;
+            let mut failures: Vec<BigUint> = Vec::new();
+            let mut gpk = [0u64; 5];
+            let mut lines = 0;
+            for line in text.lines() {
+                let f = parse_ndjson_line(line);
+                lines += 1;
+                checked += f["checked"].parse::<u64>().unwrap();
+                let st: u64 = f["max_stopping_time"].parse().unwrap();
+                let st_n: BigUint = f["max_stopping_time_number"].parse().unwrap();
+                if best.as_ref().is_none_or(|(m, mn)| st > *m || (st == *m && st_n < *mn)) {
+                    best = Some((st, st_n));
                 }
-                None => {
-                    local_failures.push(BigUint::from(n));
+                failures.extend(f["failures"].split(',').filter(|v| !v.is_empty()).map(|v| v.parse::<BigUint>().unwrap()));
+                for (acc, key) in gpk.iter_mut().zip(["total_g", "total_p", "total_k", "total_pairs", "total_steps"]) {
+                    *acc += f[key].parse::<u64>().unwrap();
                 }
+                let (cs, ce): (BigUint, BigUint) = (f["chunk_start"].parse().unwrap(), f["chunk_end"].parse().unwrap());
+                assert_eq!(&ce - &cs, BigUint::from(2 * (f["checked"].parse::<u64>().unwrap() - 1)));
             }
-            unreported += 1;
-            n += 2;
+            failures.sort();
+
+            assert_eq!(r.strategy, Strategy::ParallelU64);
+            assert_eq!(lines, r.total_checked.div_ceil(10_000));
+            assert_eq!(checked, r.total_checked);
+            assert_eq!(best, Some((r.max_stopping_time, r.max_stopping_time_number.clone())));
+            assert_eq!(failures, r.failures);
+            let g = &r.gpk_stats;
+            assert_eq!(gpk, [g.total_g, g.total_p, g.total_k, g.total_pairs, g.total_steps]);
+
+            let expected = verify_range_parallel(&start, &end, x, max_steps, |_, _| {});
+            assert_eq!(r.total_checked, expected.total_checked);
+            assert_eq!(r.max_stopping_time, expected.max_stopping_time);
+            assert_eq!(r.failures, expected.failures);
+            assert_eq!(r.gpk_stats.total_steps, expected.gpk_stats.total_steps);
+            assert_eq!(x == 5, !r.failures.is_empty());
+        }
+
+        // u64 を超える範囲は BigUint のチャンク並列経路と同じ結果・同じ実行経路になる
+        let start = (BigUint::one() << 64u32) + 1u32;
+        let end = &start + 2_000u32;
+        let r = verify_range_ndjson(&start, &end, 3, 10_000, std::io::sink()).unwrap();
+        let expected = try_verify_range(&VerifyConfig::new(start, end, 3, 10_000)).unwrap();
+        assert_eq!(r.strategy, Strategy::ParallelBigUint);
+        assert_eq!(r.strategy, expected.strategy);
+        assert_eq!(r.result_digest(), expected.result_digest());
+    }
+
+    #[test]
+    fn test_verify_range_batches_merge() {
+        for (x, start, end, max_steps) in [(3u64, 1u64, 30_000u64, 10_000u64), (5, 2, 10_001, 100)] {
+            let (start, end) = (BigUint::from(start), BigUint::from(end));
+            let full = verify_range(&start, &end, x, max_steps, |_, _| {});
+            let batches: Vec<VerifyResult> = verify_range_batches(&start, &end, x, max_steps, 777).collect();
+            assert_eq!(batches.len() as u64, full.total_checked.div_ceil(777));
+            assert!(batches.iter().all(|b| b.total_checked <= 777));
+
+            let mut merged = VerifyResult::empty();
+            for b in &batches {
+                merged.merge(b);
+            }
+            assert_eq!(merged.result_digest(), full.result_digest());
+            assert_eq!(merged.failures, full.failures);
+            assert_eq!(merged.all_converged, full.all_converged);
+            assert_eq!(merged.d_hist, full.d_hist);
+            assert_eq!((merged.reached_one_count, merged.dropped_below_count), (full.reached_one_count, full.dropped_below_count));
+            assert_eq!((merged.odd_d_steps(), merged.even_d_steps()), (full.odd_d_steps(), full.even_d_steps()));
+            assert_eq!(merged.max_intermediate_bits, full.max_intermediate_bits);
 
-            // チャンク内でも定期的に進捗報告
-            if unreported >= 100 {
-                let done = global_done.fetch_add(unreported, Ordering::Relaxed) + unreported;
-                progress_callback(done, total_odd);
-                unreported = 0;
+            // 途中から再開しても同じ区切りになる
+            let resumed: Vec<VerifyResult> = verify_range_batches(&(&start + 2u32 * 777u32 * 3u32), &end, x, max_steps, 777).collect();
+            assert_eq!(resumed.len(), batches.len() - 3);
+            for (a, b) in resumed.iter().zip(&batches[3..]) {
+                assert_eq!(a.result_digest(), b.result_digest());
             }
         }
+    }
+
+    #[test]
+    fn test_progress_total_above_u64() {
+        // 2^64 を超える値の範囲: 進捗は単調で、最後に 100% に達する
+        let start = (BigUint::one() << 70u32) + 1u32;
+        let end = &start + 19_998u32;
+        let reports = Mutex::new(Vec::new());
+        let r = verify_range(&start, &end, 3, 10_000, |done, total| reports.lock().unwrap().push((done, total)));
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(r.total_checked, 10_000);
+        assert!(reports.iter().all(|&(_, total)| total == 10_000));
+        let pct: Vec<f64> = reports.iter().map(|&(done, total)| done as f64 / total as f64 * 100.0).collect();
+        assert!(pct.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*pct.last().unwrap(), 100.0);
+        let total = odd_count(&start, &end);
+        assert_eq!(total, BigUint::from(10_000u64));
+        let fractions: Vec<f64> = reports.iter().map(|&(done, _)| progress_fraction(done, &total)).collect();
+        assert!(fractions.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*fractions.last().unwrap(), 1.0);
 
-        // 残りをグローバルに反映
-        if unreported > 0 {
-            let done = global_done.fetch_add(unreported, Ordering::Relaxed) + unreported;
-            progress_callback(done, total_odd);
+        // 奇数が 2^64 個を超える範囲: 正確な総数で割合を出す（u64 の総数は飽和し、完了数が総数を超えない）
+        let wide = odd_count(&BigUint::from(2u64), &(BigUint::one() << 70u32));
+        assert_eq!(wide, (BigUint::one() << 69u32) - 1u32);
+        assert_eq!(progress_fraction(1 << 63, &wide), 1.0 / 64.0);
+        assert_eq!(progress_fraction(1 << 63, &(BigUint::one() << 63u32)), 1.0);
+        assert_eq!(progress_fraction(5, &BigUint::ZERO), 0.0);
+        assert_eq!(odd_count(&BigUint::from(2u64), &BigUint::from(9u64)), BigUint::from(4u64));
+        assert_eq!(odd_count(&BigUint::from(3u64), &BigUint::from(1u64)), BigUint::ZERO);
+        assert_eq!(odd_count_saturating(&BigUint::from(3u64), &BigUint::from(9u64)), 4);
+        assert_eq!(odd_count_saturating(&BigUint::from(3u64), &BigUint::from(1u64)), 0);
+        assert_eq!(odd_count_saturating(&BigUint::one(), &(BigUint::one() << 65u32)), u64::MAX);
+        assert_eq!(odd_count_saturating(&BigUint::one(), &((BigUint::one() << 65u32) - 3u32)), u64::MAX);
+        assert_eq!(odd_count_saturating(&BigUint::one(), &((BigUint::one() << 65u32) - 5u32)), u64::MAX - 1);
+        let start = (BigUint::one() << 80u32) + 1u32;
+        let end = &start + (BigUint::one() << 70u32);
+        let handle = verify_range_spawn(start, end, 3, 10_000, false, true, true);
+        while handle.snapshot().done < 100 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
         }
+        let snap = handle.snapshot();
+        handle.cancel();
+        assert_eq!(snap.total, u64::MAX);
+        assert_eq!(snap.total_odd, (BigUint::one() << 69u32) + 1u32);
+        // 割合は飽和した総数ではなく正確な総数 2^69 + 1 で割る
+        let expected = snap.done as f64 / 2f64.powi(69);
+        assert!((snap.fraction_done() - expected).abs() <= expected * 1e-9);
+        let r = handle.join();
+        assert!(r.total_checked >= 100);
+    }
+
+    #[test]
+    fn test_longest_chain_seed() {
+        let start = BigUint::one();
+        let end = BigUint::from(20_001u64);
+        let r = verify_range(&start, &end, 3, 10_000, |_, _| {});
+        assert!(r.longest_chain_len() > 0);
+        assert_eq!(r.longest_chain_len(), r.gpk_stats.max_carry_chain);
+        let seed = r.longest_chain_seed().cloned().expect("最長キャリー連鎖のシードが記録される");
+        assert!(seed >= start && seed <= end && seed.bit(0));
 
-        let prev_max = global_max_st.load(Ordering::Relaxed);
-        if local_max_st > prev_max {
-            global_max_st.fetch_max(local_max_st, Ordering::Relaxed);
-            let mut guard = global_max_st_n.lock().unwrap();
-            if local_max_st >= global_max_st.load(Ordering::Relaxed) {
-                *guard = local_max_st_n;
+        // シードの軌道を停止まで再走査すると、報告された連鎖長がちょうど現れる
+        let mut n = PairNumber::from_biguint(&seed);
+        let mut longest = 0;
+        loop {
+            let step = crate::scan::collatz_step(&n, 3);
+            longest = longest.max(step.gpk.max_carry_chain);
+            n = step.next;
+            if n.to_biguint() < seed {
+                break;
             }
         }
+        assert_eq!(longest, r.longest_chain_len());
+
+        let p = verify_range_parallel(&start, &end, 3, 10_000, |_, _| {});
+        assert_eq!(p.longest_chain_len(), r.longest_chain_len());
+        assert_eq!(p.longest_chain_seed(), r.longest_chain_seed());
+    }
+
+    #[test]
+    fn test_verify_range_parallel_balanced() {
+        // 作業単位は昇順に隙間なく範囲を覆い、重いシード（27 は 96 ステップ）は軽い区間より短い
+        let units = cost_balanced_units(1, 20_001, 3, 10_000);
+        assert_eq!(units.first().unwrap().0, 1);
+        assert_eq!(units.last().unwrap().1, 20_001);
+        assert!(units.windows(2).all(|w| w[0].1 + 2 == w[1].0));
+        assert!(estimate_seed_cost(27, 3, 10_000) > estimate_seed_cost(29, 3, 10_000));
+        assert_eq!(estimate_seed_cost(7, 5, 50), 50);
 
-        if !local_failures.is_empty() {
-            global_failures.lock().unwrap().extend(local_failures);
+        for (start, end) in [(1u64, 60_001u64), (2, 2), (9_780_600_000, 9_780_700_000)] {
+            let (start, end) = (BigUint::from(start), BigUint::from(end));
+            let a = verify_range_parallel(&start, &end, 3, 10_000, |_, _| {});
+            let b = verify_range_parallel_balanced(&start, &end, 3, 10_000, |_, _| {});
+            assert_eq!(a.total_checked, b.total_checked);
+            assert_eq!(a.result_digest(), b.result_digest());
+            assert_eq!(a.max_stopping_time_number, b.max_stopping_time_number);
+            assert_eq!(a.longest_chain_seed(), b.longest_chain_seed());
         }
+        let a = verify_range_parallel(&BigUint::from(1u64), &BigUint::from(2_001u64), 5, 100, |_, _| {});
+        let b = verify_range_parallel_balanced(&BigUint::from(1u64), &BigUint::from(2_001u64), 5, 100, |_, _| {});
+        assert_eq!(a.result_digest(), b.result_digest());
+        assert_eq!(a.failures, b.failures);
+    }
 
-        global_gpk_stats.lock().unwrap().merge(&local_gpk);
-    });
+    #[test]
+    fn test_max_steps_auto() {
+        assert_eq!(MaxSteps::Auto.for_bits(3), AUTO_MIN_STEPS);
+        assert_eq!(MaxSteps::Auto.for_bits(40), 40 * AUTO_STEPS_PER_BIT);
+        assert_eq!(MaxSteps::Fixed(7).for_bits(100), 7);
+        assert_eq!(MaxSteps::from(7u64), MaxSteps::Fixed(7));
+        assert_eq!("auto".parse::<MaxSteps>(), Ok(MaxSteps::Auto));
+        assert_eq!(" AUTO ".parse::<MaxSteps>(), Ok(MaxSteps::Auto));
+        assert_eq!("500".parse::<MaxSteps>(), Ok(MaxSteps::Fixed(500)));
+        assert!("-1".parse::<MaxSteps>().is_err());
+        for m in [MaxSteps::Auto, MaxSteps::Fixed(42)] {
+            assert_eq!(m.to_string().parse::<MaxSteps>(), Ok(m));
+        }
 
-    let total_checked = global_done.load(Ordering::Relaxed);
-    let max_stopping_time = global_max_st.load(Ordering::Relaxed);
-    let max_stopping_time_number = BigUint::from(*global_max_st_n.lock().unwrap());
-    let failures = global_failures.into_inner().unwrap();
-    let gpk_stats = global_gpk_stats.into_inner().unwrap();
-
-    VerifyResult {
-        total_checked,
-        all_converged: failures.is_empty(),
-        max_stopping_time,
-        max_stopping_time_number,
-        failures,
-        gpk_stats,
+        // 1 まで追跡すると、固定の小さい上限では 27（41 ステップ）や 2^40 付近・2^70 付近の
+        // シードが failures になるが、Auto ではビット長に応じた上限で全て収束する
+        let one = BigUint::one();
+        for (start, end, fixed) in [
+            (BigUint::from(1u64), BigUint::from(2_001u64), 30u64),
+            ((&one << 40u32) + 1u32, (&one << 40u32) + 2_001u32, 100),
+            ((&one << 70u32) + 1u32, (&one << 70u32) + 201u32, 100),
+        ] {
+            let config = |max_steps: MaxSteps| VerifyConfig {
+                use_stopping_time: false,
+                ..VerifyConfig::new(start.clone(), end.clone(), 3, max_steps)
+            };
+            let fixed = try_verify_range(&config(MaxSteps::Fixed(fixed))).unwrap();
+            assert!(!fixed.failures.is_empty(), "start={start}");
+            let auto = try_verify_range(&config(MaxSteps::Auto)).unwrap();
+            assert!(auto.all_converged, "start={start}");
+            assert_eq!(auto.total_checked, fixed.total_checked);
+            assert_eq!(auto.reached_one_count, auto.total_checked);
+            let single = try_verify_range(&VerifyConfig { force_single_thread: true, ..config(MaxSteps::Auto) }).unwrap();
+            assert_eq!(single.result_digest(), auto.result_digest());
+        }
+        assert!(try_verify_range(&VerifyConfig::new(BigUint::from(1u64), BigUint::from(99u64), 3, 0)).is_err());
     }
 }